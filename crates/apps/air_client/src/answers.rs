@@ -0,0 +1,49 @@
+//! Answers sent back by the server on its uni stream.
+
+use crate::Metrics;
+use lib_codec::stream::Decoder;
+use lib_models::Answer;
+use lib_quic::quinn;
+use tracing::{debug, warn};
+
+/// Handles the answers of the server until the connection closes, clipboard
+/// answers are forwarded to `clipboard_tx`.
+pub async fn listen(
+    connection: quinn::Connection,
+    clipboard_tx: flume::Sender<Answer>,
+    metrics: Metrics,
+) {
+    let mut answers = match connection.accept_uni().await {
+        Ok(stream) => Decoder::new(stream),
+        Err(e) => {
+            warn!("Answer stream not opened: {e}");
+            return;
+        }
+    };
+
+    loop {
+        match answers.recv::<Answer>().await {
+            Ok(Some(Answer::CommandFailed { command, error })) => {
+                warn!("Server failed to apply {command:?}: {error:?}")
+            }
+            Ok(Some(Answer::CommandDenied { command, reason })) => {
+                warn!("Server denied {command:?}: {reason:?}")
+            }
+            Ok(Some(answer @ (Answer::ClipboardChunk(_) | Answer::ClipboardFormats(_)))) => {
+                if clipboard_tx.send_async(answer).await.is_err() {
+                    debug!("Clipboard sync is off, answer dropped");
+                }
+            }
+            Ok(Some(Answer::Pong {
+                sent_at,
+                received_at,
+                ..
+            })) => metrics.pong(sent_at, received_at),
+            Ok(None) => break,
+            Err(e) => {
+                warn!("Answer stream closed: {e}");
+                break;
+            }
+        }
+    }
+}
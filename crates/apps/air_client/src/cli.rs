@@ -0,0 +1,33 @@
+//! Command line of the client.
+
+use crate::Result;
+use clap::Parser;
+use lib_config::{ConfigArgs, Settings};
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Server address, overrides `ADDRESS`.
+    #[arg(long)]
+    pub address: Option<String>,
+    /// Size of the virtual display as `WIDTHxHEIGHT`, overrides `RESOLUTION`.
+    #[arg(long)]
+    pub resolution: Option<String>,
+}
+
+impl Cli {
+    /// Settings of the config file and profile, under the flags.
+    pub fn settings(&self) -> Result<Settings> {
+        let mut settings = self.config.settings(env!("CARGO_PKG_NAME"))?;
+        if let Some(address) = &self.address {
+            settings = settings.with_override("ADDRESS", address.as_str());
+        }
+        if let Some(resolution) = &self.resolution {
+            settings = settings.with_override("RESOLUTION", resolution.as_str());
+        }
+
+        Ok(settings)
+    }
+}
@@ -0,0 +1,52 @@
+//! Keeps the local clipboard in sync with the server one.
+
+use crate::HandlerCommand;
+use lib_models::{clipboard::ClipboardSync, Answer, Command};
+use std::time::Duration;
+use tracing::{debug, warn};
+
+/// Sends local clipboard changes through the event handler and applies the
+/// clipboard answers of the server received through `remote_rx`.
+pub async fn sync(
+    command_tx: flume::Sender<HandlerCommand>,
+    mut sync: ClipboardSync,
+    remote_rx: flume::Receiver<Answer>,
+    poll: Duration,
+) {
+    let mut interval = tokio::time::interval(poll);
+
+    loop {
+        tokio::select! {
+            _ = interval.tick() => {
+                let chunks = match sync.poll_chunks() {
+                    Ok(chunks) => chunks,
+                    Err(e) => {
+                        warn!("Clipboard not sent: {e}");
+                        continue;
+                    }
+                };
+
+                for chunk in chunks {
+                    let command = HandlerCommand::Command(Command::ClipboardChunk(chunk));
+                    if command_tx.send_async(command).await.is_err() {
+                        return;
+                    }
+                }
+            }
+            answer = remote_rx.recv_async() => {
+                let Ok(answer) = answer else {
+                    break;
+                };
+
+                match answer {
+                    Answer::ClipboardChunk(chunk) => match sync.receive(chunk) {
+                        Ok(applied) => debug!("Clipboard chunk from server, applied: {applied}"),
+                        Err(e) => warn!("Clipboard from server ignored: {e}"),
+                    },
+                    Answer::ClipboardFormats(formats) => sync.set_peer_formats(formats),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -1,42 +1,315 @@
 //! Crate config
 
-use crate::error::{Error, Result};
-use std::sync::OnceLock;
+use crate::{
+    error::{Error, Result},
+    OfflinePolicy,
+};
+use lib_config::{Apply, Settings};
+use lib_models::{
+    clipboard::{ClipboardBackend, DEFAULT_MAX_SIZE},
+    Key,
+};
+use std::{
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-static INSTANCE: OnceLock<Config> = OnceLock::new();
+/// Replaced configs live on while a caller still holds them.
+static INSTANCE: RwLock<Option<Arc<Config>>> = RwLock::new(None);
 
-pub fn config() -> &'static Config {
-    INSTANCE.get_or_init(|| {
-        Config::load_from_env()
-            .unwrap_or_else(|ex| panic!("FATAL - WHOLE LOADING CONF - Cause: {ex:?}"))
-    })
+/// Config given to [`Config::init_from`] or the last [`Config::reload`], or
+/// the one of the environment.
+pub fn config() -> Arc<Config> {
+    if let Some(config) = INSTANCE.read().unwrap().as_ref() {
+        return config.clone();
+    }
+
+    INSTANCE
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| {
+            let config = Config::load(&Settings::from_env())
+                .unwrap_or_else(|ex| panic!("FATAL - WHOLE LOADING CONF - Cause: {ex:?}"));
+            Arc::new(config)
+        })
+        .clone()
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Config {
     pub ADDRESS: std::net::SocketAddr,
     pub WIDTH: u32,
     pub HEIGHT: u32,
+    /// Server display the virtual output stands for.
+    pub SERVER_DISPLAY: u32,
+    pub CLIPBOARD: ClipboardBackend,
+    pub CLIPBOARD_MAX_SIZE: usize,
+    pub CLIPBOARD_POLL: Duration,
+    /// Keys held together to switch between absolute and relative pointer.
+    pub POINTER_LOCK_HOTKEY: Vec<Key>,
+    /// How long input is collected in one batch, zero sends every pointer
+    /// frame as it comes.
+    pub BATCH_INTERVAL: Duration,
+    pub PING_INTERVAL: Option<Duration>,
+    /// Serves the metrics in the Prometheus text format when set.
+    pub METRICS_ADDRESS: Option<std::net::SocketAddr>,
+    pub METRICS_LOG_INTERVAL: Option<Duration>,
+    /// First delay before reconnecting, doubled on every failure.
+    pub RECONNECT_MIN: Duration,
+    pub RECONNECT_MAX: Duration,
+    pub OFFLINE_POLICY: OfflinePolicy,
+    /// Certificate and key this device authenticates with, created on the
+    /// first run.
+    pub CLIENT_CERT: PathBuf,
+    pub CLIENT_KEY: PathBuf,
+    /// Certificates the server must chain to, the `ca.pem` of a bundle
+    /// exported by `air_server certs export`.
+    pub SERVER_CA: PathBuf,
+    /// Name the server certificate is checked against.
+    pub SERVER_NAME: String,
 }
 
 impl Config {
-    fn load_from_env() -> Result<Self> {
-        let resolution = grapple_utils::envs::get("RESOLUTION").unwrap_or("1920x1080".to_string());
-        let Some((width_str, height_str)) = resolution.split_once('x') else {
-            panic!("'RESOLUTION' should be provided in format 'WIDTHxHEIGHT'")
+    pub fn load(settings: &Settings) -> Result<Self> {
+        let resolution = settings
+            .get("RESOLUTION")
+            .unwrap_or("1920x1080".to_string());
+        let (width, height) = parse_resolution(&resolution)?;
+
+        let clipboard = match settings.get("CLIPBOARD") {
+            Some(clipboard) => clipboard.parse()?,
+            None => ClipboardBackend::default(),
+        };
+
+        let offline_policy = match settings.get("OFFLINE_POLICY") {
+            Some(policy) => policy.parse().map_err(Error::OfflinePolicyUnknown)?,
+            None => OfflinePolicy::default(),
         };
 
+        let hotkey = settings
+            .get("POINTER_LOCK_HOTKEY")
+            .unwrap_or("RightControl+RightShift".to_string());
+
         Ok(Self {
-            ADDRESS: grapple_utils::envs::get_parse("ADDRESS").unwrap(),
-            WIDTH: width_str.parse().expect("'WIDTH' should be a u32 number"),
-            HEIGHT: height_str.parse().expect("'HEIGHT' should be a u32 number"),
+            ADDRESS: settings
+                .get_parse("ADDRESS")?
+                .ok_or(Error::ConfigMissing("ADDRESS"))?,
+            WIDTH: width,
+            HEIGHT: height,
+            SERVER_DISPLAY: settings.get_parse("SERVER_DISPLAY")?.unwrap_or(0),
+            CLIPBOARD: clipboard,
+            CLIPBOARD_MAX_SIZE: settings
+                .get_parse("CLIPBOARD_MAX_SIZE")?
+                .unwrap_or(DEFAULT_MAX_SIZE),
+            CLIPBOARD_POLL: Duration::from_millis(
+                settings.get_parse("CLIPBOARD_POLL_MS")?.unwrap_or(500),
+            ),
+            POINTER_LOCK_HOTKEY: parse_hotkey(&hotkey)?,
+            BATCH_INTERVAL: Duration::from_millis(
+                settings.get_parse("BATCH_INTERVAL_MS")?.unwrap_or(0),
+            ),
+            // 0 disables them.
+            PING_INTERVAL: Some(settings.get_parse("PING_INTERVAL_MS")?.unwrap_or(1000))
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            METRICS_ADDRESS: settings.get_parse("METRICS_ADDRESS")?,
+            METRICS_LOG_INTERVAL: Some(settings.get_parse("METRICS_LOG_INTERVAL_S")?.unwrap_or(60))
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+            RECONNECT_MIN: Duration::from_millis(
+                settings.get_parse("RECONNECT_MIN_MS")?.unwrap_or(250),
+            ),
+            RECONNECT_MAX: Duration::from_millis(
+                settings.get_parse("RECONNECT_MAX_MS")?.unwrap_or(30_000),
+            ),
+            OFFLINE_POLICY: offline_policy,
+            CLIENT_CERT: settings
+                .get("CLIENT_CERT")
+                .unwrap_or("./certs/client-cert.pem".to_string())
+                .into(),
+            CLIENT_KEY: settings
+                .get("CLIENT_KEY")
+                .unwrap_or("./certs/client-key.pem".to_string())
+                .into(),
+            SERVER_CA: settings
+                .get("SERVER_CA")
+                .unwrap_or("./certs/ca.pem".to_string())
+                .into(),
+            SERVER_NAME: settings
+                .get("SERVER_NAME")
+                .unwrap_or("localhost".to_string()),
         })
     }
 
     pub fn init_from(cfg: Self) -> Result<()> {
-        INSTANCE
-            .set(cfg)
-            .map_err(|_| Error::ConfigAlreadyInitialized)
+        let mut instance = INSTANCE.write().unwrap();
+        if instance.is_some() {
+            return Err(Error::ConfigAlreadyInitialized);
+        }
+        *instance = Some(Arc::new(cfg));
+
+        Ok(())
+    }
+
+    /// Makes `cfg` the config, returns the settings it changes.
+    pub fn reload(cfg: Self) -> Vec<(&'static str, Apply)> {
+        let changes = config().changes(&cfg);
+        *INSTANCE.write().unwrap() = Some(Arc::new(cfg));
+
+        changes
+    }
+
+    /// Settings differing in `new`, with when they take effect. The display,
+    /// the pointer and the batching follow right away, the server address
+    /// on the next connection.
+    pub fn changes(&self, new: &Self) -> Vec<(&'static str, Apply)> {
+        let mut changes = Vec::new();
+        macro_rules! compare {
+            ($apply:expr => $($field:ident),+) => {
+                $(if self.$field != new.$field {
+                    changes.push((stringify!($field), $apply));
+                })+
+            };
+        }
+
+        compare!(Apply::Now =>
+            WIDTH, HEIGHT, SERVER_DISPLAY, POINTER_LOCK_HOTKEY, BATCH_INTERVAL, OFFLINE_POLICY
+        );
+        compare!(Apply::NextConnection => ADDRESS, SERVER_NAME);
+        compare!(Apply::Restart =>
+            CLIPBOARD, CLIPBOARD_MAX_SIZE, CLIPBOARD_POLL, PING_INTERVAL, METRICS_ADDRESS,
+            METRICS_LOG_INTERVAL, RECONNECT_MIN, RECONNECT_MAX, CLIENT_CERT, CLIENT_KEY, SERVER_CA
+        );
+
+        changes
     }
 }
+
+/// Size of the virtual display written as `1920x1080`.
+fn parse_resolution(text: &str) -> Result<(u32, u32)> {
+    text.split_once('x')
+        .and_then(|(width, height)| Some((width.trim().parse().ok()?, height.trim().parse().ok()?)))
+        .filter(|(width, height)| *width > 0 && *height > 0)
+        .ok_or(Error::ConfigWrongFormat("RESOLUTION"))
+}
+
+/// Keys of a hotkey written as `LeftControl+LeftAlt+L`.
+fn parse_hotkey(text: &str) -> Result<Vec<Key>> {
+    text.split('+')
+        .map(|name| {
+            let name = name.trim();
+            Key::from_name(name).ok_or_else(|| Error::HotkeyKeyUnknown(name.to_string()))
+        })
+        .collect()
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_parse_hotkey() -> Result<()> {
+        let fx_valid = [
+            (
+                "RightControl+RightShift",
+                vec![Key::RightControl, Key::RightShift],
+            ),
+            (
+                " LeftControl + LeftAlt + L ",
+                vec![Key::LeftControl, Key::LeftAlt, Key::L],
+            ),
+            ("ScrollLock", vec![Key::ScrollLock]),
+        ];
+        for (text, keys) in fx_valid {
+            assert_eq!(parse_hotkey(text)?, keys, "{text:?}");
+        }
+
+        let fx_invalid = [
+            ("LeftControl+Foo", "Foo"),
+            ("leftcontrol", "leftcontrol"),
+            ("LeftControl++L", ""),
+            ("LeftControl+", ""),
+            ("", ""),
+        ];
+        for (text, unknown) in fx_invalid {
+            match parse_hotkey(text) {
+                Err(Error::HotkeyKeyUnknown(name)) => assert_eq!(name, unknown, "{text:?}"),
+                other => panic!("{text:?} parsed as {other:?}"),
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_resolution() -> Result<()> {
+        assert_eq!(parse_resolution("1920x1080")?, (1920, 1080));
+        assert_eq!(parse_resolution(" 800 x 600 ")?, (800, 600));
+
+        for fx_text in [
+            "",
+            "1920",
+            "1920x",
+            "x1080",
+            "0x1080",
+            "1920x0",
+            "-1x5",
+            "1920*1080",
+        ] {
+            assert!(
+                matches!(
+                    parse_resolution(fx_text),
+                    Err(Error::ConfigWrongFormat("RESOLUTION"))
+                ),
+                "{fx_text:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_load_errors() -> Result<()> {
+        let fx_settings = || Settings::from_env().with_override("ADDRESS", "127.0.0.1:54321");
+        assert_eq!(Config::load(&fx_settings())?.WIDTH, 1920);
+
+        let load = |key: &str, value: &str| Config::load(&fx_settings().with_override(key, value));
+        assert!(matches!(
+            load("RESOLUTION", "wide"),
+            Err(Error::ConfigWrongFormat("RESOLUTION"))
+        ));
+        assert!(matches!(
+            load("OFFLINE_POLICY", "keep"),
+            Err(Error::OfflinePolicyUnknown(policy)) if policy == "keep"
+        ));
+        assert!(matches!(
+            load("POINTER_LOCK_HOTKEY", "Hyper"),
+            Err(Error::HotkeyKeyUnknown(key)) if key == "Hyper"
+        ));
+        assert!(matches!(
+            load("ADDRESS", "localhost"),
+            Err(Error::Config(lib_config::Error::ValueWrongFormat { key, .. })) if key == "ADDRESS"
+        ));
+        assert!(matches!(
+            load("CLIPBOARD", "paper"),
+            Err(Error::Clipboard(_))
+        ));
+        // The environment would fill it in.
+        if std::env::var("ADDRESS").is_err() {
+            assert!(matches!(
+                Config::load(&Settings::from_env()),
+                Err(Error::ConfigMissing("ADDRESS"))
+            ));
+        }
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -5,7 +5,7 @@ use std::sync::{atomic::AtomicBool, Arc};
 
 pub use error::{Error, Result};
 
-use crate::HandlerCommand;
+use crate::{ConnectionState, HandlerCommand};
 
 #[enum_dispatch::enum_dispatch(DispatcherTrait)]
 pub enum Dispatcher {
@@ -16,13 +16,21 @@ pub enum Dispatcher {
 }
 
 impl Dispatcher {
-    pub fn init(tx: flume::Sender<HandlerCommand>, is_running: Arc<AtomicBool>) -> Result<Self> {
+    /// Captured input goes to `tx`, `state_rx` tells how the server
+    /// connection is doing and `resize_rx` the size of the virtual display
+    /// after a config reload.
+    pub fn init(
+        tx: flume::Sender<HandlerCommand>,
+        state_rx: flume::Receiver<ConnectionState>,
+        resize_rx: flume::Receiver<(u32, u32)>,
+        is_running: Arc<AtomicBool>,
+    ) -> Result<Self> {
         #[cfg(unix)]
         let dispatcher = {
             use tracing::info;
 
             info!("Creating unix dispatcher...");
-            wayland::WaylandDispatcher::new(tx, is_running)
+            wayland::WaylandDispatcher::new(tx, state_rx, resize_rx, is_running)
         };
         #[cfg(windows)]
         let dispatcher = {
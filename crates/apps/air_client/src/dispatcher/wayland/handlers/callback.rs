@@ -0,0 +1,32 @@
+use crate::{dispatcher::wayland::state::WaylandState, ConnectionState};
+use wayland_client::{
+    protocol::wl_callback::{self, WlCallback},
+    Connection, Dispatch, QueueHandle,
+};
+
+/// What a sync requested by the dispatcher hands over to the dispatch.
+#[derive(Debug, Clone, Copy)]
+pub enum Wake {
+    Connection(ConnectionState),
+    /// The virtual display is made again at this size.
+    Resize(u32, u32),
+}
+
+impl Dispatch<WlCallback, Wake> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &WlCallback,
+        event: wl_callback::Event,
+        wake: &Wake,
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        if let wl_callback::Event::Done { .. } = event {
+            match *wake {
+                Wake::Connection(connection_state) => state.set_connection_state(connection_state),
+                // Only the dispatcher owns the display.
+                Wake::Resize(width, height) => state.resize = Some((width, height)),
+            }
+        }
+    }
+}
@@ -1,43 +1,100 @@
 use crate::{dispatcher::wayland::state::WaylandState, HandlerCommand};
-use lib_models::Command;
-use tracing::warn;
+use lib_models::{Command, Key, ModifierState};
+use std::{fs::File, os::fd::OwnedFd, os::unix::fs::FileExt};
+use tracing::{debug, warn};
 use wayland_client::{
-    protocol::wl_keyboard::{KeyState, WlKeyboard},
+    protocol::wl_keyboard::{self, KeyState, KeymapFormat, WlKeyboard},
     Connection, Dispatch, QueueHandle,
 };
 
+/// Reads the keymap text shared by the compositor.
+fn read_keymap(fd: OwnedFd, size: u32) -> std::io::Result<String> {
+    let mut buffer = vec![0; size as usize];
+    File::from(fd).read_exact_at(&mut buffer, 0)?;
+
+    // The text is NUL terminated.
+    let end = buffer.iter().position(|&b| b == 0).unwrap_or(buffer.len());
+    buffer.truncate(end);
+
+    String::from_utf8(buffer).map_err(std::io::Error::other)
+}
+
 impl Dispatch<WlKeyboard, ()> for WaylandState {
     fn event(
         state: &mut Self,
         _: &WlKeyboard,
-        event: wayland_client::protocol::wl_keyboard::Event,
+        event: wl_keyboard::Event,
         _: &(),
         _: &Connection,
-        _: &QueueHandle<Self>,
+        qh: &QueueHandle<Self>,
     ) {
-        match event {
-            wayland_client::protocol::wl_keyboard::Event::Key {
+        let command = match event {
+            wl_keyboard::Event::Keymap {
+                format: wayland_client::WEnum::Value(KeymapFormat::XkbV1),
+                fd,
+                size,
+            } => match read_keymap(fd, size) {
+                Ok(keymap) => Command::Keymap(keymap),
+                Err(e) => {
+                    warn!("Can't read the keymap: {e}");
+                    return;
+                }
+            },
+            wl_keyboard::Event::Key {
                 key,
                 state: key_state,
                 ..
             } => {
-                let command = match key_state {
-                    wayland_client::WEnum::Value(KeyState::Pressed) => {
-                        HandlerCommand::Command(Command::KeyPressed(key))
-                    }
-                    wayland_client::WEnum::Value(KeyState::Released) => {
-                        HandlerCommand::Command(Command::KeyReleased(key))
-                    }
-                    wayland_client::WEnum::Value(KeyState::Repeated) => {
-                        HandlerCommand::Command(Command::KeyPressed(key))
-                    }
+                let Some(key) = Key::from_evdev(key) else {
+                    debug!("Key {key} has no HID usage, not sent");
+                    return;
+                };
 
+                let pressed = match key_state {
+                    wayland_client::WEnum::Value(KeyState::Pressed | KeyState::Repeated) => true,
+                    wayland_client::WEnum::Value(KeyState::Released) => false,
                     _ => return,
                 };
 
-                let _ = state.command_tx.send(command);
+                if state.handle_hotkey(key, pressed, qh) {
+                    return;
+                }
+
+                match pressed {
+                    true => Command::KeyPressed(key),
+                    false => Command::KeyReleased(key),
+                }
             }
-            _ => {}
-        }
+            wl_keyboard::Event::Enter { keys, .. } => {
+                // Array of u32 key codes in native byte order.
+                let pressed: Vec<Key> = keys
+                    .chunks_exact(4)
+                    .map(|bytes| u32::from_ne_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+                    .filter_map(Key::from_evdev)
+                    .collect();
+                state.hotkey.enter(&pressed);
+
+                Command::FocusEntered { pressed }
+            }
+            wl_keyboard::Event::Leave { .. } => {
+                state.hotkey.leave();
+                Command::FocusLost
+            }
+            wl_keyboard::Event::Modifiers {
+                mods_depressed,
+                mods_latched,
+                mods_locked,
+                group,
+                ..
+            } => Command::Modifiers(ModifierState {
+                depressed: mods_depressed,
+                latched: mods_latched,
+                locked: mods_locked,
+                group,
+            }),
+            _ => return,
+        };
+
+        let _ = state.command_tx.send(HandlerCommand::Command(command));
     }
 }
@@ -1,6 +1,10 @@
+mod callback;
 mod keyboard;
 mod output;
 mod pointer;
 mod registry;
+mod relative_pointer;
 mod seat;
 mod xdg;
+
+pub use callback::Wake;
@@ -1,16 +1,17 @@
 // air_client2/src/dispatcher/wayland/handlers/pointer.rs
 
-use crate::{dispatcher::wayland::state::WaylandState, HandlerCommand};
-use lib_models::{Command, MouseButton, MouseScroll};
+use crate::{config, dispatcher::wayland::state::WaylandState};
+use lib_models::{normalize, Command, MouseButton, MouseScroll, ScrollDelta, ScrollSource};
+use tracing::debug;
 use wayland_client::{
-    protocol::wl_pointer::{Axis, ButtonState, Event, WlPointer},
-    Connection, Dispatch, Proxy, QueueHandle,
+    protocol::wl_pointer::{Axis, AxisSource, ButtonState, Event, WlPointer},
+    Connection, Dispatch, Proxy, QueueHandle, WEnum,
 };
 
 impl Dispatch<WlPointer, ()> for WaylandState {
     fn event(
         state: &mut Self,
-        _: &WlPointer,
+        pointer: &WlPointer,
         event: Event,
         _: &(),
         _: &Connection,
@@ -37,12 +38,14 @@ impl Dispatch<WlPointer, ()> for WaylandState {
                 surface_y,
                 ..
             } => {
-                if state.is_on_virtual {
-                    let command = HandlerCommand::Command(Command::SetMouse {
-                        x: surface_x as i32,
-                        y: surface_y as i32,
+                // The pointer is locked in relative mode, motion comes unaccelerated.
+                if state.is_on_virtual && !state.is_relative() {
+                    let (width, height) = state.surface_size;
+                    state.queue_pointer(Command::SetMouse {
+                        display: config().SERVER_DISPLAY,
+                        x: normalize(surface_x, width),
+                        y: normalize(surface_y, height),
                     });
-                    let _ = state.command_tx.send(command);
                 }
             }
 
@@ -68,54 +71,86 @@ impl Dispatch<WlPointer, ()> for WaylandState {
                     return;
                 }
 
-                let mouse_button = match button {
-                    272 => MouseButton::LEFT,
-                    273 => MouseButton::RIGHT,
-                    274 => MouseButton::MIDDLE,
-                    _ => return,
+                let Some(mouse_button) = MouseButton::from_evdev(button) else {
+                    debug!("Button {button} is not a pointer button, not sent");
+                    return;
                 };
 
                 match btn_state {
                     wayland_client::WEnum::Value(ButtonState::Pressed) => {
-                        let _ = state.command_tx.send(HandlerCommand::Command(
-                            Command::MouseButtonPressed(mouse_button),
-                        ));
+                        state.queue_pointer(Command::MouseButtonPressed(mouse_button));
                     }
                     wayland_client::WEnum::Value(ButtonState::Released) => {
-                        let _ = state.command_tx.send(HandlerCommand::Command(
-                            Command::MouseButtonReleased(mouse_button),
-                        ));
+                        state.queue_pointer(Command::MouseButtonReleased(mouse_button));
                     }
                     _ => {}
                 }
             }
 
+            Event::AxisSource {
+                axis_source: WEnum::Value(source),
+            } => {
+                state.scroll_frame.source = match source {
+                    AxisSource::Finger => ScrollSource::Finger,
+                    AxisSource::Continuous => ScrollSource::Continuous,
+                    AxisSource::WheelTilt => ScrollSource::WheelTilt,
+                    _ => ScrollSource::Wheel,
+                };
+            }
+
             Event::Axis { axis, value, .. } => {
-                if !state.is_on_virtual {
-                    return;
+                if let Some(delta) = axis_delta(&mut state.scroll_frame, axis) {
+                    delta.pixels += value;
                 }
+                // Before version 5 there are no frames to wait for.
+                if pointer.version() < 5 {
+                    queue_scroll(state);
+                }
+            }
 
-                let value = value as i32;
-                if value == 0 {
-                    return;
+            // Sent instead of `AxisValue120` before version 8.
+            Event::AxisDiscrete { axis, discrete } => {
+                if let Some(delta) = axis_delta(&mut state.scroll_frame, axis) {
+                    delta.value120 += discrete * 120;
                 }
+            }
 
-                let scroll = match axis {
-                    wayland_client::WEnum::Value(Axis::VerticalScroll) => {
-                        MouseScroll::Vertical(value)
-                    }
-                    wayland_client::WEnum::Value(Axis::HorizontalScroll) => {
-                        MouseScroll::Horizontal(value)
-                    }
-                    _ => return,
-                };
+            Event::AxisValue120 { axis, value120 } => {
+                if let Some(delta) = axis_delta(&mut state.scroll_frame, axis) {
+                    delta.value120 += value120;
+                }
+            }
 
-                let _ = state
-                    .command_tx
-                    .send(HandlerCommand::Command(Command::MouseScroll(scroll)));
+            Event::AxisStop { axis, .. } => {
+                if let Some(delta) = axis_delta(&mut state.scroll_frame, axis) {
+                    delta.stop = true;
+                }
+            }
+
+            Event::Frame => {
+                queue_scroll(state);
+                state.flush_pointer_frame();
             }
 
             _ => {}
         }
     }
 }
+
+fn axis_delta(scroll: &mut MouseScroll, axis: WEnum<Axis>) -> Option<&mut ScrollDelta> {
+    match axis {
+        WEnum::Value(Axis::VerticalScroll) => Some(&mut scroll.vertical),
+        WEnum::Value(Axis::HorizontalScroll) => Some(&mut scroll.horizontal),
+        _ => None,
+    }
+}
+
+/// Queues the scroll collected since the last frame.
+fn queue_scroll(state: &mut WaylandState) {
+    let scroll = std::mem::take(&mut state.scroll_frame);
+    if !state.is_on_virtual || scroll.is_empty() {
+        return;
+    }
+
+    state.queue_pointer(Command::MouseScroll(scroll));
+}
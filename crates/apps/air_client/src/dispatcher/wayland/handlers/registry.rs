@@ -7,6 +7,10 @@ use wayland_client::{
     },
     Connection, Dispatch, QueueHandle,
 };
+use wayland_protocols::wp::{
+    pointer_constraints::zv1::client::zwp_pointer_constraints_v1::ZwpPointerConstraintsV1,
+    relative_pointer::zv1::client::zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
+};
 
 impl Dispatch<WlRegistry, ()> for WaylandState {
     fn event(
@@ -50,6 +54,18 @@ impl Dispatch<WlRegistry, ()> for WaylandState {
                     state.wm_base = Some(wm_base);
                     println!("✅ XDG WM Base registered");
                 }
+                "zwp_relative_pointer_manager_v1" => {
+                    let manager =
+                        registry.bind::<ZwpRelativePointerManagerV1, _, _>(name, 1, qh, ());
+                    state.relative_pointer_manager = Some(manager);
+                    println!("✅ Relative pointer manager registered");
+                }
+                "zwp_pointer_constraints_v1" => {
+                    let constraints =
+                        registry.bind::<ZwpPointerConstraintsV1, _, _>(name, 1, qh, ());
+                    state.pointer_constraints = Some(constraints);
+                    println!("✅ Pointer constraints registered");
+                }
                 _ => {}
             }
         }
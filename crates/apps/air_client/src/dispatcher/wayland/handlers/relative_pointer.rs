@@ -0,0 +1,65 @@
+use crate::dispatcher::wayland::state::WaylandState;
+use lib_models::Command;
+use tracing::info;
+use wayland_client::{Connection, Dispatch, QueueHandle};
+use wayland_protocols::wp::{
+    pointer_constraints::zv1::client::zwp_locked_pointer_v1::{self, ZwpLockedPointerV1},
+    relative_pointer::zv1::client::zwp_relative_pointer_v1::{self, ZwpRelativePointerV1},
+};
+
+impl Dispatch<ZwpRelativePointerV1, ()> for WaylandState {
+    fn event(
+        state: &mut Self,
+        _: &ZwpRelativePointerV1,
+        event: zwp_relative_pointer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        let zwp_relative_pointer_v1::Event::RelativeMotion {
+            dx_unaccel,
+            dy_unaccel,
+            ..
+        } = event
+        else {
+            return;
+        };
+
+        if !state.is_on_virtual {
+            return;
+        }
+
+        // Keep the fractions for the next motion.
+        let (x, y) = state.relative_remainder;
+        let (x, y) = (x + dx_unaccel, y + dy_unaccel);
+        let (dx, dy) = (x.trunc(), y.trunc());
+        state.relative_remainder = (x - dx, y - dy);
+
+        if dx == 0.0 && dy == 0.0 {
+            return;
+        }
+
+        // Sent with the `wl_pointer` frame it belongs to.
+        state.queue_pointer(Command::MoveMouse {
+            x: dx as i32,
+            y: dy as i32,
+        });
+    }
+}
+
+impl Dispatch<ZwpLockedPointerV1, ()> for WaylandState {
+    fn event(
+        _: &mut Self,
+        _: &ZwpLockedPointerV1,
+        event: zwp_locked_pointer_v1::Event,
+        _: &(),
+        _: &Connection,
+        _: &QueueHandle<Self>,
+    ) {
+        match event {
+            zwp_locked_pointer_v1::Event::Locked => info!("🔒 Pointer locked"),
+            zwp_locked_pointer_v1::Event::Unlocked => info!("🔓 Pointer unlocked"),
+            _ => {}
+        }
+    }
+}
@@ -49,7 +49,7 @@ impl Dispatch<XdgSurface, ()> for WaylandState {
 // Добавь реализацию для XdgToplevel
 impl Dispatch<XdgToplevel, ()> for WaylandState {
     fn event(
-        _state: &mut Self,
+        state: &mut Self,
         _: &XdgToplevel,
         event: wayland_protocols::xdg::shell::client::xdg_toplevel::Event,
         _: &(),
@@ -60,7 +60,14 @@ impl Dispatch<XdgToplevel, ()> for WaylandState {
             wayland_protocols::xdg::shell::client::xdg_toplevel::Event::Close => {
                 println!("Window close requested");
             }
-            wayland_protocols::xdg::shell::client::xdg_toplevel::Event::Configure { .. } => {}
+            // 0 leaves the size to us, it stays the configured resolution.
+            wayland_protocols::xdg::shell::client::xdg_toplevel::Event::Configure {
+                width,
+                height,
+                ..
+            } if width > 0 && height > 0 => {
+                state.surface_size = (width as f64, height as f64);
+            }
             _ => {}
         }
     }
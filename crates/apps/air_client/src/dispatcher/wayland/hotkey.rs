@@ -0,0 +1,124 @@
+//! Pointer lock hotkey, followed on the keys of the client keyboard.
+
+use lib_models::Key;
+
+/// What becomes of a key event once the hotkey is followed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum HotkeyAction {
+    /// Sent to the server.
+    Forward,
+    /// Repeat or release of the key completing the hotkey, not sent.
+    Swallow,
+    /// Completes the hotkey, the pointer lock switches and the key isn't sent.
+    Toggle,
+}
+
+#[derive(Debug, Default)]
+pub struct HotkeyTracker {
+    /// Keys held on the client keyboard.
+    pressed: Vec<Key>,
+    /// Key that completed the hotkey, its release isn't sent.
+    completing: Option<Key>,
+}
+
+impl HotkeyTracker {
+    pub fn key(&mut self, key: Key, pressed: bool, hotkey: &[Key]) -> HotkeyAction {
+        if !pressed {
+            self.pressed.retain(|&held| held != key);
+            return match self.completing.take_if(|&mut held| held == key) {
+                Some(_) => HotkeyAction::Swallow,
+                None => HotkeyAction::Forward,
+            };
+        }
+
+        // Repeated press.
+        if self.pressed.contains(&key) {
+            return match self.completing == Some(key) {
+                true => HotkeyAction::Swallow,
+                false => HotkeyAction::Forward,
+            };
+        }
+        self.pressed.push(key);
+
+        if !hotkey.contains(&key) || !hotkey.iter().all(|key| self.pressed.contains(key)) {
+            return HotkeyAction::Forward;
+        }
+        self.completing = Some(key);
+
+        HotkeyAction::Toggle
+    }
+
+    /// Keys already held when the keyboard focus came in.
+    pub fn enter(&mut self, pressed: &[Key]) {
+        self.pressed = pressed.to_vec();
+    }
+
+    pub fn leave(&mut self) {
+        self.pressed.clear();
+        self.completing = None;
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use HotkeyAction::{Forward, Swallow, Toggle};
+
+    const HOTKEY: [Key; 2] = [Key::RightControl, Key::RightShift];
+
+    #[test]
+    fn test_hotkey_toggles_lock() {
+        let mut tracker = HotkeyTracker::default();
+        let mut locked = false;
+
+        let fx_events = [
+            (Key::RightControl, true, Forward, false),
+            (Key::RightShift, true, Toggle, true),
+            (Key::RightShift, true, Swallow, true), // Repeat.
+            (Key::A, true, Forward, true),
+            (Key::A, false, Forward, true),
+            (Key::RightShift, false, Swallow, true),
+            (Key::RightControl, false, Forward, true),
+            // Completed the other way round, the lock goes away.
+            (Key::RightShift, true, Forward, true),
+            (Key::RightControl, true, Toggle, false),
+            (Key::RightControl, false, Swallow, false),
+            (Key::RightShift, false, Forward, false),
+        ];
+
+        for (i, (key, pressed, action, fx_locked)) in fx_events.into_iter().enumerate() {
+            assert_eq!(tracker.key(key, pressed, &HOTKEY), action, "event {i}");
+            if action == Toggle {
+                locked = !locked;
+            }
+            assert_eq!(locked, fx_locked, "event {i}");
+        }
+    }
+
+    #[test]
+    fn test_focus_resets_hotkey() {
+        let mut tracker = HotkeyTracker::default();
+
+        // Held before the focus came in, the hotkey completes on the next key.
+        tracker.enter(&[Key::RightControl]);
+        assert_eq!(tracker.key(Key::RightShift, true, &HOTKEY), Toggle);
+
+        // The completing key was released elsewhere, its release is sent
+        // after the focus is back.
+        tracker.leave();
+        assert_eq!(tracker.key(Key::RightShift, false, &HOTKEY), Forward);
+        assert_eq!(tracker.key(Key::RightShift, true, &HOTKEY), Forward);
+    }
+
+    #[test]
+    fn test_empty_hotkey_forwards() {
+        let mut tracker = HotkeyTracker::default();
+
+        assert_eq!(tracker.key(Key::A, true, &[]), Forward);
+        assert_eq!(tracker.key(Key::A, false, &[]), Forward);
+    }
+}
+
+// endregion: --- Tests
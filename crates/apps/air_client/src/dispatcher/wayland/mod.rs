@@ -1,50 +1,86 @@
-use std::sync::{
-    atomic::{AtomicBool, Ordering},
-    Arc,
+use std::{
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
+    thread,
 };
 
+use tracing::{error, info};
 use wayland_client::{Connection, EventQueue};
 
 use super::{Error, Result};
 use crate::{
     config,
-    dispatcher::{wayland::state::WaylandState, DispatcherTrait},
-    HandlerCommand, VirtualDisplay,
+    dispatcher::{
+        wayland::{handlers::Wake, state::WaylandState},
+        DispatcherTrait,
+    },
+    ConnectionState, HandlerCommand, VirtualDisplay,
 };
 
 mod handlers;
+mod hotkey;
 mod state;
 
 pub struct WaylandDispatcher {
     command_tx: flume::Sender<HandlerCommand>,
+    state_rx: flume::Receiver<ConnectionState>,
+    resize_rx: flume::Receiver<(u32, u32)>,
     running: Arc<AtomicBool>,
     display: Option<VirtualDisplay>,
 }
 
 impl WaylandDispatcher {
-    pub fn new(command_tx: flume::Sender<HandlerCommand>, is_running: Arc<AtomicBool>) -> Self {
+    pub fn new(
+        command_tx: flume::Sender<HandlerCommand>,
+        state_rx: flume::Receiver<ConnectionState>,
+        resize_rx: flume::Receiver<(u32, u32)>,
+        is_running: Arc<AtomicBool>,
+    ) -> Self {
         Self {
             command_tx,
+            state_rx,
+            resize_rx,
             running: is_running,
             display: None,
         }
     }
 
+    /// Creates the virtual display, the previous one is removed only once the
+    /// new one exists.
     pub fn init_virtual_display(&mut self, width: u32, height: u32) -> Result<String> {
-        if let Some(display) = self.display.take() {
-            display.remove();
-        }
-
         let display =
             VirtualDisplay::create(width, height).map_err(|_| Error::DisplayCreateFail)?;
 
         println!("✅ Virtual display created: {}", display);
 
         let output_name = display.output_name().to_string();
-        self.display = Some(display);
+        if let Some(previous) = self.display.replace(display) {
+            previous.remove();
+        }
 
         Ok(output_name)
     }
+
+    /// Makes the virtual display again at the new size, the window moves to
+    /// its output once it shows up. When that fails the current display
+    /// stays.
+    fn resize(&mut self, state: &mut WaylandState, width: u32, height: u32) {
+        let output_name = match self.init_virtual_display(width, height) {
+            Ok(output_name) => output_name,
+            Err(e) => {
+                error!("Virtual display not resized to {width}x{height}: {e}");
+                return;
+            }
+        };
+
+        info!("Virtual display resized to {width}x{height}");
+        state.drop_buffer();
+        state.surface_size = (width as f64, height as f64);
+        state.virtual_output_id = None;
+        state.virtual_output_name = output_name;
+    }
 }
 
 impl DispatcherTrait for WaylandDispatcher {
@@ -64,6 +100,23 @@ impl DispatcherTrait for WaylandDispatcher {
         let registry = conn.display().get_registry(&qh, ());
         state.registry = Some(registry);
 
+        // Each state and resize rides a `wl_display.sync`, its callback wakes
+        // the queue and hands it over to the dispatch.
+        let state_rx = self.state_rx.clone();
+        let resize_rx = self.resize_rx.clone();
+        let sync_conn = conn.clone();
+        let sync_qh = qh.clone();
+        thread::spawn(move || {
+            while let Ok(wake) = flume::Selector::new()
+                .recv(&state_rx, |state| state.map(Wake::Connection))
+                .recv(&resize_rx, |size| size.map(|(w, h)| Wake::Resize(w, h)))
+                .wait()
+            {
+                sync_conn.display().sync(&sync_qh, wake);
+                let _ = sync_conn.flush();
+            }
+        });
+
         println!("🔄 Wayland dispatcher running...");
         self.running.store(true, Ordering::Relaxed);
 
@@ -71,6 +124,10 @@ impl DispatcherTrait for WaylandDispatcher {
             event_queue
                 .blocking_dispatch(&mut state)
                 .map_err(|_| Error::WaylandDispatchFail)?;
+
+            if let Some((width, height)) = state.resize.take() {
+                self.resize(&mut state, width, height);
+            }
         }
 
         self.stop();
@@ -1,6 +1,13 @@
 // air_client2/src/dispatcher/wayland/state.rs
-use crate::HandlerCommand;
-use std::{collections::HashMap, os::fd::AsFd};
+use super::hotkey::{HotkeyAction, HotkeyTracker};
+use crate::{config, ConnectionState, HandlerCommand};
+use lib_models::{Command, CommandBatch, Key, MouseScroll};
+use std::{
+    collections::HashMap,
+    fs::File,
+    os::{fd::AsFd, unix::fs::FileExt},
+};
+use tracing::{info, warn};
 use wayland_client::{
     backend::ObjectId,
     delegate_noop,
@@ -15,15 +22,31 @@ use wayland_client::{
         wl_shm_pool::WlShmPool,
         wl_surface::WlSurface,
     },
-    QueueHandle,
+    Proxy, QueueHandle,
+};
+use wayland_protocols::{
+    wp::{
+        pointer_constraints::zv1::client::{
+            zwp_locked_pointer_v1::ZwpLockedPointerV1,
+            zwp_pointer_constraints_v1::{Lifetime, ZwpPointerConstraintsV1},
+        },
+        relative_pointer::zv1::client::{
+            zwp_relative_pointer_manager_v1::ZwpRelativePointerManagerV1,
+            zwp_relative_pointer_v1::ZwpRelativePointerV1,
+        },
+    },
+    xdg::shell::client::{
+        xdg_surface::XdgSurface, xdg_toplevel::XdgToplevel, xdg_wm_base::XdgWmBase,
+    },
 };
-use wayland_protocols::xdg::shell::client::{xdg_surface::XdgSurface, xdg_wm_base::XdgWmBase};
 
 delegate_noop!(WaylandState: ignore WlCompositor);
 delegate_noop!(WaylandState: ignore WlSurface);
 delegate_noop!(WaylandState: ignore WlShm);
 delegate_noop!(WaylandState: ignore WlShmPool);
 delegate_noop!(WaylandState: ignore WlBuffer);
+delegate_noop!(WaylandState: ignore ZwpRelativePointerManagerV1);
+delegate_noop!(WaylandState: ignore ZwpPointerConstraintsV1);
 
 pub struct WaylandState {
     pub command_tx: flume::Sender<HandlerCommand>,
@@ -32,13 +55,36 @@ pub struct WaylandState {
     pub seat: Option<WlSeat>,
     pub pointer: Option<WlPointer>,
     pub keyboard: Option<WlKeyboard>,
+    pub relative_pointer_manager: Option<ZwpRelativePointerManagerV1>,
+    pub pointer_constraints: Option<ZwpPointerConstraintsV1>,
     pub wm_base: Option<XdgWmBase>,
     pub shm: Option<WlShm>,
     pub surface: Option<WlSurface>,
     pub xdg_surface: Option<XdgSurface>,
+    pub toplevel: Option<XdgToplevel>,
     pub buffer: Option<WlBuffer>,
+    /// Backs the shm pool of `buffer`, written to repaint it.
+    pub buffer_file: Option<File>,
+    pub buffer_size: (i32, i32),
+    /// Shown on the window, dimmed while not connected.
+    pub connection_state: ConnectionState,
     pub virtual_output_name: String,
     pub is_on_virtual: bool,
+    /// Size of our fullscreen surface, pointer positions are relative to it.
+    pub surface_size: (f64, f64),
+    /// Size the virtual display is to be made again at, after a reload.
+    pub resize: Option<(u32, u32)>,
+    /// Scroll of the current `wl_pointer` frame.
+    pub scroll_frame: MouseScroll,
+    /// Pointer commands of the current `wl_pointer` frame.
+    pub pointer_frame: CommandBatch,
+    /// Set in relative mode, the pointer stays locked on our surface.
+    pub relative_pointer: Option<ZwpRelativePointerV1>,
+    pub locked_pointer: Option<ZwpLockedPointerV1>,
+    /// Relative motion not sent yet, `MoveMouse` carries whole pixels.
+    pub relative_remainder: (f64, f64),
+    /// Pointer lock hotkey, followed on the client keyboard.
+    pub hotkey: HotkeyTracker,
 
     pub outputs: Vec<WlOutput>,
     pub output_names: HashMap<ObjectId, String>,
@@ -56,13 +102,27 @@ impl WaylandState {
             seat: None,
             pointer: None,
             keyboard: None,
+            relative_pointer_manager: None,
+            pointer_constraints: None,
             wm_base: None,
             shm: None,
             surface: None,
             xdg_surface: None,
+            toplevel: None,
             buffer: None,
+            buffer_file: None,
+            buffer_size: (0, 0),
+            connection_state: ConnectionState::Connecting,
             virtual_output_name,
             is_on_virtual: false,
+            surface_size: (config().WIDTH as f64, config().HEIGHT as f64),
+            resize: None,
+            scroll_frame: MouseScroll::default(),
+            pointer_frame: CommandBatch::default(),
+            relative_pointer: None,
+            locked_pointer: None,
+            relative_remainder: (0.0, 0.0),
+            hotkey: HotkeyTracker::default(),
             outputs: Vec::new(),
             output_names: HashMap::new(),
             virtual_output_id: None,
@@ -70,6 +130,73 @@ impl WaylandState {
         }
     }
 
+    /// Queues a pointer command until the end of its `wl_pointer` frame.
+    pub fn queue_pointer(&mut self, command: Command) {
+        self.pointer_frame.push(command);
+
+        // Before version 5 there are no frames to wait for.
+        if self
+            .pointer
+            .as_ref()
+            .is_none_or(|pointer| pointer.version() < 5)
+        {
+            self.flush_pointer_frame();
+        }
+    }
+
+    /// Sends the pointer commands of the frame as one batch.
+    pub fn flush_pointer_frame(&mut self) {
+        if let Some(command) = self.pointer_frame.take() {
+            let _ = self.command_tx.send(HandlerCommand::Command(command));
+        }
+    }
+
+    pub fn is_relative(&self) -> bool {
+        self.locked_pointer.is_some()
+    }
+
+    /// Switches between absolute and relative pointer mode.
+    pub fn toggle_pointer_lock(&mut self, qh: &QueueHandle<Self>) {
+        if let Some(locked_pointer) = self.locked_pointer.take() {
+            locked_pointer.destroy();
+            if let Some(relative_pointer) = self.relative_pointer.take() {
+                relative_pointer.destroy();
+            }
+            info!("Pointer mode: absolute");
+            return;
+        }
+
+        let (Some(manager), Some(constraints), Some(pointer), Some(surface)) = (
+            &self.relative_pointer_manager,
+            &self.pointer_constraints,
+            &self.pointer,
+            &self.surface,
+        ) else {
+            warn!("Relative pointer mode is not supported by the compositor");
+            return;
+        };
+
+        self.relative_pointer = Some(manager.get_relative_pointer(pointer, qh, ()));
+        // Persistent: the lock comes back whenever the pointer enters the surface again.
+        self.locked_pointer =
+            Some(constraints.lock_pointer(surface, pointer, None, Lifetime::Persistent, qh, ()));
+        self.relative_remainder = (0.0, 0.0);
+        info!("Pointer mode: relative");
+    }
+
+    /// Toggles the pointer lock when the hotkey is complete. Returns true when
+    /// the key event must not be forwarded.
+    pub fn handle_hotkey(&mut self, key: Key, pressed: bool, qh: &QueueHandle<Self>) -> bool {
+        match self.hotkey.key(key, pressed, &config().POINTER_LOCK_HOTKEY) {
+            HotkeyAction::Forward => false,
+            HotkeyAction::Swallow => true,
+            HotkeyAction::Toggle => {
+                self.toggle_pointer_lock(qh);
+                true
+            }
+        }
+    }
+
     pub fn create_surface(&mut self, qh: &QueueHandle<Self>) {
         if let Some(compositor) = &self.compositor {
             let surface = compositor.create_surface(qh, ());
@@ -82,6 +209,10 @@ impl WaylandState {
         if let (Some(wm_base), Some(surface)) = (&self.wm_base, &self.surface) {
             // НЕ создаём если уже есть
             if self.xdg_surface.is_some() {
+                // A resized virtual display comes back as a new output.
+                if let Some(toplevel) = &self.toplevel {
+                    toplevel.set_fullscreen(Some(output));
+                }
                 return;
             }
 
@@ -93,6 +224,7 @@ impl WaylandState {
             surface.commit();
 
             self.xdg_surface = Some(xdg_surface);
+            self.toplevel = Some(toplevel);
             println!("✅ Fullscreen window created on virtual output");
         }
     }
@@ -106,10 +238,9 @@ impl WaylandState {
             let stride = width * 4;
             let size = (stride * height) as u64;
 
-            let mut file = tempfile::tempfile().unwrap();
+            let file = tempfile::tempfile().unwrap();
             file.set_len(size).unwrap();
-            let black = vec![0u8; size as usize];
-            std::io::Write::write_all(&mut file, &black).unwrap();
+            fill(&file, size as usize, self.connection_state).unwrap();
 
             let pool = shm.create_pool(file.as_fd(), size as i32, qh, ());
             let buffer =
@@ -119,7 +250,52 @@ impl WaylandState {
             surface.commit();
 
             self.buffer = Some(buffer);
+            self.buffer_file = Some(file);
+            self.buffer_size = (width, height);
             println!("✅ Buffer created: {}x{}", width, height);
         }
     }
+
+    /// Lets go of the buffer, the next [`Self::create_buffer`] makes one of
+    /// the new size.
+    pub fn drop_buffer(&mut self) {
+        if let Some(buffer) = self.buffer.take() {
+            buffer.destroy();
+        }
+        self.buffer_file = None;
+    }
+
+    /// Repaints the window for the new state of the server connection.
+    pub fn set_connection_state(&mut self, connection_state: ConnectionState) {
+        if self.connection_state == connection_state {
+            return;
+        }
+        info!("Connection state: {connection_state:?}");
+        self.connection_state = connection_state;
+
+        let (Some(surface), Some(buffer), Some(file)) =
+            (&self.surface, &self.buffer, &self.buffer_file)
+        else {
+            return;
+        };
+
+        let (width, height) = self.buffer_size;
+        if let Err(e) = fill(file, (width * height * 4) as usize, connection_state) {
+            warn!("Can't repaint the window: {e}");
+            return;
+        }
+        surface.attach(Some(buffer), 0, 0);
+        surface.damage(0, 0, width, height);
+        surface.commit();
+    }
+}
+
+/// Paints the whole buffer: clear once connected, half black otherwise.
+fn fill(file: &File, size: usize, connection_state: ConnectionState) -> std::io::Result<()> {
+    // Premultiplied ARGB, little-endian.
+    let pixel: [u8; 4] = match connection_state {
+        ConnectionState::Connected => [0, 0, 0, 0],
+        ConnectionState::Connecting | ConnectionState::Disconnected => [0, 0, 0, 0x80],
+    };
+    file.write_all_at(&pixel.repeat(size / 4), 0)
 }
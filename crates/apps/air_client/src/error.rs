@@ -10,6 +10,20 @@ pub type Result<T> = core::result::Result<T, Error>;
 pub enum Error {
     // -- Config
     ConfigAlreadyInitialized,
+    ConfigMissing(&'static str),
+    ConfigWrongFormat(&'static str),
+    HotkeyKeyUnknown(String),
+    /// Not `drop`, `buffer` or `buffer:<commands>`.
+    OfflinePolicyUnknown(String),
+
+    // -- Certificates
+    /// The server authority is not there, see `SERVER_CA`.
+    ServerCaMissing(std::path::PathBuf),
+
+    // -- Handshake
+    /// The control stream ended before the server hello.
+    HandshakeClosed,
+    HandshakeRejected(lib_models::handshake::HandshakeError),
 
     // -- Modules
     #[from]
@@ -17,11 +31,21 @@ pub enum Error {
 
     // -- Externals
     #[from]
+    Config(lib_config::Error),
+    #[from]
     Codec(lib_codec::Error),
     #[from]
     Quic(lib_quic::Error),
     #[from]
+    Connect(lib_quic::quinn::ConnectError),
+    #[from]
+    Connection(lib_quic::quinn::ConnectionError),
+    #[from]
+    Tls(lib_tls::Error),
+    #[from]
     Envs(grapple_utils::envs::Error),
+    #[from]
+    Clipboard(lib_models::clipboard::Error),
 
     #[from]
     Io(std::io::Error),
@@ -0,0 +1,115 @@
+use lib_models::{Command, Key, ModifierState};
+
+/// Keyboard state of the client, replayed to a new session so the server
+/// starts from the keys actually held.
+#[derive(Debug, Default)]
+pub struct InputState {
+    keymap: Option<String>,
+    modifiers: Option<ModifierState>,
+    focused: bool,
+    pressed: Vec<Key>,
+}
+
+impl InputState {
+    /// Updates the state with a command on its way to the server.
+    pub fn track(&mut self, command: &Command) {
+        match command {
+            Command::Keymap(keymap) => self.keymap = Some(keymap.clone()),
+            Command::Modifiers(modifiers) => self.modifiers = Some(*modifiers),
+            Command::FocusEntered { pressed } => {
+                self.focused = true;
+                self.pressed = pressed.clone();
+            }
+            Command::FocusLost => {
+                self.focused = false;
+                self.pressed.clear();
+            }
+            Command::KeyPressed(key) if !self.pressed.contains(key) => self.pressed.push(*key),
+            Command::KeyReleased(key) => self.pressed.retain(|held| held != key),
+            Command::Batch(commands) => commands.iter().for_each(|command| self.track(command)),
+            _ => {}
+        }
+    }
+
+    /// Commands bringing a new session to this state, in the order the
+    /// compositor sends them: keymap, focus, modifiers.
+    pub fn resync(&self) -> Vec<Command> {
+        let mut commands = Vec::new();
+        if let Some(keymap) = &self.keymap {
+            commands.push(Command::Keymap(keymap.clone()));
+        }
+        if self.focused {
+            commands.push(Command::FocusEntered {
+                pressed: self.pressed.clone(),
+            });
+        }
+        if let Some(modifiers) = self.modifiers {
+            commands.push(Command::Modifiers(modifiers));
+        }
+        commands
+    }
+}
+
+/// Keyboard commands are never queued offline, [`InputState::resync`] stands
+/// for them.
+pub fn is_keyboard(command: &Command) -> bool {
+    matches!(
+        command,
+        Command::Keymap(_)
+            | Command::Modifiers(_)
+            | Command::FocusEntered { .. }
+            | Command::FocusLost
+            | Command::KeyPressed(_)
+            | Command::KeyReleased(_)
+    )
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::CommandKind;
+
+    #[test]
+    fn test_resync() -> Result<()> {
+        let mut state = InputState::default();
+        assert!(state.resync().is_empty());
+
+        state.track(&Command::Keymap("xkb_keymap {}".to_string()));
+        state.track(&Command::FocusEntered {
+            pressed: vec![Key::LeftShift],
+        });
+        state.track(&Command::KeyPressed(Key::A));
+        state.track(&Command::KeyPressed(Key::A));
+        state.track(&Command::KeyReleased(Key::LeftShift));
+        state.track(&Command::Modifiers(ModifierState::default()));
+        state.track(&Command::MoveMouse { x: 1, y: 1 });
+
+        let commands = state.resync();
+        let kinds: Vec<CommandKind> = commands.iter().map(Command::kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                CommandKind::Keymap,
+                CommandKind::FocusEntered,
+                CommandKind::Modifiers
+            ]
+        );
+        let Command::FocusEntered { pressed } = &commands[1] else {
+            return Err("not a focus".into());
+        };
+        assert_eq!(pressed, &[Key::A]);
+
+        // Without the focus no keys are held.
+        state.track(&Command::FocusLost);
+        let kinds: Vec<CommandKind> = state.resync().iter().map(Command::kind).collect();
+        assert_eq!(kinds, [CommandKind::Keymap, CommandKind::Modifiers]);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -1,41 +1,348 @@
 use lib_protocol::handler::Handler;
 
 mod error;
+mod input;
+mod offline;
+mod session;
 
+use crate::Metrics;
 pub use error::{Error, Result};
-use lib_quic::{datagram::Datagram, quinn, Ssrc};
+use input::InputState;
+use lib_codec::{
+    frame::{self, Flags},
+    stream::Encoder,
+};
+use lib_models::{Command, CommandBatch, CommandKind, Delivery, SequencedCommand};
+use lib_quic::Ssrc;
+pub use offline::OfflinePolicy;
+use offline::OfflineQueue;
+pub use session::Session;
+use std::time::Duration;
+use tracing::info;
+
+/// Commands coalesced in one batch at most, so it fits in a datagram.
+const MAX_BATCH: usize = 64;
 
 pub enum HandlerCommand {
     Command(lib_models::Command),
+    /// A new session, the keyboard state and the offline queue are replayed
+    /// on it.
+    Connected(Session),
+    /// The session closed, input goes to the offline queue until the next
+    /// one.
+    Disconnected,
+    /// The config was reloaded with these settings.
+    Reconfigure {
+        batch_interval: Duration,
+        offline_policy: OfflinePolicy,
+    },
 }
 
 pub struct EventHandler {
-    #[allow(unused)]
-    connection: quinn::Connection,
-    datagram: Datagram,
+    session: Option<Session>,
+    /// Number of the last command sent on the stream.
+    seq: u64,
+    /// Number of the last command sent on either channel.
+    id: u64,
+    metrics: Metrics,
     encode_buf: [u8; 1024],
+    input: InputState,
+    offline: OfflineQueue,
+    /// How long commands are collected in a batch, zero to only take the ones
+    /// already queued.
+    batch_interval: Duration,
+    /// Message that ended a batch, handled next.
+    deferred: Option<HandlerCommand>,
     command_rx: flume::Receiver<HandlerCommand>,
     command_tx: flume::Sender<HandlerCommand>,
 }
 
 impl EventHandler {
-    pub fn new(connection: quinn::Connection) -> Self {
+    /// Starts disconnected, sessions come as [`HandlerCommand::Connected`].
+    pub fn new() -> Self {
         let (command_tx, command_rx) = flume::bounded(1000);
 
-        let datagram = Datagram::new(connection.clone());
-
         Self {
-            connection,
-            datagram,
+            session: None,
+            seq: 0,
+            id: 0,
+            metrics: Metrics::default(),
             encode_buf: [0; 1024],
+            input: InputState::default(),
+            offline: OfflineQueue::default(),
+            batch_interval: Duration::ZERO,
+            deferred: None,
             command_tx,
             command_rx,
         }
     }
 
+    pub fn with_batch_interval(mut self, batch_interval: Duration) -> Self {
+        self.batch_interval = batch_interval;
+        self
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    pub fn with_offline_policy(mut self, policy: OfflinePolicy) -> Self {
+        self.offline = OfflineQueue::new(policy);
+        self
+    }
+
     pub fn sender(&self) -> flume::Sender<HandlerCommand> {
         self.command_tx.clone()
     }
+
+    /// Adds the pointer commands queued after `first`, and those coming within
+    /// the batch interval, to a batch. Any other command ends the batch.
+    async fn collect_batch(&mut self, first: Command) -> HandlerCommand {
+        if !first.is_batchable() {
+            return HandlerCommand::Command(first);
+        }
+
+        let deadline = tokio::time::Instant::now() + self.batch_interval;
+        let mut batch = CommandBatch::default();
+        batch.push(first);
+
+        while batch.len() < MAX_BATCH {
+            let next = match self.command_rx.try_recv() {
+                Ok(next) => next,
+                Err(_) if self.batch_interval.is_zero() => break,
+                Err(_) => {
+                    match tokio::time::timeout_at(deadline, self.command_rx.recv_async()).await {
+                        Ok(Ok(next)) => next,
+                        _ => break,
+                    }
+                }
+            };
+            match next {
+                HandlerCommand::Command(command) if command.is_batchable() => batch.push(command),
+                next => {
+                    self.deferred = Some(next);
+                    break;
+                }
+            }
+        }
+
+        HandlerCommand::Command(batch.take().unwrap_or(Command::Batch(Vec::new())))
+    }
+
+    /// The current session, unless its connection is already closed.
+    fn session(&self) -> Option<&Session> {
+        self.session.as_ref().filter(|session| !session.is_closed())
+    }
+
+    /// Numbers from zero in the new session and replays the keyboard state,
+    /// then the input queued offline.
+    async fn resume(&mut self, session: Session) {
+        self.session = Some(session);
+        self.seq = 0;
+        self.id = 0;
+
+        let resync = self.input.resync();
+        let queued = self.offline.take();
+        info!(
+            "Session resumed, {} keyboard and {} queued commands replayed",
+            resync.len(),
+            queued.len()
+        );
+
+        for command in resync {
+            self.send(command).await;
+        }
+        if !queued.is_empty() {
+            self.send_batch(queued).await;
+        }
+    }
+
+    async fn send(&mut self, command: Command) {
+        self.input.track(&command);
+        let Some(session) = self.session() else {
+            self.offline.push(command);
+            return;
+        };
+
+        if !session.supports(command.kind()) {
+            tracing::debug!("{:?} not supported by the server, dropped", command.kind());
+            return;
+        }
+
+        match command.delivery() {
+            Delivery::Datagram => {
+                let command = self.stamp(SequencedCommand::new(self.seq, command));
+                // A batch too large for a datagram goes on the stream instead.
+                if let Err(command) = self.send_datagram(command) {
+                    self.send_reliable_command(command.command).await;
+                }
+            }
+            Delivery::Reliable => self.send_reliable_command(command).await,
+        }
+    }
+
+    /// Numbers the command and stamps it with the server clock.
+    fn stamp(&mut self, command: SequencedCommand) -> SequencedCommand {
+        self.id += 1;
+        self.metrics.sent();
+        command.with_stamp(self.id, self.metrics.server_now())
+    }
+
+    /// Sends the commands of a batch the server supports, one by one when it
+    /// doesn't know batches. Only pointer commands are batched, the others go
+    /// on their own in order.
+    async fn send_batch(&mut self, commands: Vec<Command>) {
+        let Some(session) = self
+            .session()
+            .filter(|session| session.supports(CommandKind::Batch))
+        else {
+            for command in commands {
+                self.send(command).await;
+            }
+            return;
+        };
+
+        let supported: Vec<Command> = commands
+            .into_iter()
+            .filter(|command| match session.supports(command.kind()) {
+                true => true,
+                false => {
+                    tracing::debug!("{:?} not supported by the server, dropped", command.kind());
+                    false
+                }
+            })
+            .collect();
+
+        let mut batch = CommandBatch::default();
+        for command in supported {
+            if command.is_batchable() {
+                batch.push(command);
+                continue;
+            }
+
+            // Sent on its own, after the pointer commands before it.
+            if let Some(pointer) = batch.take() {
+                self.send(pointer).await;
+            }
+            self.send(command).await;
+        }
+        if let Some(command) = batch.take() {
+            self.send(command).await;
+        }
+    }
+
+    /// Returns the command back when it doesn't fit in the encode buffer.
+    fn send_datagram(
+        &mut self,
+        command: SequencedCommand,
+    ) -> core::result::Result<(), SequencedCommand> {
+        let Some(session) = self.session.as_ref() else {
+            return Ok(());
+        };
+        let encoded = match frame::encode_to_slice(&command, Flags::CHECKSUM, &mut self.encode_buf)
+        {
+            Ok(encoded) => encoded,
+            Err(e) if command.command.kind() == CommandKind::Batch => {
+                tracing::debug!("Batch not sent as a datagram: {e}");
+                return Err(command);
+            }
+            Err(e) => {
+                tracing::error!("Error occured in encode: {}", e);
+                return Ok(());
+            }
+        };
+
+        if let Err(e) = session.datagram.send(
+            &self.encode_buf[0..encoded],
+            0,
+            lib_quic::datagram::DatagramType::Command,
+            Ssrc(1),
+        ) {
+            tracing::error!("Error occured in send: {}", e)
+        }
+
+        Ok(())
+    }
+
+    async fn send_reliable_command(&mut self, command: Command) {
+        // Clipboard transfers ride their own stream so a large paste doesn't
+        // hold the keys back. They take no number, input never waits for them.
+        if command.kind().is_clipboard() {
+            let command = self.stamp(SequencedCommand::new(self.seq, command));
+            if let Err(command) = self.send_reliable(Channel::Clipboard, command).await {
+                tracing::warn!("{:?} not sent, dropped", command.command.kind());
+            }
+            return;
+        }
+
+        self.seq += 1;
+        let command = self.stamp(SequencedCommand::new(self.seq, command));
+        if let Err(command) = self.send_reliable(Channel::Input, command).await {
+            // The server would hold every later datagram waiting for it.
+            self.seq -= 1;
+            // The reconnect resyncs the keyboard and replays the pointer
+            // commands kept offline, a lost release doesn't stay held.
+            if let Some(session) = self.session() {
+                tracing::warn!("Input stream failed, reconnecting");
+                session.close();
+            }
+            self.offline.push(command.command);
+        }
+    }
+
+    /// Sends on the stream of `channel`, once more on a new stream when it
+    /// fails. Gives the command back when that fails too.
+    async fn send_reliable(
+        &mut self,
+        channel: Channel,
+        command: SequencedCommand,
+    ) -> core::result::Result<(), SequencedCommand> {
+        let Some(session) = self.session.as_mut() else {
+            return Err(command);
+        };
+
+        for _ in 0..2 {
+            let slot = match channel {
+                Channel::Input => &mut session.stream,
+                Channel::Clipboard => &mut session.clipboard_stream,
+            };
+            let stream = match slot {
+                Some(stream) => stream,
+                None => match session.connection.open_uni().await {
+                    Ok(stream) => slot.insert(Encoder::new(stream)),
+                    Err(e) => {
+                        tracing::error!("Error occured in open stream: {}", e);
+                        return Err(command);
+                    }
+                },
+            };
+
+            match stream.send(&command).await {
+                Ok(()) => return Ok(()),
+                Err(e) => {
+                    tracing::error!("Error occured in stream send: {}", e);
+                    *slot = None;
+                }
+            }
+        }
+
+        Err(command)
+    }
+}
+
+/// Reliable streams of a session.
+#[derive(Debug, Clone, Copy)]
+enum Channel {
+    /// Numbered commands the datagrams are ordered against.
+    Input,
+    Clipboard,
+}
+
+impl Default for EventHandler {
+    fn default() -> Self {
+        Self::new()
+    }
 }
 
 impl Handler for EventHandler {
@@ -44,27 +351,34 @@ impl Handler for EventHandler {
     type Message = HandlerCommand;
 
     async fn handle(&mut self, message: Self::Message) -> Result<bool> {
-        let _len = match message {
-            HandlerCommand::Command(command) => {
-                let encoded = lib_codec::encode_to_stack(&command, &mut self.encode_buf).unwrap();
-
-                if let Err(e) = self.datagram.send(
-                    &self.encode_buf[0..encoded],
-                    0,
-                    lib_quic::datagram::DatagramType::Command,
-                    Ssrc(1),
-                ) {
-                    tracing::error!("Error occured in send: {}", e)
-                }
+        match message {
+            HandlerCommand::Command(Command::Batch(commands)) => self.send_batch(commands).await,
+            HandlerCommand::Command(command) => self.send(command).await,
+            HandlerCommand::Connected(session) => self.resume(session).await,
+            HandlerCommand::Disconnected => {
+                info!("Session closed, input is kept offline");
+                self.session = None;
             }
-        };
+            HandlerCommand::Reconfigure {
+                batch_interval,
+                offline_policy,
+            } => {
+                self.batch_interval = batch_interval;
+                self.offline.set_policy(offline_policy);
+            }
+        }
 
         Ok(false)
     }
 
     async fn receive(&mut self) -> Result<Option<Self::Message>> {
+        if let Some(message) = self.deferred.take() {
+            return Ok(Some(message));
+        }
+
         match self.command_rx.recv_async().await {
-            Ok(command) => Ok(Some(command)),
+            Ok(HandlerCommand::Command(command)) => Ok(Some(self.collect_batch(command).await)),
+            Ok(message) => Ok(Some(message)),
             Err(e) => {
                 tracing::error!(
                     "Error occured while receiving in {}: {e}",
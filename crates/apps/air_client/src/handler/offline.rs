@@ -0,0 +1,225 @@
+use super::input::is_keyboard;
+use lib_models::{Command, CommandBatch, CommandKind, MouseButton};
+use std::str::FromStr;
+use tracing::{debug, warn};
+
+/// What happens to the input captured while the server is unreachable.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum OfflinePolicy {
+    /// Input is dropped but button releases, only the keyboard state is
+    /// resynced.
+    #[default]
+    Drop,
+    /// Input is queued, coalesced, up to a limit and sent on reconnect.
+    Buffer(usize),
+}
+
+impl FromStr for OfflinePolicy {
+    type Err = String;
+
+    /// `drop`, `buffer` or `buffer:<commands>`.
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        match text.trim().split_once(':') {
+            None if text.trim() == "drop" => Ok(Self::Drop),
+            None if text.trim() == "buffer" => Ok(Self::Buffer(DEFAULT_BUFFER_LEN)),
+            Some(("buffer", len)) => len
+                .trim()
+                .parse()
+                .map(Self::Buffer)
+                .map_err(|_| text.to_string()),
+            _ => Err(text.to_string()),
+        }
+    }
+}
+
+/// Commands kept offline by default with [`OfflinePolicy::Buffer`].
+const DEFAULT_BUFFER_LEN: usize = 256;
+
+/// Input captured while disconnected.
+///
+/// A click made offline is dropped whole rather than replayed late, and a
+/// release of a button pressed before the disconnect is always kept.
+#[derive(Debug, Default)]
+pub struct OfflineQueue {
+    policy: OfflinePolicy,
+    batch: CommandBatch,
+    /// Buttons whose press was dropped, their release goes too.
+    dropped_presses: Vec<MouseButton>,
+    dropped: usize,
+}
+
+impl OfflineQueue {
+    pub fn new(policy: OfflinePolicy) -> Self {
+        Self {
+            policy,
+            ..Default::default()
+        }
+    }
+
+    /// Applies to the commands pushed from now on, the queued ones stay.
+    pub fn set_policy(&mut self, policy: OfflinePolicy) {
+        self.policy = policy;
+    }
+
+    pub fn push(&mut self, command: Command) {
+        // The keyboard is resynced and pings are stale by the reconnect.
+        if is_keyboard(&command) || command.kind() == CommandKind::Ping {
+            return;
+        }
+
+        if let Command::MouseButtonReleased(button) = command {
+            if self.dropped_presses.contains(&button) {
+                self.dropped_presses.retain(|&dropped| dropped != button);
+                return;
+            }
+            if !self.cancel_press(button) {
+                self.batch.push(command);
+            }
+            return;
+        }
+
+        match self.policy {
+            OfflinePolicy::Buffer(len) if self.batch.len() < len => self.batch.push(command),
+            _ => {
+                if self.dropped == 0 && self.policy != OfflinePolicy::Drop {
+                    warn!("Offline buffer full, input dropped until the reconnect");
+                }
+                if let Command::MouseButtonPressed(button) = command {
+                    self.dropped_presses.push(button);
+                }
+                self.dropped += 1;
+            }
+        }
+    }
+
+    /// Removes the queued press of `button`, whether there was one.
+    fn cancel_press(&mut self, button: MouseButton) -> bool {
+        let mut commands = std::mem::take(&mut self.batch).into_commands();
+        let press = commands.iter().rposition(
+            |command| matches!(command, Command::MouseButtonPressed(pressed) if *pressed == button),
+        );
+        if let Some(index) = press {
+            commands.remove(index);
+        }
+        // Motion on both sides of the click coalesces again.
+        commands
+            .into_iter()
+            .for_each(|command| self.batch.push(command));
+        press.is_some()
+    }
+
+    /// Commands to send on reconnect.
+    pub fn take(&mut self) -> Vec<Command> {
+        if self.dropped > 0 {
+            debug!("{} commands dropped while disconnected", self.dropped);
+            self.dropped = 0;
+        }
+        self.dropped_presses.clear();
+        std::mem::take(&mut self.batch).into_commands()
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::Key;
+
+    fn kinds(queue: &mut OfflineQueue) -> Vec<CommandKind> {
+        queue.take().iter().map(Command::kind).collect()
+    }
+
+    #[test]
+    fn test_policy_from_str() -> Result<()> {
+        assert_eq!("drop".parse(), Ok(OfflinePolicy::Drop));
+        assert_eq!(
+            " buffer ".parse(),
+            Ok(OfflinePolicy::Buffer(DEFAULT_BUFFER_LEN))
+        );
+        assert_eq!("buffer: 16".parse(), Ok(OfflinePolicy::Buffer(16)));
+
+        for fx_text in ["", "keep", "buffer:", "buffer:many", "drop:1"] {
+            assert_eq!(
+                fx_text.parse::<OfflinePolicy>(),
+                Err(fx_text.to_string()),
+                "{fx_text:?}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_buffer() -> Result<()> {
+        let mut queue = OfflineQueue::new(OfflinePolicy::Buffer(2));
+
+        // Keyboard and pings aren't queued, motion coalesces.
+        queue.push(Command::KeyPressed(Key::A));
+        queue.push(Command::Ping { id: 1, sent_at: 0 });
+        queue.push(Command::MoveMouse { x: 1, y: 0 });
+        queue.push(Command::MoveMouse { x: 2, y: 0 });
+        queue.push(Command::MouseScroll(Default::default()));
+        // Past the limit.
+        queue.push(Command::SetMouse {
+            display: 0,
+            x: 1,
+            y: 1,
+        });
+
+        let commands = queue.take();
+        assert_eq!(commands.len(), 2);
+        assert!(matches!(commands[0], Command::MoveMouse { x: 3, y: 0 }));
+        assert!(queue.take().is_empty());
+
+        let mut queue = OfflineQueue::new(OfflinePolicy::Drop);
+        queue.push(Command::MoveMouse { x: 1, y: 0 });
+        assert!(queue.take().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_queue_clicks() -> Result<()> {
+        let mut queue = OfflineQueue::new(OfflinePolicy::Buffer(3));
+
+        // A click made offline is dropped whole, the motion around it merges.
+        queue.push(Command::MoveMouse { x: 1, y: 0 });
+        queue.push(Command::MouseButtonPressed(MouseButton::Left));
+        queue.push(Command::MoveMouse { x: 2, y: 0 });
+        queue.push(Command::MouseButtonReleased(MouseButton::Left));
+        let commands = queue.take();
+        assert_eq!(commands.len(), 1);
+        assert!(matches!(commands[0], Command::MoveMouse { x: 3, y: 0 }));
+
+        // The release of a button held before the disconnect is kept, even
+        // with the buffer full.
+        queue.push(Command::MoveMouse { x: 1, y: 0 });
+        queue.push(Command::MouseButtonPressed(MouseButton::Right));
+        queue.push(Command::MouseScroll(Default::default()));
+        queue.push(Command::MouseButtonPressed(MouseButton::Middle));
+        queue.push(Command::MouseButtonReleased(MouseButton::Middle));
+        queue.push(Command::MouseButtonReleased(MouseButton::Left));
+        assert_eq!(
+            kinds(&mut queue),
+            [
+                CommandKind::MoveMouse,
+                CommandKind::MouseButtonPressed,
+                CommandKind::MouseScroll,
+                CommandKind::MouseButtonReleased
+            ]
+        );
+
+        let mut queue = OfflineQueue::new(OfflinePolicy::Drop);
+        queue.push(Command::MouseButtonPressed(MouseButton::Left));
+        queue.push(Command::MouseButtonReleased(MouseButton::Left));
+        queue.push(Command::MouseButtonReleased(MouseButton::Right));
+        assert_eq!(kinds(&mut queue), [CommandKind::MouseButtonReleased]);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,40 @@
+use lib_codec::stream::Encoder;
+use lib_models::CommandKind;
+use lib_quic::{datagram::Datagram, quinn};
+
+/// A connection to the server after its handshake.
+pub struct Session {
+    pub(super) connection: quinn::Connection,
+    pub(super) datagram: Datagram,
+    /// Reliable ordered stream, opened on the first command that needs it.
+    pub(super) stream: Option<Encoder<quinn::SendStream>>,
+    /// Stream of the clipboard transfers, apart so they don't delay input.
+    pub(super) clipboard_stream: Option<Encoder<quinn::SendStream>>,
+    /// Commands the server applies, the others are dropped.
+    pub(super) supported: Vec<CommandKind>,
+}
+
+impl Session {
+    pub fn new(connection: quinn::Connection, supported: Vec<CommandKind>) -> Self {
+        Self {
+            datagram: Datagram::new(connection.clone()),
+            connection,
+            stream: None,
+            clipboard_stream: None,
+            supported,
+        }
+    }
+
+    pub fn supports(&self, command: CommandKind) -> bool {
+        self.supported.contains(&command)
+    }
+
+    /// Closes the connection, the supervisor connects again.
+    pub fn close(&self) {
+        self.connection.close(0u32.into(), b"input stream failed");
+    }
+
+    pub fn is_closed(&self) -> bool {
+        self.connection.close_reason().is_some()
+    }
+}
@@ -0,0 +1,103 @@
+//! Client side of the session handshake, see [`lib_models::handshake`].
+
+use crate::{config, Error, Result};
+use lib_codec::stream::{Decoder, Encoder};
+use lib_models::{
+    handshake::{
+        ClientHello, HandshakeError, HandshakeReply, ProtocolVersion, ServerHello,
+        PROTOCOL_VERSION, SUPPORTED_VERSIONS,
+    },
+    CommandKind, DisplayParams,
+};
+use lib_quic::quinn;
+use tracing::{info, warn};
+
+/// Identity of this client in the handshake.
+pub const CLIENT_NAME: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// Hello of this client, `clipboard_formats` is empty without clipboard.
+pub fn client_hello(clipboard_formats: Vec<String>) -> ClientHello {
+    let has_clipboard = !clipboard_formats.is_empty();
+
+    ClientHello {
+        name: CLIENT_NAME.to_string(),
+        commands: CommandKind::ALL
+            .into_iter()
+            .filter(|command| has_clipboard || !command.is_clipboard())
+            .collect(),
+        clipboard_formats,
+    }
+}
+
+/// Sends `hello` on a new control stream and waits for the server hello.
+pub async fn hello(connection: &quinn::Connection, hello: ClientHello) -> Result<ServerHello> {
+    let (send, recv) = connection.open_bi().await?;
+    let mut encoder = Encoder::new(send);
+    encoder.send(&ProtocolVersion(PROTOCOL_VERSION)).await?;
+    encoder.send(&hello).await?;
+    let _ = encoder.get_mut().finish();
+
+    let mut decoder = Decoder::new(recv);
+    // The version the server picked, or its own when it rejects ours.
+    let ProtocolVersion(version) = decoder.recv().await?.ok_or(Error::HandshakeClosed)?;
+    if !SUPPORTED_VERSIONS.contains(&version) {
+        return Err(Error::HandshakeRejected(HandshakeError::VersionMismatch {
+            client: PROTOCOL_VERSION,
+            server: version,
+        }));
+    }
+
+    let server = match decoder.recv().await? {
+        Some(HandshakeReply::Accepted(server)) => server,
+        Some(HandshakeReply::Rejected(e)) => return Err(Error::HandshakeRejected(e)),
+        None => return Err(Error::HandshakeClosed),
+    };
+    info!("Protocol version {version}");
+    log_server(&server);
+
+    Ok(server)
+}
+
+/// Logs the server and warns when the virtual output doesn't have the shape
+/// of the server display it stands for.
+fn log_server(server: &ServerHello) {
+    info!("Server: {}", server.name);
+    for (index, params) in server.displays.iter().enumerate() {
+        info!(
+            "Server display {index}: {}x{} at {},{} scale {}",
+            params.width(),
+            params.height(),
+            params.x(),
+            params.y(),
+            params.scale()
+        );
+    }
+
+    let unsupported: Vec<_> = CommandKind::ALL
+        .into_iter()
+        .filter(|command| !server.supports(*command))
+        .collect();
+    if !unsupported.is_empty() {
+        warn!("Server doesn't support {unsupported:?}, they won't be sent");
+    }
+
+    let index = config().SERVER_DISPLAY;
+    let Some(params) = server.displays.get(index as usize) else {
+        warn!("Server has no display {index}, pointer positions will be rejected");
+        return;
+    };
+    check_aspect_ratio(index, params);
+}
+
+fn check_aspect_ratio(index: u32, params: &DisplayParams) {
+    let ratio = |width: f64, height: f64| width / height;
+    let server = ratio(params.logical_width(), params.logical_height());
+    let client = ratio(config().WIDTH as f64, config().HEIGHT as f64);
+    if (server - client).abs() > 0.01 {
+        warn!(
+            "Virtual output {}x{} doesn't match the aspect ratio of server display {index}, the pointer will be stretched",
+            config().WIDTH,
+            config().HEIGHT
+        );
+    }
+}
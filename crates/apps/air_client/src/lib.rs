@@ -1,25 +1,40 @@
 // region:    --- Modules
 
+use lib_config::Settings;
 use tracing::{debug, info};
 use tracing_subscriber::EnvFilter;
 
 // -- Modules
+mod answers;
+mod cli;
+mod clipboard;
 mod config;
 mod dispatcher;
 mod display;
 mod error;
 mod handler;
+mod handshake;
+mod metrics;
+mod reload;
+mod supervisor;
 
 // -- Flatten
-pub use config::config;
+pub use answers::listen as listen_answers;
+pub use cli::Cli;
+pub use clipboard::sync as sync_clipboard;
+pub use config::{config, Config};
 pub use dispatcher::{Dispatcher, DispatcherTrait};
 pub use display::VirtualDisplay;
 pub use error::{Error, Result};
-pub use handler::{EventHandler, HandlerCommand};
+pub use handler::{EventHandler, HandlerCommand, OfflinePolicy, Session};
+pub use handshake::{client_hello, hello, CLIENT_NAME};
+pub use metrics::Metrics;
+pub use reload::watch_config;
+pub use supervisor::{ConnectionState, Supervisor};
 
 // endregion: --- Modules
 
-pub fn init() -> Result<()> {
+pub fn init(settings: &Settings) -> Result<()> {
     // LOGGING INITIALIZATION
     tracing_subscriber::fmt()
         // .without_time() // For early development
@@ -31,6 +46,10 @@ pub fn init() -> Result<()> {
 
     // CONFIG INITIALIZATION
     info!("Loading config...");
+    if let Some(path) = settings.path() {
+        info!("From {}, profile {:?}", path.display(), settings.profile());
+    }
+    Config::init_from(Config::load(settings)?)?;
     let config = config();
     debug!("{:?}", config);
 
@@ -1,6 +1,12 @@
-use air_client::{config, Dispatcher, DispatcherTrait, EventHandler, Result};
+use air_client::{
+    client_hello, config, sync_clipboard, watch_config, Cli, Dispatcher, DispatcherTrait, Error,
+    EventHandler, Metrics, Result, Supervisor, CLIENT_NAME,
+};
+use clap::Parser;
+use lib_models::clipboard::ClipboardSync;
 use lib_protocol::handler::Handler;
-use lib_quic::{client::QuicClient, tls::TlsLoader};
+use lib_quic::tls::TlsLoader;
+use lib_tls::{load_certs, Identity};
 use std::{
     path::Path,
     sync::{
@@ -9,27 +15,113 @@ use std::{
     },
     thread,
 };
-use tracing::info;
+use tracing::{error, info, warn};
+
+/// Self-signed server certificate the client pinned before the server had an
+/// authority, trusted when there is no `SERVER_CA`.
+const LEGACY_SERVER_CERT: &str = "./certs/cert.pem";
 
 #[tokio::main(flavor = "multi_thread", worker_threads = 2)]
 async fn main() -> Result<()> {
-    air_client::init()?;
+    let cli = Cli::parse();
+    let settings = cli.settings()?;
+    air_client::init(&settings)?;
 
     TlsLoader::init_provider();
     TlsLoader::debug_cipher_info();
 
-    let address = config().ADDRESS;
-
-    let client = QuicClient::new(Path::new("./certs/cert.pem"))
-        .await
-        .unwrap();
-    let conn = client.connect(address, "localhost").await.unwrap();
+    let identity = Identity::load_or_create(
+        &config().CLIENT_CERT,
+        &config().CLIENT_KEY,
+        vec![CLIENT_NAME.replace(' ', "-")],
+    )?;
+    let server_ca = &config().SERVER_CA;
+    let legacy_cert = Path::new(LEGACY_SERVER_CERT);
+    let server_certs = match server_ca.exists() {
+        true => load_certs(server_ca)?,
+        false if legacy_cert.exists() => {
+            warn!(
+                "No server authority in {}, pinning the server certificate {LEGACY_SERVER_CERT}",
+                server_ca.display()
+            );
+            load_certs(legacy_cert)?
+        }
+        false => {
+            error!(
+                "No server authority in {}, copy the ca.pem of `air_server certs export`",
+                server_ca.display()
+            );
+            return Err(Error::ServerCaMissing(server_ca.clone()));
+        }
+    };
+    let endpoint = lib_tls::endpoint::client(server_certs, &identity)?;
 
-    info!("✅ Client connected to server");
+    let clipboard = config()
+        .CLIPBOARD
+        .open()
+        .unwrap_or_else(|e| {
+            warn!("Clipboard not synchronised, it can't be opened: {e}");
+            None
+        })
+        .map(|clipboard| ClipboardSync::new(clipboard, config().CLIPBOARD_MAX_SIZE));
+    let formats = clipboard.as_ref().map(ClipboardSync::formats);
 
+    let metrics = Metrics::default();
     let is_running = Arc::new(AtomicBool::new(false));
-    let event_handler = EventHandler::new(conn);
-    let mut dispatcher = Dispatcher::init(event_handler.sender(), is_running.clone()).unwrap();
+    let event_handler = EventHandler::new()
+        .with_batch_interval(config().BATCH_INTERVAL)
+        .with_offline_policy(config().OFFLINE_POLICY)
+        .with_metrics(metrics.clone());
+
+    let (clipboard_tx, clipboard_rx) = flume::bounded(4);
+    let (state_tx, state_rx) = flume::unbounded();
+    let supervisor = Supervisor::new(
+        endpoint,
+        identity.fingerprint(),
+        client_hello(formats.unwrap_or_default()),
+        event_handler.sender(),
+        clipboard_tx,
+    )
+    .with_metrics(metrics.clone())
+    .with_state(state_tx);
+    let supervisor_handle = tokio::spawn(supervisor.run());
+
+    let mut metrics_handles = Vec::new();
+    if let Some(period) = config().PING_INTERVAL {
+        metrics_handles.push(tokio::spawn(
+            metrics.clone().ping_every(event_handler.sender(), period),
+        ));
+    }
+    if let Some(period) = config().METRICS_LOG_INTERVAL {
+        metrics_handles.push(tokio::spawn(metrics.clone().report_every(period)));
+    }
+    if let Some(address) = config().METRICS_ADDRESS {
+        let metrics = metrics.clone();
+        metrics_handles.push(tokio::spawn(async move {
+            if let Err(e) = lib_metrics::prometheus::serve(address, move || metrics.render()).await
+            {
+                error!("Metrics endpoint stopped: {e}");
+            }
+        }));
+    }
+    // Without a local clipboard the answers for it are dropped.
+    let clipboard_handle = clipboard.map(|sync| {
+        tokio::spawn(sync_clipboard(
+            event_handler.sender(),
+            sync,
+            clipboard_rx,
+            config().CLIPBOARD_POLL,
+        ))
+    });
+    let (resize_tx, resize_rx) = flume::unbounded();
+    let reload_handle = tokio::spawn(watch_config(settings, event_handler.sender(), resize_tx));
+    let mut dispatcher = Dispatcher::init(
+        event_handler.sender(),
+        state_rx,
+        resize_rx,
+        is_running.clone(),
+    )
+    .unwrap();
 
     let _command_tx = event_handler.sender();
 
@@ -40,9 +132,15 @@ async fn main() -> Result<()> {
     is_running.store(false, Ordering::Relaxed);
 
     _ = dispatcher_handle.join();
+    supervisor_handle.abort();
+    reload_handle.abort();
     event_handler.abort();
+    metrics_handles.iter().for_each(|handle| handle.abort());
+    if let Some(clipboard_handle) = clipboard_handle {
+        clipboard_handle.abort();
+    }
 
-    info!("✅ Client disconnected from server");
+    info!("✅ Client stopped");
 
     Ok(())
 }
@@ -0,0 +1,167 @@
+//! Live metrics of the session: round trips of the pings, clock offset to the
+//! server and commands sent.
+
+use crate::HandlerCommand;
+use lib_metrics::{prometheus::Exposition, Latency, Rate};
+use lib_models::Command;
+use std::{
+    collections::VecDeque,
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc, Mutex,
+    },
+    time::Duration,
+};
+use tracing::{debug, info, info_span};
+
+/// Pongs the clock offset is picked from.
+const CLOCK_SAMPLES: usize = 16;
+
+/// Metrics of the session, cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    commands: Rate,
+    rtt: Latency,
+    pings: AtomicU64,
+    pongs: AtomicU64,
+    /// Round trip and clock offset of the last pongs.
+    clock: Mutex<VecDeque<(u64, i64)>>,
+}
+
+impl Metrics {
+    pub fn sent(&self) {
+        self.inner.commands.add(1);
+    }
+
+    /// Next ping to send.
+    pub fn ping(&self) -> Command {
+        Command::Ping {
+            id: self.inner.pings.fetch_add(1, Ordering::Relaxed) + 1,
+            sent_at: lib_metrics::now_micros(),
+        }
+    }
+
+    /// Records the round trip of a ping and the clock offset it shows.
+    pub fn pong(&self, sent_at: u64, received_at: u64) {
+        let now = lib_metrics::now_micros();
+        let rtt = now.saturating_sub(sent_at);
+        self.inner.pongs.fetch_add(1, Ordering::Relaxed);
+        self.inner.rtt.record(Duration::from_micros(rtt));
+
+        // The server got the ping halfway through the round trip.
+        let offset = received_at as i64 - (sent_at + rtt / 2) as i64;
+        let mut clock = self.inner.clock.lock().unwrap_or_else(|e| e.into_inner());
+        if clock.len() == CLOCK_SAMPLES {
+            clock.pop_front();
+        }
+        clock.push_back((rtt, offset));
+    }
+
+    /// Offset of the server clock, from the pong with the shortest round trip
+    /// as it has the least queuing in it.
+    pub fn clock_offset(&self) -> Option<i64> {
+        let clock = self.inner.clock.lock().unwrap_or_else(|e| e.into_inner());
+        clock
+            .iter()
+            .min_by_key(|(rtt, _)| *rtt)
+            .map(|(_, offset)| *offset)
+    }
+
+    /// Server clock in microseconds since the Unix epoch, 0 before the first
+    /// pong.
+    pub fn server_now(&self) -> u64 {
+        match self.clock_offset() {
+            Some(offset) => lib_metrics::now_micros().saturating_add_signed(offset),
+            None => 0,
+        }
+    }
+
+    pub fn rtt(&self) -> &Latency {
+        &self.inner.rtt
+    }
+
+    pub fn pings_lost(&self) -> u64 {
+        let pongs = self.inner.pongs.load(Ordering::Relaxed);
+        self.inner
+            .pings
+            .load(Ordering::Relaxed)
+            .saturating_sub(pongs)
+    }
+
+    pub fn render(&self) -> String {
+        let inner = &self.inner;
+        Exposition::default()
+            .counter(
+                "air_client_commands_total",
+                "Commands sent.",
+                inner.commands.total(),
+            )
+            .gauge(
+                "air_client_commands_per_second",
+                "Commands sent per second.",
+                inner.commands.per_second(),
+            )
+            .summary(
+                "air_client_rtt_seconds",
+                "Round trip of the pings.",
+                &inner.rtt,
+            )
+            .counter(
+                "air_client_pings_total",
+                "Pings sent.",
+                inner.pings.load(Ordering::Relaxed),
+            )
+            .gauge(
+                "air_client_pings_lost",
+                "Pings without a pong, lost or still on the way.",
+                self.pings_lost() as f64,
+            )
+            .gauge(
+                "air_client_clock_offset_seconds",
+                "Offset of the server clock.",
+                self.clock_offset().unwrap_or_default() as f64 / 1e6,
+            )
+            .finish()
+    }
+
+    /// Sends a ping every `period` until the event handler is gone.
+    pub async fn ping_every(self, sender: flume::Sender<HandlerCommand>, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        loop {
+            interval.tick().await;
+            if sender
+                .send_async(HandlerCommand::Command(self.ping()))
+                .await
+                .is_err()
+            {
+                debug!("Event handler closed, pings stopped");
+                return;
+            }
+        }
+    }
+
+    /// Logs the metrics every `period`, never returns.
+    pub async fn report_every(self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let _span = info_span!("metrics").entered();
+            info!(
+                commands_per_second = self.inner.commands.per_second(),
+                rtt_p50 = ?self.inner.rtt.p50(),
+                rtt_p99 = ?self.inner.rtt.p99(),
+                pings_lost = self.pings_lost(),
+                clock_offset_us = ?self.clock_offset(),
+                "📊 Session metrics"
+            );
+        }
+    }
+}
@@ -0,0 +1,62 @@
+//! Follows the config file while the client runs.
+
+use crate::{config, Config, HandlerCommand, Result};
+use lib_config::{FileWatch, Settings};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the file is checked for changes.
+const PERIOD: Duration = Duration::from_secs(1);
+
+/// Reloads the config file when it changes. A new resolution goes to the
+/// dispatcher on `resize_tx`, the batching and the offline policy to the
+/// handler on `handler_tx`, the settings needing a restart are reported. A
+/// file that doesn't load keeps the current config.
+pub async fn watch_config(
+    mut settings: Settings,
+    handler_tx: flume::Sender<HandlerCommand>,
+    resize_tx: flume::Sender<(u32, u32)>,
+) {
+    let mut file = FileWatch::new(settings.path());
+    let mut interval = tokio::time::interval(PERIOD);
+    loop {
+        interval.tick().await;
+        if !file.changed() {
+            continue;
+        }
+
+        match reload(&settings, &handler_tx, &resize_tx).await {
+            Ok(reloaded) => settings = reloaded,
+            Err(e) => warn!("Config not reloaded, keeping the current one: {e}"),
+        }
+    }
+}
+
+async fn reload(
+    settings: &Settings,
+    handler_tx: &flume::Sender<HandlerCommand>,
+    resize_tx: &flume::Sender<(u32, u32)>,
+) -> Result<Settings> {
+    let settings = settings.reload()?;
+    let cfg = Config::load(&settings)?;
+
+    let old = config();
+    let changes = Config::reload(cfg);
+    let new = config();
+    info!("Config reloaded");
+    lib_config::report(&changes);
+
+    if (old.WIDTH, old.HEIGHT) != (new.WIDTH, new.HEIGHT) {
+        let _ = resize_tx.send((new.WIDTH, new.HEIGHT));
+    }
+    if (old.BATCH_INTERVAL, old.OFFLINE_POLICY) != (new.BATCH_INTERVAL, new.OFFLINE_POLICY) {
+        let _ = handler_tx
+            .send_async(HandlerCommand::Reconfigure {
+                batch_interval: new.BATCH_INTERVAL,
+                offline_policy: new.OFFLINE_POLICY,
+            })
+            .await;
+    }
+
+    Ok(settings)
+}
@@ -0,0 +1,226 @@
+//! Keeps the client connected: reconnects with exponential backoff and hands
+//! every new session to the event handler.
+
+use crate::{config, hello, listen_answers, Error, HandlerCommand, Metrics, Result, Session};
+use lib_models::{
+    handshake::{ClientHello, UNTRUSTED_DEVICE},
+    Answer, CommandKind,
+};
+use lib_quic::quinn;
+use lib_tls::{endpoint::peer_fingerprint, pairing_code, Fingerprint};
+use std::time::Duration;
+use tracing::{error, info, warn};
+
+/// Connection to the server as shown by the dispatcher.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConnectionState {
+    Connecting,
+    Connected,
+    Disconnected,
+}
+
+pub struct Supervisor {
+    endpoint: quinn::Endpoint,
+    /// Fingerprint of this device, shown with the pairing code.
+    device: Fingerprint,
+    hello: ClientHello,
+    metrics: Metrics,
+    handler_tx: flume::Sender<HandlerCommand>,
+    clipboard_tx: flume::Sender<Answer>,
+    state_tx: Option<flume::Sender<ConnectionState>>,
+}
+
+impl Supervisor {
+    /// `endpoint` authenticates as `device`. Server answers for the clipboard
+    /// go to `clipboard_tx`, with the formats of every new server first.
+    pub fn new(
+        endpoint: quinn::Endpoint,
+        device: Fingerprint,
+        hello: ClientHello,
+        handler_tx: flume::Sender<HandlerCommand>,
+        clipboard_tx: flume::Sender<Answer>,
+    ) -> Self {
+        Self {
+            endpoint,
+            device,
+            hello,
+            metrics: Metrics::default(),
+            handler_tx,
+            clipboard_tx,
+            state_tx: None,
+        }
+    }
+
+    pub fn with_metrics(mut self, metrics: Metrics) -> Self {
+        self.metrics = metrics;
+        self
+    }
+
+    /// Reports every change of the connection state to `state_tx`.
+    pub fn with_state(mut self, state_tx: flume::Sender<ConnectionState>) -> Self {
+        self.state_tx = Some(state_tx);
+        self
+    }
+
+    /// Connects until the event handler is gone.
+    pub async fn run(self) {
+        let mut backoff = Backoff::new(config().RECONNECT_MIN, config().RECONNECT_MAX);
+
+        loop {
+            self.set_state(ConnectionState::Connecting);
+            match self.connect().await {
+                Ok(connection) => {
+                    backoff.reset();
+                    let reason = connection.closed().await;
+                    match is_untrusted(&reason) {
+                        true => error!("Server doesn't trust this device any more"),
+                        false => warn!("Connection lost: {reason}"),
+                    }
+
+                    if self
+                        .handler_tx
+                        .send_async(HandlerCommand::Disconnected)
+                        .await
+                        .is_err()
+                    {
+                        return;
+                    }
+                    self.set_state(ConnectionState::Disconnected);
+                }
+                Err(Error::Connection(reason)) if is_untrusted(&reason) => error!(
+                    "Server doesn't trust device {}, pair it with PAIRING=true on the server",
+                    self.device
+                ),
+                Err(e) => warn!("Connection to {} failed: {e}", config().ADDRESS),
+            }
+
+            if self.handler_tx.is_disconnected() {
+                return;
+            }
+
+            let delay = backoff.next_delay();
+            info!("Reconnecting in {delay:?}");
+            tokio::time::sleep(delay).await;
+        }
+    }
+
+    /// Connects, runs the handshake and starts the session.
+    async fn connect(&self) -> Result<quinn::Connection> {
+        let connection = self
+            .endpoint
+            .connect(config().ADDRESS, &config().SERVER_NAME)?
+            .await?;
+        if let Some(server) = peer_fingerprint(&connection) {
+            info!(
+                "Device {}, pairing code {}",
+                self.device,
+                pairing_code(&server, &self.device)
+            );
+        }
+
+        let server = match hello(&connection, self.hello.clone()).await {
+            Ok(server) => server,
+            // The server closed the connection under the handshake.
+            Err(e) => return Err(connection.close_reason().map(Error::from).unwrap_or(e)),
+        };
+
+        info!("✅ Client connected to server");
+
+        // Clipboard sync only runs when both sides have one.
+        let clipboard_formats = match server.supports(CommandKind::ClipboardChunk) {
+            true => server.clipboard_formats,
+            false => Vec::new(),
+        };
+        let _ = self
+            .clipboard_tx
+            .try_send(Answer::ClipboardFormats(clipboard_formats));
+        // Ends with the connection.
+        tokio::spawn(listen_answers(
+            connection.clone(),
+            self.clipboard_tx.clone(),
+            self.metrics.clone(),
+        ));
+
+        let session = Session::new(connection.clone(), server.commands);
+        if self
+            .handler_tx
+            .send_async(HandlerCommand::Connected(session))
+            .await
+            .is_err()
+        {
+            connection.close(0u32.into(), b"client stopped");
+        }
+        self.set_state(ConnectionState::Connected);
+
+        Ok(connection)
+    }
+
+    fn set_state(&self, state: ConnectionState) {
+        if let Some(state_tx) = &self.state_tx {
+            let _ = state_tx.send(state);
+        }
+    }
+}
+
+fn is_untrusted(reason: &quinn::ConnectionError) -> bool {
+    matches!(
+        reason,
+        quinn::ConnectionError::ApplicationClosed(close)
+            if close.error_code == UNTRUSTED_DEVICE.into()
+    )
+}
+
+/// Delays between reconnects, doubled up to `max`.
+#[derive(Debug)]
+struct Backoff {
+    min: Duration,
+    max: Duration,
+    next: Duration,
+}
+
+impl Backoff {
+    fn new(min: Duration, max: Duration) -> Self {
+        Self {
+            min,
+            max,
+            next: min,
+        }
+    }
+
+    fn next_delay(&mut self) -> Duration {
+        let delay = self.next;
+        self.next = (self.next * 2).min(self.max);
+        delay
+    }
+
+    fn reset(&mut self) {
+        self.next = self.min;
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_backoff() -> Result<()> {
+        let fx_delays = [100, 200, 400, 500, 500].map(Duration::from_millis);
+        let mut backoff = Backoff::new(Duration::from_millis(100), Duration::from_millis(500));
+
+        let delays: Vec<Duration> = fx_delays.iter().map(|_| backoff.next_delay()).collect();
+        assert_eq!(delays, fx_delays);
+
+        // A connection that came up starts over from the shortest delay.
+        backoff.reset();
+        assert_eq!(backoff.next_delay(), Duration::from_millis(100));
+        assert_eq!(backoff.next_delay(), Duration::from_millis(200));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,264 @@
+//! The `certs` command: a private authority issues the server certificate
+//! and one certificate per client, so the clients only have to trust the
+//! authority.
+
+use crate::{config, CertsCommand, Error, Result};
+use lib_tls::{
+    load_certs,
+    pki::{self, CertificateAuthority, Usage},
+    trust::{DeviceStatus, TrustStore},
+    Fingerprint, Identity,
+};
+use std::{fs, path::Path, path::PathBuf};
+use tracing::info;
+
+/// Common name of the authorities created by `certs init`.
+const CA_NAME: &str = "air-link CA";
+
+/// Files of the server identity and the devices it trusts.
+#[derive(Debug, Clone)]
+pub struct CertPaths {
+    pub cert: PathBuf,
+    pub key: PathBuf,
+    pub ca_cert: PathBuf,
+    pub ca_key: PathBuf,
+    pub trusted_devices: PathBuf,
+}
+
+impl CertPaths {
+    pub fn from_config() -> Self {
+        let config = config();
+        Self {
+            cert: config.TLS_CERT.clone(),
+            key: config.TLS_KEY.clone(),
+            ca_cert: config.TLS_CA_CERT.clone(),
+            ca_key: config.TLS_CA_KEY.clone(),
+            trusted_devices: config.TRUSTED_DEVICES.clone(),
+        }
+    }
+
+    /// Identity the server listens with.
+    pub fn server_identity(&self) -> Result<Identity> {
+        require(&self.cert)?;
+        Ok(Identity::load(&self.cert, &self.key)?)
+    }
+
+    pub fn authority(&self) -> Result<CertificateAuthority> {
+        require(&self.ca_cert)?;
+        Ok(CertificateAuthority::load(&self.ca_cert, &self.ca_key)?)
+    }
+
+    /// Issues the server certificate for `names`, with the authority created
+    /// on the first run.
+    pub fn init(&self, names: &[String], force: bool) -> Result<Identity> {
+        if self.cert.exists() && !force {
+            return Err(Error::CertificateExists(self.cert.clone()));
+        }
+
+        let authority = match self.ca_cert.exists() {
+            true => self.authority()?,
+            false => self.create_authority()?,
+        };
+
+        Ok(authority.issue(Usage::Server, names, &self.cert, &self.key)?)
+    }
+
+    /// Replaces the server key and certificate, keeping its names.
+    pub fn rotate(&self, new_authority: bool) -> Result<Identity> {
+        require(&self.cert)?;
+        let names = pki::names(&load_certs(&self.cert)?[0])?;
+
+        let authority = match new_authority {
+            true => self.create_authority()?,
+            false => self.authority()?,
+        };
+
+        Ok(authority.issue(Usage::Server, &names, &self.cert, &self.key)?)
+    }
+
+    /// Writes `ca.pem`, `client-cert.pem` and `client-key.pem` of a new
+    /// device to `out` and trusts it. An existing bundle is kept unless
+    /// `force` is set.
+    pub fn export(&self, name: &str, out: &Path, force: bool) -> Result<Identity> {
+        let bundle = ["ca.pem", "client-cert.pem", "client-key.pem"].map(|file| out.join(file));
+        if let Some(existing) = bundle.iter().find(|path| path.exists()) {
+            if !force {
+                return Err(Error::CertificateExists(existing.clone()));
+            }
+        }
+
+        let authority = self.authority()?;
+        let [ca, cert, key] = bundle;
+        let identity = authority.issue(Usage::Client, &[name.to_string()], &cert, &key)?;
+        fs::copy(&self.ca_cert, ca)?;
+
+        let mut store = TrustStore::load(&self.trusted_devices)?;
+        store.trust(identity.fingerprint(), name);
+        store.save(&self.trusted_devices)?;
+
+        Ok(identity)
+    }
+
+    pub fn revoke(&self, fingerprint: &Fingerprint) -> Result<()> {
+        let mut store = TrustStore::load(&self.trusted_devices)?;
+        if !store.revoke(fingerprint) {
+            return Err(Error::DeviceUnknown(*fingerprint));
+        }
+
+        Ok(store.save(&self.trusted_devices)?)
+    }
+
+    /// Runs a `certs` command.
+    pub fn run(&self, command: CertsCommand) -> Result<()> {
+        match command {
+            CertsCommand::Init { sans, force } => {
+                let identity = self.init(&sans, force)?;
+                println!("Server {} for {}", identity.fingerprint(), sans.join(", "));
+            }
+            CertsCommand::List => list(self)?,
+            CertsCommand::Rotate { ca } => {
+                let identity = self.rotate(ca)?;
+                println!("Server {}", identity.fingerprint());
+                if ca {
+                    println!("Export a new bundle for every client");
+                }
+            }
+            CertsCommand::Export { name, out, force } => {
+                let identity = self.export(&name, &out, force)?;
+                println!("Device {} trusted as {name}", identity.fingerprint());
+                println!("Bundle in {}", out.display());
+            }
+            CertsCommand::Revoke { fingerprint } => {
+                let fingerprint = fingerprint.parse()?;
+                self.revoke(&fingerprint)?;
+                println!("Device {fingerprint} revoked");
+            }
+        }
+
+        Ok(())
+    }
+
+    fn create_authority(&self) -> Result<CertificateAuthority> {
+        let authority = CertificateAuthority::create(CA_NAME, &self.ca_cert, &self.ca_key)?;
+        info!(
+            "New certificate authority {} in {}",
+            Fingerprint::of(authority.cert()),
+            self.ca_cert.display()
+        );
+
+        Ok(authority)
+    }
+}
+
+fn list(paths: &CertPaths) -> Result<()> {
+    if paths.ca_cert.exists() {
+        let cert = &load_certs(&paths.ca_cert)?[0];
+        println!("Authority {}", Fingerprint::of(cert));
+    }
+    if paths.cert.exists() {
+        let cert = &load_certs(&paths.cert)?[0];
+        println!(
+            "Server    {} {}",
+            Fingerprint::of(cert),
+            pki::names(cert)?.join(", ")
+        );
+    }
+
+    for device in TrustStore::load(&paths.trusted_devices)?.devices() {
+        let status = match device.status {
+            DeviceStatus::Trusted => "trusted",
+            DeviceStatus::Revoked => "revoked",
+        };
+        println!("{status}   {} {}", device.fingerprint, device.name);
+    }
+
+    Ok(())
+}
+
+fn require(path: &Path) -> Result<()> {
+    match path.exists() {
+        true => Ok(()),
+        false => Err(Error::CertificateMissing(path.to_path_buf())),
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_certs() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("air-server-certs-{}", std::process::id()));
+        let paths = CertPaths {
+            cert: dir.join("cert.pem"),
+            key: dir.join("key.pem"),
+            ca_cert: dir.join("ca.pem"),
+            ca_key: dir.join("ca-key.pem"),
+            trusted_devices: dir.join("trusted_devices"),
+        };
+        let names = ["desktop.lan".to_string(), "10.0.0.2".to_string()];
+
+        assert!(matches!(
+            paths.rotate(false),
+            Err(Error::CertificateMissing(_))
+        ));
+        let server = paths.init(&names, false)?;
+        assert!(matches!(
+            paths.init(&names, false),
+            Err(Error::CertificateExists(_))
+        ));
+
+        // Same names, new key.
+        let rotated = paths.rotate(false)?;
+        assert_ne!(rotated.fingerprint(), server.fingerprint());
+        assert_eq!(pki::names(&rotated.certs[0])?, names);
+        assert_eq!(
+            paths.server_identity()?.fingerprint(),
+            rotated.fingerprint()
+        );
+
+        let out = dir.join("export");
+        let laptop = paths.export("laptop", &out, false)?;
+        assert_eq!(
+            load_certs(&out.join("ca.pem"))?,
+            load_certs(&paths.ca_cert)?
+        );
+        // The bundle of the laptop isn't overwritten by accident.
+        assert!(matches!(
+            paths.export("phone", &out, false),
+            Err(Error::CertificateExists(_))
+        ));
+        let phone = paths.export("phone", &out, true)?;
+        assert_eq!(
+            Identity::load(&out.join("client-cert.pem"), &out.join("client-key.pem"))?
+                .fingerprint(),
+            phone.fingerprint()
+        );
+        let store = TrustStore::load(&paths.trusted_devices)?;
+        assert_eq!(
+            store.status(&laptop.fingerprint()),
+            Some(DeviceStatus::Trusted)
+        );
+
+        paths.revoke(&laptop.fingerprint())?;
+        let store = TrustStore::load(&paths.trusted_devices)?;
+        assert_eq!(
+            store.status(&laptop.fingerprint()),
+            Some(DeviceStatus::Revoked)
+        );
+        assert!(matches!(
+            paths.revoke(&Fingerprint::of(b"unknown")),
+            Err(Error::DeviceUnknown(_))
+        ));
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
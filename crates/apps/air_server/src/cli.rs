@@ -0,0 +1,74 @@
+//! Command line of the server. Without a command it serves.
+
+use crate::Result;
+use clap::{Parser, Subcommand};
+use lib_config::{ConfigArgs, Settings};
+use std::path::PathBuf;
+
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(flatten)]
+    pub config: ConfigArgs,
+    /// Address to listen on, overrides `ADDRESS`.
+    #[arg(long, global = true)]
+    pub address: Option<String>,
+    #[command(subcommand)]
+    pub command: Option<CliCommand>,
+}
+
+impl Cli {
+    /// Settings of the config file and profile, under the flags.
+    pub fn settings(&self) -> Result<Settings> {
+        let mut settings = self.config.settings(env!("CARGO_PKG_NAME"))?;
+        if let Some(address) = &self.address {
+            settings = settings.with_override("ADDRESS", address.as_str());
+        }
+
+        Ok(settings)
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CliCommand {
+    /// Manages the certificate authority, the server certificate and the
+    /// trusted devices.
+    Certs {
+        #[command(subcommand)]
+        command: CertsCommand,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum CertsCommand {
+    /// Creates the authority when missing and issues the server certificate.
+    Init {
+        /// DNS name or IP address the clients connect to, repeatable.
+        #[arg(long = "san", default_values = ["localhost", "127.0.0.1"])]
+        sans: Vec<String>,
+        /// Replaces an existing server certificate.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Prints the fingerprints of the authority, the server and the devices.
+    List,
+    /// Issues a new server key and certificate for the same names.
+    Rotate {
+        /// Creates a new authority first, every client needs a new bundle.
+        #[arg(long)]
+        ca: bool,
+    },
+    /// Issues a client certificate, writes the files the client needs and
+    /// trusts the device.
+    Export {
+        /// Name of the device in the trusted devices.
+        name: String,
+        #[arg(long, default_value = "./certs/export")]
+        out: PathBuf,
+        /// Replaces an existing bundle in `out`.
+        #[arg(long)]
+        force: bool,
+    },
+    /// Rejects a device from its next connection on.
+    Revoke { fingerprint: String },
+}
@@ -1,34 +1,240 @@
 //! Crate config
 
 use crate::error::{Error, Result};
-use std::{net::SocketAddr, sync::OnceLock};
+use crate::error_policy::ErrorPolicies;
+use crate::input::InputBackend;
+use lib_config::{Apply, Settings};
+use lib_models::{
+    clipboard::{ClipboardBackend, DEFAULT_MAX_SIZE},
+    DisplayParams,
+};
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::PathBuf,
+    sync::{Arc, RwLock},
+    time::Duration,
+};
 
-static INSTANCE: OnceLock<Config> = OnceLock::new();
+/// Listens on every interface without an `ADDRESS`.
+const DEFAULT_ADDRESS: SocketAddr = SocketAddr::new(IpAddr::V4(Ipv4Addr::UNSPECIFIED), 54321);
 
-pub fn config() -> &'static Config {
-    INSTANCE.get_or_init(|| {
-        Config::load_from_env()
-            .unwrap_or_else(|ex| panic!("FATAL - WHOLE LOADING CONF - Cause: {ex:?}"))
-    })
+/// Replaced configs live on while a caller still holds them.
+static INSTANCE: RwLock<Option<Arc<Config>>> = RwLock::new(None);
+
+/// Config given to [`Config::init_from`] or the last [`Config::reload`], or
+/// the one of the environment.
+pub fn config() -> Arc<Config> {
+    if let Some(config) = INSTANCE.read().unwrap().as_ref() {
+        return config.clone();
+    }
+
+    INSTANCE
+        .write()
+        .unwrap()
+        .get_or_insert_with(|| {
+            let config = Config::load(&Settings::from_env())
+                .unwrap_or_else(|ex| panic!("FATAL - WHOLE LOADING CONF - Cause: {ex:?}"));
+            Arc::new(config)
+        })
+        .clone()
 }
 
 #[allow(non_snake_case)]
-#[derive(Debug)]
+#[derive(Debug, PartialEq)]
 pub struct Config {
     pub ADDRESS: SocketAddr,
+    /// Screens of the desktop, reported to the clients.
+    pub SCREENS: Vec<DisplayParams>,
+    pub INPUT_BACKENDS: Vec<InputBackend>,
+    pub RECORDING_PATH: Option<PathBuf>,
+    pub INPUT_ERROR_POLICY: ErrorPolicies,
+    pub CLIPBOARD: ClipboardBackend,
+    pub CLIPBOARD_MAX_SIZE: usize,
+    pub CLIPBOARD_POLL: Duration,
+    /// Held keys and buttons are released after this long without commands.
+    pub IDLE_RELEASE: Option<Duration>,
+    /// Serves the metrics in the Prometheus text format when set.
+    pub METRICS_ADDRESS: Option<SocketAddr>,
+    pub METRICS_LOG_INTERVAL: Option<Duration>,
+    /// Certificate chain and key of the server.
+    pub TLS_CERT: PathBuf,
+    pub TLS_KEY: PathBuf,
+    /// Authority issuing the server and client certificates.
+    pub TLS_CA_CERT: PathBuf,
+    pub TLS_CA_KEY: PathBuf,
+    /// Store of the paired client devices.
+    pub TRUSTED_DEVICES: PathBuf,
+    /// Unknown devices are paired on the terminal instead of rejected.
+    pub PAIRING: bool,
+    /// Rules restricting what the clients may do, everything is allowed
+    /// without them.
+    pub POLICY: Option<PathBuf>,
 }
 
 impl Config {
-    fn load_from_env() -> Result<Self> {
+    pub fn load(settings: &Settings) -> Result<Self> {
+        // `SCREEN` is the single screen of older configs.
+        let screens = settings
+            .get("SCREENS")
+            .or_else(|| settings.get("SCREEN"))
+            .unwrap_or("1920x1080".to_string())
+            .split(',')
+            .map(str::parse)
+            .collect::<core::result::Result<Vec<DisplayParams>, _>>()
+            .map_err(|_| Error::ConfigWrongFormat("SCREENS"))?;
+
+        let input_backends = match settings.get("INPUT_BACKENDS") {
+            Some(backends) => backends
+                .split(',')
+                .map(str::parse)
+                .collect::<Result<Vec<InputBackend>>>()?,
+            None => InputBackend::defaults(),
+        };
+
+        let input_error_policy = ErrorPolicies::parse(
+            &settings.get("INPUT_ERROR_POLICY").unwrap_or_default(),
+            settings.get_parse("INPUT_RETRIES")?.unwrap_or(2),
+        )?;
+
+        let clipboard = match settings.get("CLIPBOARD") {
+            Some(clipboard) => clipboard
+                .parse()
+                .map_err(|_| Error::ConfigWrongFormat("CLIPBOARD"))?,
+            None => ClipboardBackend::default(),
+        };
+
         Ok(Self {
-            ADDRESS: grapple_utils::envs::get_parse("ADDRESS")
-                .unwrap_or("192.168.0.151:54321".parse().unwrap()),
+            ADDRESS: settings.get_parse("ADDRESS")?.unwrap_or(DEFAULT_ADDRESS),
+            SCREENS: screens,
+            INPUT_BACKENDS: input_backends,
+            RECORDING_PATH: settings.get("RECORDING_PATH").map(PathBuf::from),
+            INPUT_ERROR_POLICY: input_error_policy,
+            CLIPBOARD: clipboard,
+            CLIPBOARD_MAX_SIZE: settings
+                .get_parse("CLIPBOARD_MAX_SIZE")?
+                .unwrap_or(DEFAULT_MAX_SIZE),
+            CLIPBOARD_POLL: Duration::from_millis(
+                settings.get_parse("CLIPBOARD_POLL_MS")?.unwrap_or(500),
+            ),
+            // 0 disables it.
+            IDLE_RELEASE: Some(settings.get_parse("IDLE_RELEASE_MS")?.unwrap_or(30_000))
+                .filter(|ms| *ms > 0)
+                .map(Duration::from_millis),
+            METRICS_ADDRESS: settings.get_parse("METRICS_ADDRESS")?,
+            // 0 disables it.
+            METRICS_LOG_INTERVAL: Some(settings.get_parse("METRICS_LOG_INTERVAL_S")?.unwrap_or(60))
+                .filter(|s| *s > 0)
+                .map(Duration::from_secs),
+            TLS_CERT: settings
+                .get("TLS_CERT")
+                .unwrap_or("./certs/cert.pem".to_string())
+                .into(),
+            TLS_KEY: settings
+                .get("TLS_KEY")
+                .unwrap_or("./certs/key.pem".to_string())
+                .into(),
+            TLS_CA_CERT: settings
+                .get("TLS_CA_CERT")
+                .unwrap_or("./certs/ca.pem".to_string())
+                .into(),
+            TLS_CA_KEY: settings
+                .get("TLS_CA_KEY")
+                .unwrap_or("./certs/ca-key.pem".to_string())
+                .into(),
+            TRUSTED_DEVICES: settings
+                .get("TRUSTED_DEVICES")
+                .unwrap_or("./certs/trusted_devices".to_string())
+                .into(),
+            PAIRING: settings.get_parse("PAIRING")?.unwrap_or(false),
+            POLICY: settings.get("POLICY").map(PathBuf::from),
         })
     }
 
     pub fn init_from(cfg: Self) -> Result<()> {
-        INSTANCE
-            .set(cfg)
-            .map_err(|_| Error::ConfigAlreadyInitialized)
+        let mut instance = INSTANCE.write().unwrap();
+        if instance.is_some() {
+            return Err(Error::ConfigAlreadyInitialized);
+        }
+        *instance = Some(Arc::new(cfg));
+
+        Ok(())
+    }
+
+    /// Makes `cfg` the config, returns the settings it changes.
+    pub fn reload(cfg: Self) -> Vec<(&'static str, Apply)> {
+        let changes = config().changes(&cfg);
+        *INSTANCE.write().unwrap() = Some(Arc::new(cfg));
+
+        changes
+    }
+
+    /// Settings differing in `new`, with when they take effect. Only the
+    /// policy is followed by a running server.
+    pub fn changes(&self, new: &Self) -> Vec<(&'static str, Apply)> {
+        let mut changes = Vec::new();
+        macro_rules! compare {
+            ($apply:expr => $($field:ident),+) => {
+                $(if self.$field != new.$field {
+                    changes.push((stringify!($field), $apply));
+                })+
+            };
+        }
+
+        compare!(Apply::Now => POLICY);
+        compare!(Apply::Restart =>
+            ADDRESS, SCREENS, INPUT_BACKENDS, RECORDING_PATH, INPUT_ERROR_POLICY, CLIPBOARD,
+            CLIPBOARD_MAX_SIZE, CLIPBOARD_POLL, IDLE_RELEASE, METRICS_ADDRESS,
+            METRICS_LOG_INTERVAL, TLS_CERT, TLS_KEY, TLS_CA_CERT, TLS_CA_KEY, TRUSTED_DEVICES,
+            PAIRING
+        );
+
+        changes
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_load_errors() -> Result<()> {
+        let config = Config::load(&Settings::from_env().with_override("SCREENS", "800x600"))?;
+        assert_eq!(config.SCREENS, [DisplayParams::new(800, 600)]);
+
+        let load = |key: &str, value: &str| {
+            Config::load(
+                &Settings::from_env()
+                    .with_override("SCREENS", "800x600")
+                    .with_override(key, value),
+            )
+        };
+        assert!(matches!(
+            load("SCREENS", "1920x1080,wide"),
+            Err(Error::ConfigWrongFormat("SCREENS"))
+        ));
+        assert!(matches!(
+            load("CLIPBOARD", "paper"),
+            Err(Error::ConfigWrongFormat("CLIPBOARD"))
+        ));
+        assert!(matches!(
+            load("INPUT_BACKENDS", "uinput,xdotool"),
+            Err(Error::InputBackendUnknown(backend)) if backend == "xdotool"
+        ));
+        assert!(matches!(
+            load("INPUT_ERROR_POLICY", "backend"),
+            Err(Error::ConfigWrongFormat("INPUT_ERROR_POLICY"))
+        ));
+        assert!(matches!(
+            load("PAIRING", "maybe"),
+            Err(Error::Config(lib_config::Error::ValueWrongFormat { key, .. })) if key == "PAIRING"
+        ));
+
+        Ok(())
     }
 }
+
+// endregion: --- Tests
@@ -0,0 +1,137 @@
+//! Clients are devices known by the fingerprint of their certificate. Unknown
+//! devices are paired on the first connect when pairing is on, after the
+//! operator checks both sides show the same code.
+
+use crate::{Error, Result};
+use lib_quic::quinn;
+use lib_tls::{
+    endpoint::peer_fingerprint,
+    pairing_code,
+    trust::{Device, DeviceStatus, TrustStore},
+    Fingerprint,
+};
+use std::{io::BufRead, path::PathBuf, sync::Arc, time::Duration};
+use tokio::sync::{mpsc, Mutex};
+use tracing::{info, warn};
+
+/// How long the operator has to confirm a pairing.
+const PAIRING_TIMEOUT: Duration = Duration::from_secs(60);
+
+/// Trusted devices of the server, read from the store on every connection so
+/// a revocation applies to the next one.
+#[derive(Debug, Clone)]
+pub struct Devices {
+    store: PathBuf,
+    /// Fingerprint of the server certificate, half of the pairing code.
+    server: Fingerprint,
+    pairing: bool,
+    /// One pairing prompt at a time, and one writer of the store. Holds the
+    /// lines of the terminal, read from the first prompt on.
+    terminal: Arc<Mutex<Option<mpsc::UnboundedReceiver<String>>>>,
+}
+
+impl Devices {
+    pub fn new(store: PathBuf, server: Fingerprint) -> Self {
+        Self {
+            store,
+            server,
+            pairing: false,
+            terminal: Arc::default(),
+        }
+    }
+
+    /// Unknown devices are paired instead of rejected.
+    pub fn with_pairing(mut self, pairing: bool) -> Self {
+        self.pairing = pairing;
+        self
+    }
+
+    /// The device on `connection` when it is trusted.
+    ///
+    /// Known devices don't wait for a pairing prompt, only unknown ones take
+    /// the pairing lock.
+    pub async fn authorize(&self, connection: &quinn::Connection) -> Result<Device> {
+        let fingerprint = peer_fingerprint(connection).ok_or(Error::DeviceUnauthenticated)?;
+
+        if let Some(device) = known(&TrustStore::load(&self.store)?, fingerprint)? {
+            return Ok(device);
+        }
+        if !self.pairing {
+            return Err(Error::DeviceUnknown(fingerprint));
+        }
+
+        let mut terminal = self.terminal.lock().await;
+        // Paired or revoked while this one waited for the prompt.
+        let store = TrustStore::load(&self.store)?;
+        if let Some(device) = known(&store, fingerprint)? {
+            return Ok(device);
+        }
+        let lines = terminal.get_or_insert_with(read_terminal);
+        let name = connection.remote_address().ip().to_string();
+        self.pair(lines, store, fingerprint, name).await
+    }
+
+    /// Asks the operator on the terminal to trust the device.
+    async fn pair(
+        &self,
+        lines: &mut mpsc::UnboundedReceiver<String>,
+        mut store: TrustStore,
+        fingerprint: Fingerprint,
+        name: String,
+    ) -> Result<Device> {
+        // Typed before the prompt, e.g. a late answer to the last one.
+        while lines.try_recv().is_ok() {}
+
+        let code = pairing_code(&self.server, &fingerprint);
+        println!("🔐 Pairing request from {name}");
+        println!("   Device: {fingerprint}");
+        println!("   Code:   {code}");
+        println!("Trust it if the client shows the same code [y/N]:");
+
+        let answer = tokio::time::timeout(PAIRING_TIMEOUT, lines.recv()).await;
+        let confirmed = matches!(
+            answer,
+            Ok(Some(line)) if line.trim().eq_ignore_ascii_case("y")
+        );
+        if !confirmed {
+            warn!("Pairing of {fingerprint} refused");
+            return Err(Error::PairingRefused(fingerprint));
+        }
+
+        store.trust(fingerprint, &name);
+        store.save(&self.store)?;
+        info!("Device {fingerprint} paired as {name}");
+
+        Ok(Device {
+            fingerprint,
+            status: DeviceStatus::Trusted,
+            name,
+        })
+    }
+}
+
+/// The device when the store knows it, an error when it is revoked.
+fn known(store: &TrustStore, fingerprint: Fingerprint) -> Result<Option<Device>> {
+    match store.get(&fingerprint) {
+        Some(device) if device.status == DeviceStatus::Trusted => Ok(Some(device.clone())),
+        Some(_) => Err(Error::DeviceRevoked(fingerprint)),
+        None => Ok(None),
+    }
+}
+
+/// Reads the terminal on a thread of its own for every prompt to come, a
+/// prompt that timed out leaves no read behind.
+fn read_terminal() -> mpsc::UnboundedReceiver<String> {
+    let (tx, rx) = mpsc::unbounded_channel();
+    std::thread::spawn(move || {
+        for line in std::io::stdin().lock().lines() {
+            let Ok(line) = line else {
+                return;
+            };
+            if tx.send(line).is_err() {
+                return;
+            }
+        }
+    });
+    rx
+}
@@ -1,6 +1,9 @@
 //! Main Crate Error
 
 use derive_more::derive::From;
+use lib_models::{handshake::HandshakeError, CommandKind, InputErrorKind};
+
+use crate::input::InputBackend;
 
 pub type Result<T> = core::result::Result<T, Error>;
 
@@ -8,19 +11,108 @@ pub type Result<T> = core::result::Result<T, Error>;
 pub enum Error {
     // -- Config
     ConfigAlreadyInitialized,
+    ConfigWrongFormat(&'static str),
+    PolicyWrongFormat {
+        line: usize,
+        content: String,
+    },
+
+    // -- Certificates
+    /// Create it with `air_server certs init`.
+    CertificateMissing(std::path::PathBuf),
+    /// Replace it with `--force` of `air_server certs init` or `export`.
+    CertificateExists(std::path::PathBuf),
+
+    // -- Handshake
+    HandshakeTimeout,
+    /// The control stream ended before the hello.
+    HandshakeClosed,
+    HandshakeRejected(HandshakeError),
+
+    // -- Devices
+    /// The client sent no certificate.
+    DeviceUnauthenticated,
+    DeviceUnknown(lib_tls::Fingerprint),
+    DeviceRevoked(lib_tls::Fingerprint),
+    PairingRefused(lib_tls::Fingerprint),
+
+    // -- Input
+    InputBackendUnknown(String),
+    InputBackendUnsupported(InputBackend),
+    InputBackendNotDetected,
+    NoInputBackend,
+    Input {
+        kind: InputErrorKind,
+        command: Option<CommandKind>,
+    },
 
     // -- Modules
 
     // -- Externals
     #[from]
+    Config(lib_config::Error),
+    #[from]
+    Codec(lib_codec::Error),
+    #[from]
     Quic(lib_quic::Error),
     #[from]
+    Connection(lib_quic::quinn::ConnectionError),
+    #[from]
     Envs(grapple_utils::envs::Error),
+    #[from]
+    Tls(lib_tls::Error),
+    #[from]
+    Enigo(enigo::NewConError),
 
     #[from]
     Io(std::io::Error), // as example
 }
 
+impl Error {
+    pub fn input(kind: InputErrorKind) -> Self {
+        Self::Input {
+            kind,
+            command: None,
+        }
+    }
+
+    /// Attaches the command being processed to an input error.
+    pub fn for_command(self, command: CommandKind) -> Self {
+        match self {
+            Self::Input { kind, .. } => Self::Input {
+                kind,
+                command: Some(command),
+            },
+            other => other,
+        }
+    }
+}
+
+impl From<enigo::InputError> for Error {
+    fn from(value: enigo::InputError) -> Self {
+        Self::input(InputErrorKind::Backend(value.to_string()))
+    }
+}
+
+impl From<lib_models::clipboard::Error> for Error {
+    fn from(value: lib_models::clipboard::Error) -> Self {
+        use lib_models::clipboard::Error as ClipboardError;
+
+        match value {
+            ClipboardError::TooLarge { size, max } => {
+                Self::input(InputErrorKind::TooLarge { size, max })
+            }
+            other => Self::input(InputErrorKind::Backend(other.to_string())),
+        }
+    }
+}
+
+impl From<lib_models::keymap::Error> for Error {
+    fn from(value: lib_models::keymap::Error) -> Self {
+        Self::input(InputErrorKind::Backend(value.to_string()))
+    }
+}
+
 // region:    --- Error Boilerplate
 
 impl core::fmt::Display for Error {
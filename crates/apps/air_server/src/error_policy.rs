@@ -0,0 +1,198 @@
+//! What to do with a command the input backend failed to inject.
+
+use crate::{Error, Result};
+use lib_models::{CommandKind, InputErrorKind};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorPolicy {
+    /// Report the failure and go on with the next command.
+    Skip,
+    /// Try the command again, then skip it when retries run out. Only
+    /// commands applying the same twice are retried, the others are skipped.
+    Retry,
+    /// Report the failure and close the connection.
+    Disconnect,
+}
+
+impl core::str::FromStr for ErrorPolicy {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "skip" => Ok(Self::Skip),
+            "retry" => Ok(Self::Retry),
+            "disconnect" => Ok(Self::Disconnect),
+            _ => Err(Error::ConfigWrongFormat("INPUT_ERROR_POLICY")),
+        }
+    }
+}
+
+/// Groups of [`InputErrorKind`] sharing a policy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorClass {
+    /// The command can't be expressed by the backend, retrying won't help.
+    Unsupported,
+    /// The backend itself failed.
+    Backend,
+}
+
+impl ErrorClass {
+    pub fn of(kind: &InputErrorKind) -> Self {
+        match kind {
+            InputErrorKind::UnsupportedKey(_)
+            | InputErrorKind::UnsupportedButton(_)
+            | InputErrorKind::UnsupportedChar(_)
+            | InputErrorKind::UnsupportedCommand
+            | InputErrorKind::UnknownDisplay(_)
+            | InputErrorKind::TooLarge { .. } => Self::Unsupported,
+            InputErrorKind::Backend(_) => Self::Backend,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ErrorPolicies {
+    pub unsupported: ErrorPolicy,
+    pub backend: ErrorPolicy,
+    /// Attempts made after the first failure under [`ErrorPolicy::Retry`].
+    pub retries: u32,
+}
+
+impl Default for ErrorPolicies {
+    fn default() -> Self {
+        Self {
+            unsupported: ErrorPolicy::Skip,
+            backend: ErrorPolicy::Retry,
+            retries: 2,
+        }
+    }
+}
+
+impl ErrorPolicies {
+    /// Overrides the defaults with `class=policy` pairs separated by commas,
+    /// e.g. `unsupported=skip,backend=disconnect`.
+    pub fn parse(value: &str, retries: u32) -> Result<Self> {
+        let mut policies = Self {
+            retries,
+            ..Default::default()
+        };
+
+        for pair in value.split(',').filter(|pair| !pair.trim().is_empty()) {
+            let (class, policy) = pair
+                .split_once('=')
+                .ok_or(Error::ConfigWrongFormat("INPUT_ERROR_POLICY"))?;
+            let policy = policy.parse()?;
+
+            match class.trim() {
+                "unsupported" => policies.unsupported = policy,
+                "backend" => policies.backend = policy,
+                _ => return Err(Error::ConfigWrongFormat("INPUT_ERROR_POLICY")),
+            }
+        }
+
+        Ok(policies)
+    }
+
+    pub fn get(&self, class: ErrorClass) -> ErrorPolicy {
+        match class {
+            ErrorClass::Unsupported => self.unsupported,
+            ErrorClass::Backend => self.backend,
+        }
+    }
+
+    /// Policy for a failed `command`, [`ErrorPolicy::Retry`] becomes
+    /// [`ErrorPolicy::Skip`] when running it again would repeat part of it.
+    pub fn for_command(&self, class: ErrorClass, command: CommandKind) -> ErrorPolicy {
+        match self.get(class) {
+            ErrorPolicy::Retry if !is_idempotent(command) => ErrorPolicy::Skip,
+            policy => policy,
+        }
+    }
+}
+
+/// Whether applying `command` twice leaves the same state as once. Text
+/// typed halfway would be typed again, a chunk pushed twice breaks the
+/// transfer.
+fn is_idempotent(command: CommandKind) -> bool {
+    matches!(
+        command,
+        CommandKind::SetMouse
+            | CommandKind::MouseButtonReleased
+            | CommandKind::KeyReleased
+            | CommandKind::FocusEntered
+            | CommandKind::FocusLost
+            | CommandKind::Modifiers
+            | CommandKind::Keymap
+            | CommandKind::ClipboardFormats
+    )
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::Key;
+
+    #[test]
+    fn test_parse_overrides_defaults() -> Result<()> {
+        let policies = ErrorPolicies::parse("backend=disconnect", 5)?;
+
+        assert_eq!(policies.unsupported, ErrorPolicy::Skip);
+        assert_eq!(policies.backend, ErrorPolicy::Disconnect);
+        assert_eq!(policies.retries, 5);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_wrong_format() {
+        assert!(ErrorPolicies::parse("backend", 0).is_err());
+        assert!(ErrorPolicies::parse("device=skip", 0).is_err());
+        assert!(ErrorPolicies::parse("backend=ignore", 0).is_err());
+    }
+
+    #[test]
+    fn test_retry_idempotent_only() -> Result<()> {
+        let policies = ErrorPolicies::default();
+
+        for (command, policy) in [
+            (CommandKind::SetMouse, ErrorPolicy::Retry),
+            (CommandKind::KeyReleased, ErrorPolicy::Retry),
+            (CommandKind::FocusEntered, ErrorPolicy::Retry),
+            (CommandKind::InputText, ErrorPolicy::Skip),
+            (CommandKind::ClipboardChunk, ErrorPolicy::Skip),
+            (CommandKind::MoveMouse, ErrorPolicy::Skip),
+        ] {
+            assert_eq!(
+                policies.for_command(ErrorClass::Backend, command),
+                policy,
+                "{command:?}"
+            );
+        }
+
+        let policies = ErrorPolicies::parse("backend=disconnect", 2)?;
+        assert_eq!(
+            policies.for_command(ErrorClass::Backend, CommandKind::InputText),
+            ErrorPolicy::Disconnect
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_class_of_kind() {
+        assert_eq!(
+            ErrorClass::of(&InputErrorKind::UnsupportedKey(Key::A)),
+            ErrorClass::Unsupported
+        );
+        assert_eq!(
+            ErrorClass::of(&InputErrorKind::Backend("io".to_string())),
+            ErrorClass::Backend
+        );
+    }
+}
+
+// endregion: --- Tests
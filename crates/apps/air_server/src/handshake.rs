@@ -0,0 +1,65 @@
+//! Server side of the session handshake, see [`lib_models::handshake`].
+
+use crate::{Error, Result};
+use lib_codec::stream::{Decoder, Encoder};
+use lib_models::handshake::{
+    negotiate_version, ClientHello, HandshakeError, HandshakeReply, ProtocolVersion, ServerHello,
+    PROTOCOL_VERSION,
+};
+use lib_quic::quinn;
+use std::time::Duration;
+
+/// Identity of this server in the handshake.
+pub const SERVER_NAME: &str = concat!(env!("CARGO_PKG_NAME"), " ", env!("CARGO_PKG_VERSION"));
+
+/// How long a client has to send its hello.
+const HELLO_TIMEOUT: Duration = Duration::from_secs(5);
+
+/// How long a rejection waits to be read before the connection closes.
+const REJECT_LINGER: Duration = Duration::from_secs(1);
+
+/// Reads the hello of the client on the control stream and answers with
+/// `hello`, or with the reason the client can't be served.
+pub async fn accept(connection: &quinn::Connection, hello: ServerHello) -> Result<ClientHello> {
+    tokio::time::timeout(HELLO_TIMEOUT, exchange(connection, hello))
+        .await
+        .map_err(|_| Error::HandshakeTimeout)?
+}
+
+async fn exchange(connection: &quinn::Connection, hello: ServerHello) -> Result<ClientHello> {
+    let (send, recv) = connection.accept_bi().await?;
+
+    let mut decoder = Decoder::new(recv);
+    let ProtocolVersion(client_version) = decoder.recv().await?.ok_or(Error::HandshakeClosed)?;
+    let version = negotiate_version(client_version);
+    // Ours when there is no common one, the client tells the mismatch from it.
+    let reply_version = version
+        .as_ref()
+        .map_or(PROTOCOL_VERSION, |version| *version);
+    let client = match version {
+        Ok(_) => decoder
+            .recv::<ClientHello>()
+            .await
+            .ok()
+            .flatten()
+            .ok_or(HandshakeError::Malformed),
+        Err(e) => Err(e),
+    };
+
+    let reply = match &client {
+        Ok(_) => HandshakeReply::Accepted(hello),
+        Err(e) => HandshakeReply::Rejected(e.clone()),
+    };
+    let mut encoder = Encoder::new(send);
+    encoder.send(&ProtocolVersion(reply_version)).await?;
+    encoder.send(&reply).await?;
+    let _ = encoder.get_mut().finish();
+
+    client.map_err(Error::HandshakeRejected)
+}
+
+/// Waits a little for the client to read a rejection before the connection is
+/// closed under it.
+pub async fn linger(connection: &quinn::Connection) {
+    let _ = tokio::time::timeout(REJECT_LINGER, connection.closed()).await;
+}
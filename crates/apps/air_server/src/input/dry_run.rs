@@ -0,0 +1,51 @@
+//! Backend that only logs what would have been injected.
+
+use super::InputSimulator;
+use crate::Result;
+use lib_models::{Key, MouseButton, MouseScroll};
+use tracing::info;
+
+#[derive(Debug, Default)]
+pub struct DryRunSimulator;
+
+impl InputSimulator for DryRunSimulator {
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()> {
+        info!("[dry-run] set_mouse x={x} y={y}");
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        info!("[dry-run] move_mouse x={x} y={y}");
+        Ok(())
+    }
+
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        info!("[dry-run] mouse_press {button:?}");
+        Ok(())
+    }
+
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        info!("[dry-run] mouse_release {button:?}");
+        Ok(())
+    }
+
+    fn key_press(&mut self, key: Key) -> Result<()> {
+        info!("[dry-run] key_press {key:?}");
+        Ok(())
+    }
+
+    fn key_release(&mut self, key: Key) -> Result<()> {
+        info!("[dry-run] key_release {key:?}");
+        Ok(())
+    }
+
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()> {
+        info!("[dry-run] scroll {scroll:?}");
+        Ok(())
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        info!("[dry-run] text {text:?}");
+        Ok(())
+    }
+}
@@ -0,0 +1,341 @@
+//! Input injection through `enigo`.
+//!
+//! Native backend on Windows and macOS. On Linux it talks to the X11 server or
+//! to the Wayland compositor (virtual keyboard/pointer protocols or libei).
+
+use super::{
+    scroll::{ScrollAccumulator, ScrollSteps},
+    InputSimulator,
+};
+use crate::{Error, Result};
+use enigo::{Enigo, Key, Settings};
+use lib_models::{keymap::PortableKey, InputErrorKind, MouseButton, MouseScroll};
+use std::collections::HashMap;
+
+/// Function keys by number, starting at F1.
+const FUNCTION_KEYS: [Key; 20] = [
+    Key::F1,
+    Key::F2,
+    Key::F3,
+    Key::F4,
+    Key::F5,
+    Key::F6,
+    Key::F7,
+    Key::F8,
+    Key::F9,
+    Key::F10,
+    Key::F11,
+    Key::F12,
+    Key::F13,
+    Key::F14,
+    Key::F15,
+    Key::F16,
+    Key::F17,
+    Key::F18,
+    Key::F19,
+    Key::F20,
+];
+
+pub struct EnigoSimulator {
+    inner: Enigo,
+    /// Keys pressed from a keysym by client key, released the same way.
+    translated: HashMap<lib_models::Key, Key>,
+    scroll: ScrollAccumulator,
+}
+
+impl EnigoSimulator {
+    /// Uses whatever connection `enigo` manages to establish.
+    pub fn new() -> Result<Self> {
+        Self::with_settings(Self::settings())
+    }
+
+    /// Connects to the X11 server from `DISPLAY`.
+    #[cfg(target_os = "linux")]
+    pub fn x11() -> Result<Self> {
+        let display = std::env::var("DISPLAY").map_err(|_| Error::InputBackendNotDetected)?;
+
+        Self::with_settings(Settings {
+            x11_display: Some(display),
+            ..Self::settings()
+        })
+    }
+
+    /// Connects to the Wayland compositor from `WAYLAND_DISPLAY`.
+    #[cfg(target_os = "linux")]
+    pub fn wayland() -> Result<Self> {
+        let display =
+            std::env::var("WAYLAND_DISPLAY").map_err(|_| Error::InputBackendNotDetected)?;
+
+        Self::with_settings(Settings {
+            wayland_display: Some(display),
+            ..Self::settings()
+        })
+    }
+
+    fn settings() -> Settings {
+        Settings {
+            linux_delay: 1,
+            windows_subject_to_mouse_speed_and_acceleration_level: false,
+            release_keys_when_dropped: true,
+            independent_of_keyboard_state: true,
+            ..Default::default()
+        }
+    }
+
+    fn with_settings(settings: Settings) -> Result<Self> {
+        let inner = Enigo::new(&settings)?;
+        Ok(Self {
+            inner,
+            translated: HashMap::new(),
+            scroll: ScrollAccumulator::default(),
+        })
+    }
+
+    /// Code of `key` taken by [`enigo::Keyboard::raw`] on this platform.
+    #[cfg(not(target_os = "windows"))]
+    fn raw_keycode(key: lib_models::Key) -> Result<u16> {
+        #[cfg(target_os = "macos")]
+        let code = key.to_mac();
+        // XKB keycode, the evdev code shifted by 8.
+        #[cfg(not(target_os = "macos"))]
+        let code = key.to_evdev().and_then(|code| u16::try_from(code + 8).ok());
+
+        code.ok_or(Error::input(InputErrorKind::UnsupportedKey(key)))
+    }
+
+    /// `enigo` key of the virtual key of `key`. On Windows `raw` takes a
+    /// scan code, so physical keys go through `key` as `Key::Other(vk)`.
+    #[cfg_attr(not(target_os = "windows"), allow(dead_code))]
+    fn virtual_key(key: lib_models::Key) -> Result<Key> {
+        key.to_vk()
+            .map(|vk| Key::Other(vk.into()))
+            .ok_or(Error::input(InputErrorKind::UnsupportedKey(key)))
+    }
+
+    /// Injects the physical `key`, independent of the server layout.
+    fn physical_key(&mut self, key: lib_models::Key, direction: enigo::Direction) -> Result<()> {
+        use enigo::Keyboard;
+
+        #[cfg(target_os = "windows")]
+        self.inner.key(Self::virtual_key(key)?, direction)?;
+        #[cfg(not(target_os = "windows"))]
+        self.inner.raw(Self::raw_keycode(key)?, direction)?;
+
+        Ok(())
+    }
+
+    /// `enigo` key typing the keysym of `key`, independent of the server
+    /// layout.
+    fn map_portable_key(key: PortableKey) -> Option<Key> {
+        let key = match key.keysym? {
+            0x20 => Key::Space,
+            0xff08 => Key::Backspace,
+            0xff09 => Key::Tab,
+            0xff0d | 0xff8d => Key::Return, // Return, KP_Enter
+            0xff1b => Key::Escape,
+            0xffff => Key::Delete,
+            0xff50 => Key::Home,
+            0xff51 => Key::LeftArrow,
+            0xff52 => Key::UpArrow,
+            0xff53 => Key::RightArrow,
+            0xff54 => Key::DownArrow,
+            0xff55 => Key::PageUp,
+            0xff56 => Key::PageDown,
+            0xff57 => Key::End,
+            0xffe1 => Key::LShift,
+            0xffe2 => Key::RShift,
+            0xffe3 => Key::LControl,
+            0xffe4 => Key::RControl,
+            0xffe5 => Key::CapsLock,
+            0xffe7 | 0xffe8 | 0xffeb | 0xffec => Key::Meta, // Meta_L/R, Super_L/R
+            0xffe9 | 0xffea => Key::Alt,
+            keysym @ 0xffbe..=0xffd1 => FUNCTION_KEYS[(keysym - 0xffbe) as usize],
+            _ => Key::Unicode(key.char()?),
+        };
+
+        Some(key)
+    }
+
+    /// Scrolls in 1/120 notches, `enigo` only scrolls whole notches.
+    #[cfg(target_os = "windows")]
+    fn scroll_axis(&mut self, steps: ScrollSteps, axis: enigo::Axis) -> Result<()> {
+        match steps.value120 {
+            0 => Ok(()),
+            value120 => send_wheel(value120, axis),
+        }
+    }
+
+    /// Scrolls in pixels, like a touchpad.
+    #[cfg(target_os = "macos")]
+    fn scroll_axis(&mut self, steps: ScrollSteps, axis: enigo::Axis) -> Result<()> {
+        use enigo::Mouse;
+        if steps.pixels != 0 {
+            self.inner.smooth_scroll(steps.pixels, axis)?;
+        }
+        Ok(())
+    }
+
+    /// Scrolls whole notches, X11 and the Wayland protocols have nothing
+    /// finer through `enigo`.
+    #[cfg(not(any(target_os = "windows", target_os = "macos")))]
+    fn scroll_axis(&mut self, steps: ScrollSteps, axis: enigo::Axis) -> Result<()> {
+        use enigo::Mouse;
+        if steps.notches != 0 {
+            self.inner.scroll(steps.notches, axis)?;
+        }
+        Ok(())
+    }
+
+    fn map_mouse_button(mouse_button: MouseButton) -> Result<enigo::Button> {
+        let button = match mouse_button {
+            MouseButton::Left => enigo::Button::Left,
+            MouseButton::Right => enigo::Button::Right,
+            MouseButton::Middle => enigo::Button::Middle,
+            MouseButton::Back => enigo::Button::Back,
+            MouseButton::Forward => enigo::Button::Forward,
+            button @ MouseButton::Other(_) => {
+                return Err(Error::input(InputErrorKind::UnsupportedButton(button)))
+            }
+        };
+
+        Ok(button)
+    }
+}
+
+/// Sends a wheel motion in 1/120 notches, positive down or right like the
+/// client sends it.
+#[cfg(target_os = "windows")]
+#[allow(unsafe_code)]
+fn send_wheel(value120: i32, axis: enigo::Axis) -> Result<()> {
+    use windows::Win32::UI::Input::KeyboardAndMouse::{
+        SendInput, INPUT, INPUT_0, INPUT_MOUSE, MOUSEEVENTF_HWHEEL, MOUSEEVENTF_WHEEL, MOUSEINPUT,
+    };
+
+    // A positive wheel delta scrolls up on Windows.
+    let (flags, delta) = match axis {
+        enigo::Axis::Vertical => (MOUSEEVENTF_WHEEL, -value120),
+        enigo::Axis::Horizontal => (MOUSEEVENTF_HWHEEL, value120),
+    };
+    let input = INPUT {
+        r#type: INPUT_MOUSE,
+        Anonymous: INPUT_0 {
+            mi: MOUSEINPUT {
+                dx: 0,
+                dy: 0,
+                // Signed delta in an unsigned field.
+                mouseData: delta as u32,
+                dwFlags: flags,
+                time: 0,
+                dwExtraInfo: 0,
+            },
+        },
+    };
+
+    // SAFETY: a single initialised INPUT, passed along with its size.
+    let sent = unsafe { SendInput(&[input], core::mem::size_of::<INPUT>() as i32) };
+    match sent {
+        1 => Ok(()),
+        _ => Err(Error::input(InputErrorKind::Backend(
+            std::io::Error::last_os_error().to_string(),
+        ))),
+    }
+}
+
+impl InputSimulator for EnigoSimulator {
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()> {
+        use enigo::{Coordinate, Mouse};
+        // enigo only moves to whole pixels.
+        let (x, y) = (x.round() as i32, y.round() as i32);
+        self.inner.move_mouse(x, y, Coordinate::Abs)?;
+        Ok(())
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        use enigo::{Coordinate, Mouse};
+        self.inner.move_mouse(x, y, Coordinate::Rel)?;
+        Ok(())
+    }
+
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        use enigo::Mouse;
+        let button = Self::map_mouse_button(button)?;
+        self.inner.button(button, enigo::Direction::Press)?;
+        Ok(())
+    }
+
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        use enigo::Mouse;
+        let button = Self::map_mouse_button(button)?;
+        self.inner.button(button, enigo::Direction::Release)?;
+        Ok(())
+    }
+
+    fn key_press(&mut self, key: lib_models::Key) -> Result<()> {
+        self.physical_key(key, enigo::Direction::Press)
+    }
+
+    fn key_release(&mut self, key: lib_models::Key) -> Result<()> {
+        use enigo::Keyboard;
+        let direction = enigo::Direction::Release;
+
+        match self.translated.remove(&key) {
+            Some(translated) => self.inner.key(translated, direction)?,
+            None => self.physical_key(key, direction)?,
+        }
+
+        Ok(())
+    }
+
+    fn key_press_translated(&mut self, key: PortableKey) -> Result<()> {
+        use enigo::Keyboard;
+
+        let Some(translated) = Self::map_portable_key(key) else {
+            return self.key_press(key.key);
+        };
+        self.inner.key(translated, enigo::Direction::Press)?;
+        self.translated.insert(key.key, translated);
+
+        Ok(())
+    }
+
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()> {
+        let (vertical, horizontal) = self.scroll.push(&scroll);
+        self.scroll_axis(vertical, enigo::Axis::Vertical)?;
+        self.scroll_axis(horizontal, enigo::Axis::Horizontal)
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        use enigo::Keyboard;
+        self.inner.text(text)?;
+        Ok(())
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_virtual_key_windows() -> Result<()> {
+        assert_eq!(
+            EnigoSimulator::virtual_key(lib_models::Key::A)?,
+            Key::Other(0x41)
+        );
+        assert_eq!(
+            EnigoSimulator::virtual_key(lib_models::Key::Delete)?,
+            Key::Other(0x2e)
+        );
+        assert_eq!(
+            EnigoSimulator::virtual_key(lib_models::Key::LeftShift)?,
+            Key::Other(0xa0)
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
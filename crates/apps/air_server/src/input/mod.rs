@@ -0,0 +1,158 @@
+mod dry_run;
+mod enigo;
+mod pressed;
+mod recording;
+mod scroll;
+#[cfg(target_os = "linux")]
+mod uinput;
+
+use crate::{config, Error, Result};
+use lib_models::{keymap::PortableKey, Key, MouseButton, MouseScroll};
+use tracing::{debug, info, warn};
+
+pub use self::enigo::EnigoSimulator;
+pub use dry_run::DryRunSimulator;
+pub use pressed::PressedTracker;
+pub use recording::{RecordedEntry, RecordedEvent, Recording, RecordingSimulator};
+#[cfg(target_os = "linux")]
+pub use uinput::{AbsoluteArea, EventSink, UinputSimulator};
+
+#[enum_dispatch::enum_dispatch]
+pub trait InputSimulator {
+    /// Moves to a position in desktop coordinates, fractions of a logical
+    /// pixel included.
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()>;
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()>;
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()>;
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()>;
+    fn key_press(&mut self, key: Key) -> Result<()>;
+    fn key_release(&mut self, key: Key) -> Result<()>;
+    /// Presses the symbol the client keymap gives for the key. Backends that
+    /// can't inject symbols press the physical key, its release must undo
+    /// whatever was pressed.
+    fn key_press_translated(&mut self, key: PortableKey) -> Result<()> {
+        self.key_press(key.key)
+    }
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()>;
+    fn text(&mut self, text: &str) -> Result<()>;
+}
+
+#[enum_dispatch::enum_dispatch(InputSimulator)]
+#[allow(clippy::large_enum_variant)] // One simulator per connection.
+pub enum Simulator {
+    #[cfg(target_os = "linux")]
+    Uinput(UinputSimulator),
+    Enigo(EnigoSimulator),
+    DryRun(DryRunSimulator),
+    Recording(RecordingSimulator),
+}
+
+/// Input backends that can be listed in the `INPUT_BACKENDS` config.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputBackend {
+    /// Linux uinput virtual devices.
+    Uinput,
+    /// `enigo` with its own platform detection.
+    Enigo,
+    /// `enigo` connected to the X11 server.
+    EnigoX11,
+    /// `enigo` connected to the Wayland compositor or libei.
+    EnigoWayland,
+    /// Only logs the events.
+    DryRun,
+    /// Keeps the events in memory.
+    Recording,
+}
+
+impl InputBackend {
+    /// Backends tried in order when none are configured.
+    pub fn defaults() -> Vec<Self> {
+        if cfg!(target_os = "linux") {
+            vec![
+                Self::Uinput,
+                Self::EnigoWayland,
+                Self::EnigoX11,
+                Self::DryRun,
+            ]
+        } else {
+            vec![Self::Enigo, Self::DryRun]
+        }
+    }
+}
+
+impl core::str::FromStr for InputBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "uinput" => Ok(Self::Uinput),
+            "enigo" => Ok(Self::Enigo),
+            "enigo-x11" => Ok(Self::EnigoX11),
+            "enigo-wayland" => Ok(Self::EnigoWayland),
+            "dry-run" => Ok(Self::DryRun),
+            "recording" => Ok(Self::Recording),
+            _ => Err(Error::InputBackendUnknown(s.to_string())),
+        }
+    }
+}
+
+impl core::fmt::Display for InputBackend {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        let name = match self {
+            Self::Uinput => "uinput",
+            Self::Enigo => "enigo",
+            Self::EnigoX11 => "enigo-x11",
+            Self::EnigoWayland => "enigo-wayland",
+            Self::DryRun => "dry-run",
+            Self::Recording => "recording",
+        };
+
+        write!(fmt, "{name}")
+    }
+}
+
+impl Simulator {
+    /// Creates the first of `backends` that initialises, a recording backend
+    /// writes into `recording`.
+    pub fn from_backends(backends: &[InputBackend], recording: &Recording) -> Result<Self> {
+        // The session the backends are detected in, logged for the first
+        // connection only.
+        static SESSION_LOGGED: std::sync::Once = std::sync::Once::new();
+        SESSION_LOGGED.call_once(|| {
+            debug!("DISPLAY: {:?}", std::env::var("DISPLAY"));
+            debug!("WAYLAND_DISPLAY: {:?}", std::env::var("WAYLAND_DISPLAY"));
+        });
+
+        for &backend in backends {
+            match Self::create(backend, recording) {
+                Ok(simulator) => {
+                    info!("Input backend: {backend}");
+                    return Ok(simulator);
+                }
+                Err(e) => warn!("Input backend {backend} failed to initialise: {e}"),
+            }
+        }
+
+        Err(Error::NoInputBackend)
+    }
+
+    pub fn create(backend: InputBackend, recording: &Recording) -> Result<Self> {
+        let simulator = match backend {
+            #[cfg(target_os = "linux")]
+            InputBackend::Uinput => {
+                UinputSimulator::new(AbsoluteArea::of(&config().SCREENS))?.into()
+            }
+            #[cfg(target_os = "linux")]
+            InputBackend::EnigoX11 => EnigoSimulator::x11()?.into(),
+            #[cfg(target_os = "linux")]
+            InputBackend::EnigoWayland => EnigoSimulator::wayland()?.into(),
+            InputBackend::Enigo => EnigoSimulator::new()?.into(),
+            InputBackend::DryRun => DryRunSimulator.into(),
+            InputBackend::Recording => RecordingSimulator::new(recording.clone()).into(),
+            #[allow(unreachable_patterns)]
+            backend => return Err(Error::InputBackendUnsupported(backend)),
+        };
+
+        Ok(simulator)
+    }
+}
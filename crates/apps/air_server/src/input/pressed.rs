@@ -0,0 +1,412 @@
+//! Remembers the keys and buttons held through a simulator, so they can be
+//! released when the client can't do it anymore.
+
+use super::InputSimulator;
+use crate::Result;
+use lib_models::{keymap::PortableKey, Command, Key, ModifierState, MouseButton, MouseScroll};
+use tracing::{info, warn};
+
+/// Modifier masks with the keys holding them, the first one is pressed when
+/// the client holds the modifier.
+const MODIFIER_KEYS: [(u32, &[Key]); 4] = [
+    (ModifierState::SHIFT, &[Key::LeftShift, Key::RightShift]),
+    (ModifierState::CTRL, &[Key::LeftControl, Key::RightControl]),
+    (ModifierState::ALT, &[Key::LeftAlt, Key::RightAlt]),
+    (ModifierState::SUPER, &[Key::LeftMeta, Key::RightMeta]),
+];
+
+/// Lock masks with the key toggling them.
+const LOCK_KEYS: [(u32, Key); 2] = [
+    (ModifierState::CAPS_LOCK, Key::CapsLock),
+    (ModifierState::NUM_LOCK, Key::NumLock),
+];
+
+pub struct PressedTracker<S> {
+    inner: S,
+    /// Held keys, in press order.
+    keys: Vec<Key>,
+    buttons: Vec<MouseButton>,
+    /// Locks believed active on the server, off until the client tells.
+    locked: u32,
+}
+
+impl<S: InputSimulator> PressedTracker<S> {
+    pub fn new(inner: S) -> Self {
+        Self {
+            inner,
+            keys: Vec::new(),
+            buttons: Vec::new(),
+            locked: 0,
+        }
+    }
+
+    pub fn inner(&self) -> &S {
+        &self.inner
+    }
+
+    pub fn pressed_keys(&self) -> &[Key] {
+        &self.keys
+    }
+
+    pub fn pressed_buttons(&self) -> &[MouseButton] {
+        &self.buttons
+    }
+
+    pub fn has_pressed(&self) -> bool {
+        !self.keys.is_empty() || !self.buttons.is_empty()
+    }
+
+    /// Keys held once `command` is applied, without applying it.
+    pub fn held_after(&self, command: &Command) -> Vec<Key> {
+        let mut keys = self.keys.clone();
+        match command {
+            Command::KeyPressed(key) if !keys.contains(key) => keys.push(*key),
+            Command::KeyReleased(key) => keys.retain(|held| held != key),
+            Command::FocusEntered { pressed } => keys = pressed.clone(),
+            Command::FocusLost => keys.clear(),
+            Command::Modifiers(state) => {
+                for (mask, modifier_keys) in MODIFIER_KEYS {
+                    let held = keys.iter().any(|key| modifier_keys.contains(key));
+                    match (state.active() & mask != 0, held) {
+                        (true, false) => keys.push(modifier_keys[0]),
+                        (false, true) => keys.retain(|key| !modifier_keys.contains(key)),
+                        _ => {}
+                    }
+                }
+            }
+            _ => {}
+        }
+        keys
+    }
+
+    /// Presses and releases keys so exactly `pressed` are held.
+    pub fn sync_keys(&mut self, pressed: &[Key]) -> Result<()> {
+        let released: Vec<Key> = self
+            .keys
+            .iter()
+            .copied()
+            .filter(|key| !pressed.contains(key))
+            .collect();
+        for key in released {
+            self.key_release(key)?;
+        }
+
+        for &key in pressed {
+            if !self.keys.contains(&key) {
+                self.key_press(key)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Holds the modifiers active on the client and toggles the locks that
+    /// differ from it. The layout group can't be switched through a simulator.
+    pub fn sync_modifiers(&mut self, state: &ModifierState) -> Result<()> {
+        let active = state.active();
+
+        for (mask, keys) in MODIFIER_KEYS {
+            let held: Vec<Key> = self
+                .keys
+                .iter()
+                .copied()
+                .filter(|key| keys.contains(key))
+                .collect();
+
+            match (active & mask != 0, held.is_empty()) {
+                (true, true) => self.key_press(keys[0])?,
+                (false, false) => {
+                    for key in held {
+                        self.key_release(key)?;
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        for (mask, key) in LOCK_KEYS {
+            if (self.locked ^ state.locked) & mask != 0 {
+                self.inner.key_press(key)?;
+                self.inner.key_release(key)?;
+            }
+        }
+        self.locked = state.locked;
+
+        Ok(())
+    }
+
+    /// Releases everything still held, the most recent press first. Every
+    /// release is tried, the ones failing stay held and the first error is
+    /// returned.
+    pub fn release_all(&mut self) -> Result<()> {
+        if self.has_pressed() {
+            info!(
+                "Releasing held keys {:?} and buttons {:?}",
+                self.keys, self.buttons
+            );
+        }
+
+        let mut first_error = None;
+        for button in std::mem::take(&mut self.buttons).into_iter().rev() {
+            if let Err(e) = self.inner.mouse_release(button) {
+                warn!("Failed to release {button:?}: {e}");
+                self.buttons.insert(0, button);
+                first_error.get_or_insert(e);
+            }
+        }
+        for key in std::mem::take(&mut self.keys).into_iter().rev() {
+            if let Err(e) = self.inner.key_release(key) {
+                warn!("Failed to release {key:?}: {e}");
+                self.keys.insert(0, key);
+                first_error.get_or_insert(e);
+            }
+        }
+
+        first_error.map_or(Ok(()), Err)
+    }
+}
+
+impl<S: InputSimulator> InputSimulator for PressedTracker<S> {
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()> {
+        self.inner.set_mouse(x, y)
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        self.inner.move_mouse(x, y)
+    }
+
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        self.inner.mouse_press(button)?;
+        if !self.buttons.contains(&button) {
+            self.buttons.push(button);
+        }
+        Ok(())
+    }
+
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        self.buttons.retain(|held| *held != button);
+        self.inner.mouse_release(button)
+    }
+
+    fn key_press(&mut self, key: Key) -> Result<()> {
+        self.inner.key_press(key)?;
+        if !self.keys.contains(&key) {
+            self.keys.push(key);
+        }
+        Ok(())
+    }
+
+    fn key_release(&mut self, key: Key) -> Result<()> {
+        self.keys.retain(|held| *held != key);
+        self.inner.key_release(key)
+    }
+
+    fn key_press_translated(&mut self, key: PortableKey) -> Result<()> {
+        self.inner.key_press_translated(key)?;
+        if !self.keys.contains(&key.key) {
+            self.keys.push(key.key);
+        }
+        Ok(())
+    }
+
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()> {
+        self.inner.scroll(scroll)
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.inner.text(text)
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use crate::input::{RecordedEvent, RecordingSimulator};
+
+    #[test]
+    fn test_release_all() -> Result<()> {
+        let mut input = PressedTracker::new(RecordingSimulator::default());
+
+        input.key_press(Key::LeftControl)?;
+        input.key_press(Key::A)?;
+        input.mouse_press(MouseButton::Left)?;
+        input.key_press(Key::S)?;
+        input.key_release(Key::S)?;
+        input.release_all()?;
+
+        assert!(!input.has_pressed());
+        assert_eq!(
+            input.inner().recording().events()[5..],
+            [
+                RecordedEvent::MouseRelease(MouseButton::Left),
+                RecordedEvent::KeyRelease(Key::A),
+                RecordedEvent::KeyRelease(Key::LeftControl),
+            ]
+        );
+
+        Ok(())
+    }
+
+    /// Fails to release `stuck`, records everything else.
+    #[derive(Default)]
+    struct StuckKey {
+        inner: RecordingSimulator,
+        stuck: Option<Key>,
+    }
+
+    impl InputSimulator for StuckKey {
+        fn set_mouse(&mut self, x: f64, y: f64) -> crate::Result<()> {
+            self.inner.set_mouse(x, y)
+        }
+
+        fn move_mouse(&mut self, x: i32, y: i32) -> crate::Result<()> {
+            self.inner.move_mouse(x, y)
+        }
+
+        fn mouse_press(&mut self, button: MouseButton) -> crate::Result<()> {
+            self.inner.mouse_press(button)
+        }
+
+        fn mouse_release(&mut self, button: MouseButton) -> crate::Result<()> {
+            self.inner.mouse_release(button)
+        }
+
+        fn key_press(&mut self, key: Key) -> crate::Result<()> {
+            self.inner.key_press(key)
+        }
+
+        fn key_release(&mut self, key: Key) -> crate::Result<()> {
+            match self.stuck == Some(key) {
+                true => Err(crate::Error::input(lib_models::InputErrorKind::Backend(
+                    "stuck".to_string(),
+                ))),
+                false => self.inner.key_release(key),
+            }
+        }
+
+        fn scroll(&mut self, scroll: MouseScroll) -> crate::Result<()> {
+            self.inner.scroll(scroll)
+        }
+
+        fn text(&mut self, text: &str) -> crate::Result<()> {
+            self.inner.text(text)
+        }
+    }
+
+    #[test]
+    fn test_release_all_past_failure() -> Result<()> {
+        let mut input = PressedTracker::new(StuckKey {
+            stuck: Some(Key::A),
+            ..Default::default()
+        });
+
+        input.key_press(Key::LeftControl)?;
+        input.key_press(Key::A)?;
+        input.mouse_press(MouseButton::Left)?;
+        input.key_press(Key::S)?;
+        assert!(input.release_all().is_err());
+
+        // Everything else is released, the stuck key is tried again later.
+        assert_eq!(
+            input.inner().inner.recording().events()[4..],
+            [
+                RecordedEvent::MouseRelease(MouseButton::Left),
+                RecordedEvent::KeyRelease(Key::S),
+                RecordedEvent::KeyRelease(Key::LeftControl),
+            ]
+        );
+        assert_eq!(input.pressed_keys(), [Key::A]);
+        assert!(input.pressed_buttons().is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_release_all_when_nothing_held() -> Result<()> {
+        let mut input = PressedTracker::new(RecordingSimulator::default());
+
+        input.mouse_press(MouseButton::Right)?;
+        input.mouse_release(MouseButton::Right)?;
+        input.release_all()?;
+
+        assert_eq!(input.inner().recording().events().len(), 2);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_keys() -> Result<()> {
+        let mut input = PressedTracker::new(RecordingSimulator::default());
+
+        input.key_press(Key::LeftControl)?;
+        input.key_press(Key::A)?;
+        input.sync_keys(&[Key::A, Key::S])?;
+
+        assert_eq!(input.pressed_keys(), [Key::A, Key::S]);
+        assert_eq!(
+            input.inner().recording().events()[2..],
+            [
+                RecordedEvent::KeyRelease(Key::LeftControl),
+                RecordedEvent::KeyPress(Key::S)
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_sync_modifiers() -> Result<()> {
+        let mut input = PressedTracker::new(RecordingSimulator::default());
+        input.key_press(Key::RightShift)?; // Held from before.
+
+        input.sync_modifiers(&ModifierState {
+            depressed: ModifierState::CTRL,
+            locked: ModifierState::CAPS_LOCK,
+            ..Default::default()
+        })?;
+        assert_eq!(input.pressed_keys(), [Key::LeftControl]);
+
+        // Same locks, nothing toggled again.
+        input.sync_modifiers(&ModifierState {
+            locked: ModifierState::CAPS_LOCK,
+            ..Default::default()
+        })?;
+
+        assert!(!input.has_pressed());
+        assert_eq!(
+            input.inner().recording().events()[1..],
+            [
+                RecordedEvent::KeyRelease(Key::RightShift),
+                RecordedEvent::KeyPress(Key::LeftControl),
+                RecordedEvent::KeyPress(Key::CapsLock),
+                RecordedEvent::KeyRelease(Key::CapsLock),
+                RecordedEvent::KeyRelease(Key::LeftControl),
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_repeated_press_tracked_once() -> Result<()> {
+        let mut input = PressedTracker::new(RecordingSimulator::default());
+
+        input.key_press(Key::A)?;
+        input.key_press(Key::A)?;
+        assert_eq!(input.pressed_keys(), [Key::A]);
+
+        input.release_all()?;
+        assert_eq!(
+            input.inner().recording().events().last(),
+            Some(&RecordedEvent::KeyRelease(Key::A))
+        );
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,278 @@
+//! Backend that records every injected event instead of touching the host.
+//!
+//! Events are kept in memory with the time elapsed since the recording started
+//! and, when a path is given, appended to a JSONL file as they arrive.
+
+use super::InputSimulator;
+use crate::Result;
+use lib_models::{Key, MouseButton, MouseScroll, ScrollDelta};
+use std::{
+    fs::File,
+    io::Write,
+    path::Path,
+    sync::{Arc, Mutex},
+    time::{Duration, Instant},
+};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum RecordedEvent {
+    SetMouse { x: f64, y: f64 },
+    MoveMouse { x: i32, y: i32 },
+    MousePress(MouseButton),
+    MouseRelease(MouseButton),
+    KeyPress(Key),
+    KeyRelease(Key),
+    Scroll(MouseScroll),
+    Text(String),
+}
+
+impl RecordedEvent {
+    /// Single-line JSON object describing the event.
+    pub fn to_json(&self) -> String {
+        match self {
+            Self::SetMouse { x, y } => format!(r#"{{"event":"set_mouse","x":{x},"y":{y}}}"#),
+            Self::MoveMouse { x, y } => format!(r#"{{"event":"move_mouse","x":{x},"y":{y}}}"#),
+            Self::MousePress(button) => {
+                format!(
+                    r#"{{"event":"mouse_press","button":{}}}"#,
+                    button.to_evdev()
+                )
+            }
+            Self::MouseRelease(button) => {
+                format!(
+                    r#"{{"event":"mouse_release","button":{}}}"#,
+                    button.to_evdev()
+                )
+            }
+            Self::KeyPress(key) => format!(r#"{{"event":"key_press","key":"{key:?}"}}"#),
+            Self::KeyRelease(key) => format!(r#"{{"event":"key_release","key":"{key:?}"}}"#),
+            Self::Scroll(scroll) => format!(
+                r#"{{"event":"scroll","source":"{:?}","vertical":{},"horizontal":{}}}"#,
+                scroll.source,
+                delta_json(&scroll.vertical),
+                delta_json(&scroll.horizontal)
+            ),
+            Self::Text(text) => format!(r#"{{"event":"text","text":{}}}"#, json_string(text)),
+        }
+    }
+}
+
+/// A recorded event with the time elapsed since the recording started.
+#[derive(Debug, Clone)]
+pub struct RecordedEntry {
+    pub elapsed: Duration,
+    pub event: RecordedEvent,
+}
+
+impl RecordedEntry {
+    pub fn to_json(&self) -> String {
+        let event = self.event.to_json();
+        // Prepend the timestamp to the event object.
+        format!(
+            r#"{{"elapsed_us":{},{}"#,
+            self.elapsed.as_micros(),
+            &event[1..]
+        )
+    }
+}
+
+#[derive(Debug)]
+struct RecordingInner {
+    started: Instant,
+    entries: Vec<RecordedEntry>,
+    file: Option<File>,
+}
+
+/// Shared handle to the events recorded by [`RecordingSimulator`]s.
+#[derive(Debug, Clone)]
+pub struct Recording(Arc<Mutex<RecordingInner>>);
+
+impl Default for Recording {
+    fn default() -> Self {
+        Self::with_file(None)
+    }
+}
+
+impl Recording {
+    /// Records in memory and appends every event to a JSONL file.
+    pub fn to_file(path: impl AsRef<Path>) -> Result<Self> {
+        let file = File::options().create(true).append(true).open(path)?;
+        Ok(Self::with_file(Some(file)))
+    }
+
+    fn with_file(file: Option<File>) -> Self {
+        Self(Arc::new(Mutex::new(RecordingInner {
+            started: Instant::now(),
+            entries: Vec::new(),
+            file,
+        })))
+    }
+
+    pub fn entries(&self) -> Vec<RecordedEntry> {
+        self.0.lock().unwrap().entries.clone()
+    }
+
+    pub fn events(&self) -> Vec<RecordedEvent> {
+        self.entries()
+            .into_iter()
+            .map(|entry| entry.event)
+            .collect()
+    }
+
+    fn push(&self, event: RecordedEvent) -> Result<()> {
+        let mut inner = self.0.lock().unwrap();
+        let entry = RecordedEntry {
+            elapsed: inner.started.elapsed(),
+            event,
+        };
+
+        if let Some(file) = inner.file.as_mut() {
+            writeln!(file, "{}", entry.to_json())?;
+        }
+        inner.entries.push(entry);
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct RecordingSimulator {
+    recording: Recording,
+}
+
+impl RecordingSimulator {
+    pub fn new(recording: Recording) -> Self {
+        Self { recording }
+    }
+
+    pub fn recording(&self) -> &Recording {
+        &self.recording
+    }
+}
+
+impl InputSimulator for RecordingSimulator {
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()> {
+        self.recording.push(RecordedEvent::SetMouse { x, y })
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        self.recording.push(RecordedEvent::MoveMouse { x, y })
+    }
+
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        self.recording.push(RecordedEvent::MousePress(button))
+    }
+
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        self.recording.push(RecordedEvent::MouseRelease(button))
+    }
+
+    fn key_press(&mut self, key: Key) -> Result<()> {
+        self.recording.push(RecordedEvent::KeyPress(key))
+    }
+
+    fn key_release(&mut self, key: Key) -> Result<()> {
+        self.recording.push(RecordedEvent::KeyRelease(key))
+    }
+
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()> {
+        self.recording.push(RecordedEvent::Scroll(scroll))
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        self.recording.push(RecordedEvent::Text(text.to_string()))
+    }
+}
+
+fn delta_json(delta: &ScrollDelta) -> String {
+    format!(
+        r#"{{"value120":{},"pixels":{},"stop":{}}}"#,
+        delta.value120, delta.pixels, delta.stop
+    )
+}
+
+fn json_string(value: &str) -> String {
+    let mut out = String::with_capacity(value.len() + 2);
+    out.push('"');
+    for ch in value.chars() {
+        match ch {
+            '"' => out.push_str("\\\""),
+            '\\' => out.push_str("\\\\"),
+            '\n' => out.push_str("\\n"),
+            '\r' => out.push_str("\\r"),
+            '\t' => out.push_str("\\t"),
+            ch if (ch as u32) < 0x20 => out.push_str(&format!("\\u{:04x}", ch as u32)),
+            ch => out.push(ch),
+        }
+    }
+    out.push('"');
+    out
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_records_in_order() -> Result<()> {
+        let mut sim = RecordingSimulator::default();
+
+        sim.set_mouse(1.0, 2.5)?;
+        sim.key_press(Key::A)?;
+        sim.text("hi")?;
+
+        assert_eq!(
+            sim.recording().events(),
+            vec![
+                RecordedEvent::SetMouse { x: 1.0, y: 2.5 },
+                RecordedEvent::KeyPress(Key::A),
+                RecordedEvent::Text("hi".to_string()),
+            ]
+        );
+
+        let entries = sim.recording().entries();
+        assert!(entries.windows(2).all(|w| w[0].elapsed <= w[1].elapsed));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_jsonl_file() -> Result<()> {
+        let path = std::env::temp_dir().join(format!("air-recording-{}.jsonl", std::process::id()));
+        let _ = std::fs::remove_file(&path);
+
+        let mut sim = RecordingSimulator::new(Recording::to_file(&path)?);
+        sim.mouse_press(MouseButton::Left)?;
+        sim.key_release(Key::Escape)?;
+        sim.text("say \"hi\"\n")?;
+        sim.scroll(MouseScroll {
+            source: lib_models::ScrollSource::Finger,
+            vertical: ScrollDelta {
+                pixels: 7.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        let content = std::fs::read_to_string(&path)?;
+        std::fs::remove_file(&path)?;
+
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(lines.len(), 4);
+        assert!(lines[0].starts_with(r#"{"elapsed_us":"#));
+        assert!(lines[0].ends_with(r#""event":"mouse_press","button":272}"#));
+        assert!(lines[1].ends_with(r#""event":"key_release","key":"Escape"}"#));
+        assert!(lines[2].ends_with(r#""event":"text","text":"say \"hi\"\n"}"#));
+        assert!(lines[3].ends_with(
+            r#""source":"Finger","vertical":{"value120":0,"pixels":7.5,"stop":false},"horizontal":{"value120":0,"pixels":0,"stop":false}}"#
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,167 @@
+//! Conversion of scroll deltas to the whole units the backends inject.
+//!
+//! Touchpads send fractions of a notch, backends write 1/120 notches, whole
+//! pixels or whole notches only. What doesn't fit is carried to the next
+//! scroll on the axis.
+
+use lib_models::{MouseScroll, ScrollDelta};
+
+/// Units of one axis to inject for a scroll.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ScrollSteps {
+    /// Motion in 1/120 of a notch.
+    pub value120: i32,
+    /// Notches completed by this motion.
+    pub notches: i32,
+    /// Motion in whole pixels.
+    pub pixels: i32,
+}
+
+#[derive(Debug, Default)]
+pub struct ScrollAccumulator {
+    vertical: AxisAccumulator,
+    horizontal: AxisAccumulator,
+}
+
+impl ScrollAccumulator {
+    /// Steps of the vertical and horizontal axes.
+    pub fn push(&mut self, scroll: &MouseScroll) -> (ScrollSteps, ScrollSteps) {
+        (
+            self.vertical.push(&scroll.vertical),
+            self.horizontal.push(&scroll.horizontal),
+        )
+    }
+}
+
+#[derive(Debug, Default)]
+struct AxisAccumulator {
+    /// Fraction of 1/120 notch not injected yet.
+    fraction: f64,
+    /// 1/120 notches injected since the last whole notch.
+    partial: i32,
+    /// Fraction of a pixel not injected yet.
+    pixel_fraction: f64,
+}
+
+impl AxisAccumulator {
+    fn push(&mut self, delta: &ScrollDelta) -> ScrollSteps {
+        let total = self.fraction + delta.to_value120();
+        let value120 = total.trunc() as i32;
+        self.fraction = total - value120 as f64;
+
+        // A change of direction starts a new notch.
+        if value120.signum() == -self.partial.signum() {
+            self.partial = 0;
+        }
+        self.partial += value120;
+        let notches = self.partial / 120;
+        self.partial -= notches * 120;
+
+        let total = self.pixel_fraction + delta.to_pixels();
+        let pixels = total.trunc() as i32;
+        self.pixel_fraction = total - pixels as f64;
+
+        if delta.stop {
+            *self = Self::default();
+        }
+
+        ScrollSteps {
+            value120,
+            notches,
+            pixels,
+        }
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::ScrollSource;
+
+    fn fx_scroll(value120: i32, pixels: f64) -> MouseScroll {
+        MouseScroll {
+            source: ScrollSource::Finger,
+            vertical: ScrollDelta {
+                value120,
+                pixels,
+                stop: false,
+            },
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_wheel_notches() -> Result<()> {
+        let mut accumulator = ScrollAccumulator::default();
+
+        let (vertical, horizontal) = accumulator.push(&fx_scroll(120, 15.0));
+        assert_eq!(
+            vertical,
+            ScrollSteps {
+                value120: 120,
+                notches: 1,
+                pixels: 15,
+            }
+        );
+        assert_eq!(horizontal, ScrollSteps::default());
+
+        // Two half notches of a high-resolution wheel make one notch.
+        assert_eq!(accumulator.push(&fx_scroll(60, 7.5)).0.notches, 0);
+        assert_eq!(accumulator.push(&fx_scroll(60, 7.5)).0.notches, 1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_fractions_carry() -> Result<()> {
+        let mut accumulator = ScrollAccumulator::default();
+
+        // 1/16 px is half a 1/120 notch, nothing to inject until it adds up.
+        let steps: Vec<_> = (0..4)
+            .map(|_| accumulator.push(&fx_scroll(0, 0.0625)).0.value120)
+            .collect();
+        assert_eq!(steps, [0, 1, 0, 1]);
+
+        // A notch backwards, the partial forward notch is dropped.
+        let steps = accumulator.push(&fx_scroll(0, -15.0)).0;
+        assert_eq!(steps.notches, -1);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pixels_carry() -> Result<()> {
+        let mut accumulator = ScrollAccumulator::default();
+
+        let pixels: Vec<_> = (0..4)
+            .map(|_| accumulator.push(&fx_scroll(0, 0.5)).0.pixels)
+            .collect();
+        assert_eq!(pixels, [0, 1, 0, 1]);
+
+        // A wheel without pixels scrolls the pixels of its notches.
+        assert_eq!(accumulator.push(&fx_scroll(-60, 0.0)).0.pixels, -7);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_stop_resets() -> Result<()> {
+        let mut accumulator = ScrollAccumulator::default();
+
+        accumulator.push(&fx_scroll(0, 10.0));
+        let mut stop = fx_scroll(0, 0.0);
+        stop.vertical.stop = true;
+        accumulator.push(&stop);
+
+        // The 2/3 notch left before the stop is not completed.
+        assert_eq!(accumulator.push(&fx_scroll(0, 10.0)).0.notches, 0);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
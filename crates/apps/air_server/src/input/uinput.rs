@@ -0,0 +1,592 @@
+//! Linux input injection through uinput virtual devices.
+//!
+//! Three devices are created: a keyboard, a relative pointer (buttons, motion
+//! and wheels) and an absolute pointer spanning every server screen, in
+//! physical pixels of the densest one. Keys are
+//! written as their evdev code, the client already sends evdev button codes.
+//! Wheels report 1/120 notches on the high-resolution axes and whole notches
+//! on the legacy ones, like a high-resolution mouse.
+
+use super::{scroll::ScrollAccumulator, InputSimulator};
+use crate::{Error, Result};
+use evdev::{
+    uinput::VirtualDevice, AbsInfo, AbsoluteAxisCode, AttributeSet, EventType, InputEvent, KeyCode,
+    RelativeAxisCode, UinputAbsSetup,
+};
+use lib_models::{DisplayParams, InputErrorKind, Key, MouseButton, MouseScroll};
+
+/// Highest keyboard keycode registered on the virtual keyboard (`KEY_MICMUTE`).
+const KEY_LAST: u16 = 248;
+
+/// Buttons registered on the pointer, `BTN_LEFT` to `BTN_TASK`.
+const BUTTONS: core::ops::RangeInclusive<u16> = 0x110..=0x117;
+
+/// Destination for batches of raw input events.
+///
+/// Implemented by [`VirtualDevice`], tests use an in-memory writer instead.
+pub trait EventSink {
+    /// Writes a batch of events followed by `SYN_REPORT`.
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()>;
+}
+
+impl EventSink for VirtualDevice {
+    fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+        VirtualDevice::emit(self, events)
+    }
+}
+
+/// Desktop area the absolute pointer spans: the box around every display,
+/// in logical pixels.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct AbsoluteArea {
+    x: f64,
+    y: f64,
+    width: f64,
+    height: f64,
+    /// Device units per logical pixel, the scale of the densest display.
+    scale: f64,
+}
+
+impl AbsoluteArea {
+    pub fn of(displays: &[DisplayParams]) -> Self {
+        let Some(first) = displays.first() else {
+            return Self::default();
+        };
+
+        let (mut left, mut top) = (f64::MAX, f64::MAX);
+        let (mut right, mut bottom) = (f64::MIN, f64::MIN);
+        let mut scale = first.scale();
+        for display in displays {
+            left = left.min(display.x() as f64);
+            top = top.min(display.y() as f64);
+            right = right.max(display.x() as f64 + display.logical_width());
+            bottom = bottom.max(display.y() as f64 + display.logical_height());
+            scale = scale.max(display.scale());
+        }
+
+        Self {
+            x: left,
+            y: top,
+            width: right - left,
+            height: bottom - top,
+            scale,
+        }
+    }
+
+    /// Device units of the area.
+    fn size(self) -> (i32, i32) {
+        (
+            (self.width * self.scale).round() as i32,
+            (self.height * self.scale).round() as i32,
+        )
+    }
+
+    /// Device position of desktop coordinates.
+    fn to_device(self, x: f64, y: f64) -> (i32, i32) {
+        (
+            ((x - self.x) * self.scale).round() as i32,
+            ((y - self.y) * self.scale).round() as i32,
+        )
+    }
+}
+
+impl Default for AbsoluteArea {
+    fn default() -> Self {
+        Self {
+            x: 0.0,
+            y: 0.0,
+            width: 0.0,
+            height: 0.0,
+            scale: 1.0,
+        }
+    }
+}
+
+pub struct UinputSimulator<S: EventSink = VirtualDevice> {
+    keyboard: S,
+    pointer: S,
+    absolute: S,
+    area: AbsoluteArea,
+    scroll: ScrollAccumulator,
+}
+
+impl UinputSimulator {
+    /// Creates the virtual devices, the absolute pointer spans `area`.
+    pub fn new(area: AbsoluteArea) -> Result<Self> {
+        let keys: AttributeSet<KeyCode> = (1..=KEY_LAST).map(KeyCode).collect();
+        let keyboard = VirtualDevice::builder()?
+            .name("air-link keyboard")
+            .with_keys(&keys)?
+            .build()?;
+
+        let buttons: AttributeSet<KeyCode> = BUTTONS.map(KeyCode).collect();
+        let axes: AttributeSet<RelativeAxisCode> = [
+            RelativeAxisCode::REL_X,
+            RelativeAxisCode::REL_Y,
+            RelativeAxisCode::REL_WHEEL,
+            RelativeAxisCode::REL_HWHEEL,
+            RelativeAxisCode::REL_WHEEL_HI_RES,
+            RelativeAxisCode::REL_HWHEEL_HI_RES,
+        ]
+        .into_iter()
+        .collect();
+        let pointer = VirtualDevice::builder()?
+            .name("air-link pointer")
+            .with_keys(&buttons)?
+            .with_relative_axes(&axes)?
+            .build()?;
+
+        let (width, height) = area.size();
+        let abs_x = UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_X,
+            AbsInfo::new(0, 0, (width - 1).max(0), 0, 0, 0),
+        );
+        let abs_y = UinputAbsSetup::new(
+            AbsoluteAxisCode::ABS_Y,
+            AbsInfo::new(0, 0, (height - 1).max(0), 0, 0, 0),
+        );
+        let absolute = VirtualDevice::builder()?
+            .name("air-link absolute pointer")
+            .with_keys(&buttons)?
+            .with_absolute_axis(&abs_x)?
+            .with_absolute_axis(&abs_y)?
+            .build()?;
+
+        Ok(Self::with_sinks(keyboard, pointer, absolute, area))
+    }
+}
+
+impl<S: EventSink> UinputSimulator<S> {
+    pub fn with_sinks(keyboard: S, pointer: S, absolute: S, area: AbsoluteArea) -> Self {
+        Self {
+            keyboard,
+            pointer,
+            absolute,
+            area,
+            scroll: ScrollAccumulator::default(),
+        }
+    }
+
+    fn key_event(key: Key, value: i32) -> Result<InputEvent> {
+        let code = key
+            .to_evdev()
+            .and_then(|code| u16::try_from(code).ok())
+            .ok_or(Error::input(InputErrorKind::UnsupportedKey(key)))?;
+        Ok(Self::code_event(KeyCode(code), value))
+    }
+
+    fn code_event(key: KeyCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::KEY.0, key.0, value)
+    }
+
+    fn button_event(button: MouseButton, value: i32) -> Result<InputEvent> {
+        let code = u16::try_from(button.to_evdev())
+            .ok()
+            .filter(|code| BUTTONS.contains(code))
+            .ok_or(Error::input(InputErrorKind::UnsupportedButton(button)))?;
+        Ok(InputEvent::new(EventType::KEY.0, code, value))
+    }
+
+    fn rel_event(axis: RelativeAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::RELATIVE.0, axis.0, value)
+    }
+
+    fn abs_event(axis: AbsoluteAxisCode, value: i32) -> InputEvent {
+        InputEvent::new(EventType::ABSOLUTE.0, axis.0, value)
+    }
+
+    /// Presses and releases a key, holding shift around it when asked to.
+    fn tap(&mut self, key: KeyCode, shift: bool) -> Result<()> {
+        let shift_key = KeyCode::KEY_LEFTSHIFT;
+
+        if shift {
+            emit(&mut self.keyboard, &[Self::code_event(shift_key, 1)])?;
+        }
+        emit(&mut self.keyboard, &[Self::code_event(key, 1)])?;
+        emit(&mut self.keyboard, &[Self::code_event(key, 0)])?;
+        if shift {
+            emit(&mut self.keyboard, &[Self::code_event(shift_key, 0)])?;
+        }
+
+        Ok(())
+    }
+}
+
+impl<S: EventSink> InputSimulator for UinputSimulator<S> {
+    fn set_mouse(&mut self, x: f64, y: f64) -> Result<()> {
+        let (x, y) = self.area.to_device(x, y);
+        emit(
+            &mut self.absolute,
+            &[
+                Self::abs_event(AbsoluteAxisCode::ABS_X, x),
+                Self::abs_event(AbsoluteAxisCode::ABS_Y, y),
+            ],
+        )
+    }
+
+    fn move_mouse(&mut self, x: i32, y: i32) -> Result<()> {
+        emit(
+            &mut self.pointer,
+            &[
+                Self::rel_event(RelativeAxisCode::REL_X, x),
+                Self::rel_event(RelativeAxisCode::REL_Y, y),
+            ],
+        )
+    }
+
+    fn mouse_press(&mut self, button: MouseButton) -> Result<()> {
+        emit(&mut self.pointer, &[Self::button_event(button, 1)?])
+    }
+
+    fn mouse_release(&mut self, button: MouseButton) -> Result<()> {
+        emit(&mut self.pointer, &[Self::button_event(button, 0)?])
+    }
+
+    fn key_press(&mut self, key: Key) -> Result<()> {
+        emit(&mut self.keyboard, &[Self::key_event(key, 1)?])
+    }
+
+    fn key_release(&mut self, key: Key) -> Result<()> {
+        emit(&mut self.keyboard, &[Self::key_event(key, 0)?])
+    }
+
+    fn scroll(&mut self, scroll: MouseScroll) -> Result<()> {
+        let (vertical, horizontal) = self.scroll.push(&scroll);
+
+        // Wayland axis values grow downwards, REL_WHEEL grows upwards.
+        let events: Vec<_> = [
+            (RelativeAxisCode::REL_WHEEL_HI_RES, -vertical.value120),
+            (RelativeAxisCode::REL_WHEEL, -vertical.notches),
+            (RelativeAxisCode::REL_HWHEEL_HI_RES, horizontal.value120),
+            (RelativeAxisCode::REL_HWHEEL, horizontal.notches),
+        ]
+        .into_iter()
+        .filter(|&(_, value)| value != 0)
+        .map(|(axis, value)| Self::rel_event(axis, value))
+        .collect();
+
+        if events.is_empty() {
+            return Ok(());
+        }
+        emit(&mut self.pointer, &events)
+    }
+
+    fn text(&mut self, text: &str) -> Result<()> {
+        for ch in text.chars() {
+            let (key, shift) =
+                char_to_key(ch).ok_or(Error::input(InputErrorKind::UnsupportedChar(ch)))?;
+            self.tap(key, shift)?;
+        }
+
+        Ok(())
+    }
+}
+
+fn emit<S: EventSink>(sink: &mut S, events: &[InputEvent]) -> Result<()> {
+    sink.emit(events)
+        .map_err(|e| Error::input(InputErrorKind::Backend(e.to_string())))
+}
+
+/// Maps a character to its key on a US layout, with whether shift is needed.
+fn char_to_key(ch: char) -> Option<(KeyCode, bool)> {
+    const LETTERS: [KeyCode; 26] = [
+        KeyCode::KEY_A,
+        KeyCode::KEY_B,
+        KeyCode::KEY_C,
+        KeyCode::KEY_D,
+        KeyCode::KEY_E,
+        KeyCode::KEY_F,
+        KeyCode::KEY_G,
+        KeyCode::KEY_H,
+        KeyCode::KEY_I,
+        KeyCode::KEY_J,
+        KeyCode::KEY_K,
+        KeyCode::KEY_L,
+        KeyCode::KEY_M,
+        KeyCode::KEY_N,
+        KeyCode::KEY_O,
+        KeyCode::KEY_P,
+        KeyCode::KEY_Q,
+        KeyCode::KEY_R,
+        KeyCode::KEY_S,
+        KeyCode::KEY_T,
+        KeyCode::KEY_U,
+        KeyCode::KEY_V,
+        KeyCode::KEY_W,
+        KeyCode::KEY_X,
+        KeyCode::KEY_Y,
+        KeyCode::KEY_Z,
+    ];
+    const DIGITS: [KeyCode; 10] = [
+        KeyCode::KEY_0,
+        KeyCode::KEY_1,
+        KeyCode::KEY_2,
+        KeyCode::KEY_3,
+        KeyCode::KEY_4,
+        KeyCode::KEY_5,
+        KeyCode::KEY_6,
+        KeyCode::KEY_7,
+        KeyCode::KEY_8,
+        KeyCode::KEY_9,
+    ];
+
+    let key = match ch {
+        'a'..='z' => (LETTERS[(ch as u8 - b'a') as usize], false),
+        'A'..='Z' => (LETTERS[(ch as u8 - b'A') as usize], true),
+        '0'..='9' => (DIGITS[(ch as u8 - b'0') as usize], false),
+        ')' => (KeyCode::KEY_0, true),
+        '!' => (KeyCode::KEY_1, true),
+        '@' => (KeyCode::KEY_2, true),
+        '#' => (KeyCode::KEY_3, true),
+        '$' => (KeyCode::KEY_4, true),
+        '%' => (KeyCode::KEY_5, true),
+        '^' => (KeyCode::KEY_6, true),
+        '&' => (KeyCode::KEY_7, true),
+        '*' => (KeyCode::KEY_8, true),
+        '(' => (KeyCode::KEY_9, true),
+        ' ' => (KeyCode::KEY_SPACE, false),
+        '\n' => (KeyCode::KEY_ENTER, false),
+        '\t' => (KeyCode::KEY_TAB, false),
+        '-' => (KeyCode::KEY_MINUS, false),
+        '_' => (KeyCode::KEY_MINUS, true),
+        '=' => (KeyCode::KEY_EQUAL, false),
+        '+' => (KeyCode::KEY_EQUAL, true),
+        '[' => (KeyCode::KEY_LEFTBRACE, false),
+        '{' => (KeyCode::KEY_LEFTBRACE, true),
+        ']' => (KeyCode::KEY_RIGHTBRACE, false),
+        '}' => (KeyCode::KEY_RIGHTBRACE, true),
+        '\\' => (KeyCode::KEY_BACKSLASH, false),
+        '|' => (KeyCode::KEY_BACKSLASH, true),
+        ';' => (KeyCode::KEY_SEMICOLON, false),
+        ':' => (KeyCode::KEY_SEMICOLON, true),
+        '\'' => (KeyCode::KEY_APOSTROPHE, false),
+        '"' => (KeyCode::KEY_APOSTROPHE, true),
+        '`' => (KeyCode::KEY_GRAVE, false),
+        '~' => (KeyCode::KEY_GRAVE, true),
+        ',' => (KeyCode::KEY_COMMA, false),
+        '<' => (KeyCode::KEY_COMMA, true),
+        '.' => (KeyCode::KEY_DOT, false),
+        '>' => (KeyCode::KEY_DOT, true),
+        '/' => (KeyCode::KEY_SLASH, false),
+        '?' => (KeyCode::KEY_SLASH, true),
+        _ => return None,
+    };
+
+    Some(key)
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::{ScrollDelta, ScrollSource};
+
+    #[derive(Default)]
+    struct MockSink {
+        batches: Vec<Vec<(u16, u16, i32)>>,
+    }
+
+    impl EventSink for MockSink {
+        fn emit(&mut self, events: &[InputEvent]) -> std::io::Result<()> {
+            self.batches.push(
+                events
+                    .iter()
+                    .map(|e| (e.event_type().0, e.code(), e.value()))
+                    .collect(),
+            );
+            Ok(())
+        }
+    }
+
+    fn simulator() -> UinputSimulator<MockSink> {
+        UinputSimulator::with_sinks(
+            MockSink::default(),
+            MockSink::default(),
+            MockSink::default(),
+            AbsoluteArea::default(),
+        )
+    }
+
+    const KEY: u16 = EventType::KEY.0;
+
+    #[test]
+    fn test_key_press_release() -> Result<()> {
+        let mut sim = simulator();
+
+        sim.key_press(Key::Q)?;
+        sim.key_release(Key::Q)?;
+
+        let code = KeyCode::KEY_Q.0;
+        assert_eq!(
+            sim.keyboard.batches,
+            vec![vec![(KEY, code, 1)], vec![(KEY, code, 0)]]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_key_without_evdev_code() {
+        let mut sim = simulator();
+
+        assert!(sim.key_press(Key::Unknown(0x7_00a5)).is_err());
+        assert!(sim.keyboard.batches.is_empty());
+    }
+
+    #[test]
+    fn test_mouse_buttons() -> Result<()> {
+        let mut sim = simulator();
+
+        sim.mouse_press(MouseButton::Left)?;
+        sim.mouse_release(MouseButton::Forward)?;
+        sim.mouse_press(MouseButton::Other(0x116))?; // BTN_BACK
+        assert!(sim.mouse_press(MouseButton::Other(0x100)).is_err());
+
+        assert_eq!(
+            sim.pointer.batches,
+            vec![
+                vec![(KEY, 0x110, 1)],
+                vec![(KEY, 0x114, 0)],
+                vec![(KEY, 0x116, 1)]
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_set_and_move_mouse() -> Result<()> {
+        let mut sim = simulator();
+
+        sim.set_mouse(100.0, 200.0)?;
+        sim.move_mouse(-3, 4)?;
+
+        let abs = EventType::ABSOLUTE.0;
+        let rel = EventType::RELATIVE.0;
+        assert_eq!(
+            sim.absolute.batches,
+            vec![vec![(abs, 0, 100), (abs, 1, 200)]]
+        );
+        assert_eq!(sim.pointer.batches, vec![vec![(rel, 0, -3), (rel, 1, 4)]]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_absolute_area() -> Result<()> {
+        // HiDPI screen left of the primary one.
+        let area = AbsoluteArea::of(&[
+            DisplayParams::new(1920, 1080),
+            DisplayParams::new(2560, 1440)
+                .with_position(-1280, -200)
+                .with_scale(2.0),
+        ]);
+        assert_eq!(area.size(), (6400, 2560));
+
+        let mut sim = simulator();
+        sim.area = area;
+        sim.set_mouse(-1280.0, -200.0)?;
+        sim.set_mouse(0.5, 0.25)?;
+
+        let abs = EventType::ABSOLUTE.0;
+        assert_eq!(
+            sim.absolute.batches,
+            vec![
+                vec![(abs, 0, 0), (abs, 1, 0)],
+                vec![(abs, 0, 2561), (abs, 1, 401)]
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_direction() -> Result<()> {
+        let mut sim = simulator();
+
+        sim.scroll(MouseScroll {
+            vertical: ScrollDelta {
+                value120: 120,
+                pixels: 15.0,
+                stop: false,
+            },
+            horizontal: ScrollDelta {
+                value120: -60,
+                pixels: -7.5,
+                stop: false,
+            },
+            ..Default::default()
+        })?;
+        // Half a notch of touchpad motion, only the high-resolution axis moves.
+        sim.scroll(MouseScroll {
+            source: ScrollSource::Finger,
+            vertical: ScrollDelta {
+                pixels: 7.5,
+                ..Default::default()
+            },
+            ..Default::default()
+        })?;
+
+        let rel = EventType::RELATIVE.0;
+        assert_eq!(
+            sim.pointer.batches,
+            vec![
+                vec![(rel, 0x0b, -120), (rel, 0x08, -1), (rel, 0x0c, -60)],
+                vec![(rel, 0x0b, -60)]
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_with_shift() -> Result<()> {
+        let mut sim = simulator();
+
+        sim.text("a!")?;
+
+        let shift = KeyCode::KEY_LEFTSHIFT.0;
+        assert_eq!(
+            sim.keyboard.batches,
+            vec![
+                vec![(KEY, KeyCode::KEY_A.0, 1)],
+                vec![(KEY, KeyCode::KEY_A.0, 0)],
+                vec![(KEY, shift, 1)],
+                vec![(KEY, KeyCode::KEY_1.0, 1)],
+                vec![(KEY, KeyCode::KEY_1.0, 0)],
+                vec![(KEY, shift, 0)],
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_text_unsupported_char() {
+        let mut sim = simulator();
+
+        assert!(matches!(
+            sim.text("ж"),
+            Err(Error::Input {
+                kind: InputErrorKind::UnsupportedChar('ж'),
+                ..
+            })
+        ));
+    }
+
+    #[test]
+    #[ignore = "needs write access to /dev/uinput"]
+    fn test_real_device() -> Result<()> {
+        let mut sim = UinputSimulator::new(AbsoluteArea::of(&[DisplayParams::new(1920, 1080)]))?;
+
+        sim.move_mouse(1, 1)?;
+        sim.move_mouse(-1, -1)?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -1,21 +1,46 @@
 // region:    --- Modules
 
+use lib_config::Settings;
 use tracing::{debug, info, Level};
 use tracing_subscriber::EnvFilter;
 
 // -- Modules
+mod certs;
+mod cli;
 mod config;
+mod devices;
 mod error;
+mod error_policy;
+mod handshake;
 mod input;
+mod merge;
+mod metrics;
+mod policy;
+mod reload;
+mod server;
 
 // -- Flatten
-pub use config::config;
+pub use certs::CertPaths;
+pub use cli::{CertsCommand, Cli, CliCommand};
+pub use config::{config, Config};
+pub use devices::Devices;
 pub use error::{Error, Result};
-pub use input::{InputSimulator, Simulator};
+pub use error_policy::{ErrorClass, ErrorPolicies, ErrorPolicy};
+pub use handshake::SERVER_NAME;
+pub use input::{
+    DryRunSimulator, EnigoSimulator, InputBackend, InputSimulator, PressedTracker, RecordedEntry,
+    RecordedEvent, Recording, RecordingSimulator, Simulator,
+};
+#[cfg(target_os = "linux")]
+pub use input::{EventSink, UinputSimulator};
+pub use metrics::Metrics;
+pub use policy::{ClientPolicy, KeyCombo, Permission, Policies, PolicyGuard, SharedPolicies};
+pub use reload::watch_config;
+pub use server::{handler, serve, ServerState};
 
 // endregion: --- Modules
 
-pub fn init() -> Result<()> {
+pub fn init(settings: &Settings) -> Result<()> {
     // LOGGING INITIALIZATION
     tracing_subscriber::fmt()
         .with_target(false)
@@ -27,6 +52,10 @@ pub fn init() -> Result<()> {
 
     // CONFIG INITIALIZATION
     info!("Loading config...");
+    if let Some(path) = settings.path() {
+        info!("From {}, profile {:?}", path.display(), settings.profile());
+    }
+    Config::init_from(Config::load(settings)?)?;
     let config = config();
     debug!("{:?}", config);
 
@@ -1,42 +1,59 @@
-use std::path::Path;
-
-use air_server::{config, InputSimulator, Result, Simulator};
-use lib_models::Command;
-use lib_quic::{
-    datagram::{Datagram, ReceivedDatagram},
-    quinn,
-    server::QuicServer,
-    tls::TlsLoader,
-};
+use air_server::{config, serve, CertPaths, Cli, CliCommand, Error, Result, ServerState};
+use clap::Parser;
+use lib_quic::tls::TlsLoader;
 use tracing::{error, info};
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    air_server::init()?;
+    let cli = Cli::parse();
+    let settings = cli.settings()?;
+    air_server::init(&settings)?;
+
+    let paths = CertPaths::from_config();
+    if let Some(CliCommand::Certs { command }) = cli.command {
+        return paths.run(command);
+    }
 
     TlsLoader::init_provider();
     TlsLoader::debug_cipher_info();
 
     let address = config().ADDRESS;
+    let identity = match paths.server_identity() {
+        Err(Error::CertificateMissing(path)) => {
+            error!(
+                "No server certificate in {}, create one with `air_server certs init`",
+                path.display()
+            );
+            return Err(Error::CertificateMissing(path));
+        }
+        identity => identity?,
+    };
+    info!("🔑 Server fingerprint {}", identity.fingerprint());
+    let state = ServerState::from_config(identity.fingerprint())?;
+
+    tokio::spawn(air_server::watch_config(settings, state.policies.clone()));
+    if let Some(period) = config().METRICS_LOG_INTERVAL {
+        tokio::spawn(state.metrics.clone().report_every(period));
+    }
+    if let Some(metrics_address) = config().METRICS_ADDRESS {
+        let metrics = state.metrics.clone();
+        tokio::spawn(async move {
+            if let Err(e) =
+                lib_metrics::prometheus::serve(metrics_address, move || metrics.render()).await
+            {
+                error!("Metrics endpoint stopped: {e}");
+            }
+        });
+    }
 
     info!("🔊 Server starting on {}", address);
 
-    let server = QuicServer::new(
-        address,
-        Path::new("./certs/cert.pem"),
-        Path::new("./certs/key.pem"),
-    )
-    .await
-    .unwrap();
-
-    let server_addr = server.local_addr().unwrap();
-    info!("✅ Server listening on {}", server_addr);
+    let endpoint = lib_tls::endpoint::server(address, &identity)?;
+    info!("✅ Server listening on {}", endpoint.local_addr()?);
 
     tokio::select! {
-         server = server.run(handler, ()) => {
-             if let Err(e) = server {
-                 error!("Server error: {e}");
-             }
+         _ = serve(endpoint, state) => {
+             error!("Server endpoint closed");
          },
          _ctrlc = tokio::signal::ctrl_c() => {
              info!("Received ctrl-c")
@@ -45,64 +62,3 @@ async fn main() -> Result<()> {
 
     Ok(())
 }
-
-struct Handler {
-    datagram: Datagram,
-}
-
-impl Handler {
-    pub fn new(connection: quinn::Connection) -> Self {
-        let datagram = Datagram::new(connection);
-
-        Self { datagram }
-    }
-
-    async fn receive(&self) -> Option<ReceivedDatagram> {
-        self.datagram.receive().await
-    }
-
-    fn process(&mut self, input: &mut Simulator, command: Command) -> Result<()> {
-        // info!("Reveived command: {:?}", command);
-
-        match command {
-            Command::SetMouse { x, y } => input.set_mouse(x, y).unwrap(),
-            Command::MoveMouse { x, y } => input.move_mouse(x, y).unwrap(),
-            Command::MouseButtonPressed(button) => input.mouse_press(button).unwrap(),
-            Command::MouseButtonReleased(button) => input.mouse_release(button).unwrap(),
-            Command::MouseScroll(scroll) => input.scroll(scroll).unwrap(),
-            Command::InputText(text) => input.text(&text).unwrap(),
-            Command::KeyPressed(keycode) => input.key_press(keycode).unwrap(),
-            Command::KeyReleased(keycode) => input.key_release(keycode).unwrap(),
-            Command::SetClipboard(_) => todo!(),
-        }
-
-        Ok(())
-    }
-}
-
-async fn handler(connection: quinn::Connection, _state: ()) -> lib_quic::Result<()> {
-    let address = connection.remote_address();
-    info!("New connection: {}", address);
-
-    println!("DISPLAY: {:?}", std::env::var("DISPLAY"));
-    println!("WAYLAND_DISPLAY: {:?}", std::env::var("WAYLAND_DISPLAY"));
-
-    let mut input = Simulator::new().unwrap();
-    let mut handler = Handler::new(connection);
-
-    while let Some(data) = handler.receive().await {
-        let Ok(command) = lib_codec::decode::<Command>(&data.data) else {
-            error!("Decode command failed");
-            continue;
-        };
-
-        if let Err(e) = handler.process(&mut input, command) {
-            error!("Error occured: {}", e);
-            break;
-        }
-    }
-
-    info!("👋 Client disconnected: {}", address);
-
-    Ok(())
-}
@@ -0,0 +1,227 @@
+//! Orders the commands of the datagram and reliable channels.
+//!
+//! The reliable stream can lag behind datagrams while a loss is retransmitted.
+//! A datagram sent after reliable command `n` waits until `n` is applied, so a
+//! drag doesn't move the pointer before the button press.
+//!
+//! Reliable commands come in order on one stream, but a command the client
+//! resends on a new stream can overtake the ones still on the old stream.
+//! They are held until the commands before them arrive.
+
+use lib_models::SequencedCommand;
+use std::collections::{BTreeMap, VecDeque};
+use tokio::time::Instant;
+use tracing::warn;
+
+/// Datagrams kept while waiting for the reliable stream, the oldest are
+/// dropped beyond it. Reliable commands held past it skip the missing ones.
+const MAX_PENDING: usize = 256;
+
+/// A command with the time it was decoded.
+#[derive(Debug)]
+pub struct Received {
+    pub command: SequencedCommand,
+    pub at: Instant,
+}
+
+impl Received {
+    pub fn now(command: SequencedCommand) -> Self {
+        Self {
+            command,
+            at: Instant::now(),
+        }
+    }
+
+    fn seq(&self) -> u64 {
+        self.command.seq
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct CommandMerger {
+    /// Number of the last reliable command released.
+    last: u64,
+    pending: VecDeque<Received>,
+    /// Reliable commands that came ahead of a missing one, by number.
+    held: BTreeMap<u64, Received>,
+}
+
+impl CommandMerger {
+    /// Returns the commands ready to be applied after a datagram.
+    pub fn datagram(&mut self, command: Received) -> Vec<Received> {
+        if command.seq() <= self.last {
+            return vec![command];
+        }
+
+        if self.pending.len() == MAX_PENDING {
+            warn!("Too many datagrams waiting for the reliable stream, dropping the oldest");
+            self.pending.pop_front();
+        }
+        self.pending.push_back(command);
+
+        Vec::new()
+    }
+
+    /// Returns the commands ready to be applied after a reliable command.
+    pub fn reliable(&mut self, command: Received) -> Vec<Received> {
+        let seq = command.seq();
+        if seq <= self.last || self.held.contains_key(&seq) {
+            warn!("Reliable command {seq} already received");
+            return Vec::new();
+        }
+        self.held.insert(seq, command);
+
+        if !self.held.contains_key(&(self.last + 1)) {
+            if self.held.len() <= MAX_PENDING {
+                return Vec::new();
+            }
+            if let Some(&first) = self.held.keys().next() {
+                warn!("Reliable commands {}..{first} missing", self.last + 1);
+                self.last = first - 1;
+            }
+        }
+
+        let mut ready = Vec::new();
+        while let Some(command) = self.held.remove(&(self.last + 1)) {
+            self.last += 1;
+            ready.push(command);
+            self.release_pending(&mut ready);
+        }
+
+        ready
+    }
+
+    /// Moves the datagrams sent before the last reliable command to `ready`.
+    fn release_pending(&mut self, ready: &mut Vec<Received>) {
+        let mut waiting = VecDeque::new();
+        for pending in self.pending.drain(..) {
+            match pending.seq() <= self.last {
+                true => ready.push(pending),
+                false => waiting.push_back(pending),
+            }
+        }
+        self.pending = waiting;
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_models::{Command, CommandKind, Key, MouseButton};
+
+    fn sequenced(seq: u64, command: Command) -> Received {
+        Received::now(SequencedCommand::new(seq, command))
+    }
+
+    fn kinds(commands: Vec<Received>) -> Vec<CommandKind> {
+        commands
+            .iter()
+            .map(|received| received.command.command.kind())
+            .collect()
+    }
+
+    #[test]
+    fn test_datagram_waits_for_reliable() -> Result<()> {
+        let mut merger = CommandMerger::default();
+
+        // Motion before any reliable command goes through.
+        let ready = merger.datagram(sequenced(
+            0,
+            Command::SetMouse {
+                display: 0,
+                x: 1,
+                y: 1,
+            },
+        ));
+        assert_eq!(kinds(ready), vec![CommandKind::SetMouse]);
+
+        // The drag arrives before its button press.
+        let ready = merger.datagram(sequenced(1, Command::MoveMouse { x: 5, y: 0 }));
+        assert!(ready.is_empty());
+
+        let press = Command::MouseButtonPressed(MouseButton::Left);
+        let ready = merger.reliable(sequenced(1, press));
+        assert_eq!(
+            kinds(ready),
+            vec![CommandKind::MouseButtonPressed, CommandKind::MoveMouse]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_late_datagram_applies() -> Result<()> {
+        let mut merger = CommandMerger::default();
+        merger.reliable(sequenced(1, Command::KeyPressed(Key::A)));
+        merger.reliable(sequenced(2, Command::KeyReleased(Key::A)));
+
+        let ready = merger.datagram(sequenced(1, Command::MoveMouse { x: 1, y: 0 }));
+        assert_eq!(kinds(ready), vec![CommandKind::MoveMouse]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_duplicate_reliable_ignored() -> Result<()> {
+        let mut merger = CommandMerger::default();
+        merger.reliable(sequenced(1, Command::KeyPressed(Key::A)));
+
+        assert!(merger
+            .reliable(sequenced(1, Command::KeyPressed(Key::A)))
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reliable_held_until_gap_fills() -> Result<()> {
+        let mut merger = CommandMerger::default();
+        merger.reliable(sequenced(1, Command::KeyPressed(Key::A)));
+
+        // Resent on a new stream, 3 overtakes 2 still on the old one.
+        assert!(merger
+            .reliable(sequenced(3, Command::KeyPressed(Key::B)))
+            .is_empty());
+        assert!(merger
+            .datagram(sequenced(2, Command::MoveMouse { x: 1, y: 0 }))
+            .is_empty());
+        let ready = merger.reliable(sequenced(2, Command::KeyReleased(Key::A)));
+        assert_eq!(
+            kinds(ready),
+            vec![
+                CommandKind::KeyReleased,
+                CommandKind::MoveMouse,
+                CommandKind::KeyPressed
+            ]
+        );
+        assert!(merger
+            .reliable(sequenced(3, Command::KeyPressed(Key::B)))
+            .is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_missing_reliable_skipped() -> Result<()> {
+        let mut merger = CommandMerger::default();
+
+        for seq in 2..=MAX_PENDING as u64 + 1 {
+            assert!(merger
+                .reliable(sequenced(seq, Command::MoveMouse { x: 1, y: 0 }))
+                .is_empty());
+        }
+        let ready = merger.reliable(sequenced(
+            MAX_PENDING as u64 + 2,
+            Command::MoveMouse { x: 1, y: 0 },
+        ));
+        assert_eq!(ready.len(), MAX_PENDING + 1);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
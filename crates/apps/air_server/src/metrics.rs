@@ -0,0 +1,173 @@
+//! Live metrics of the sessions, logged periodically and optionally served in
+//! the Prometheus text format.
+
+use lib_metrics::{prometheus::Exposition, Latency, Rate, SequenceTracker};
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Arc,
+    },
+    time::Duration,
+};
+use tracing::{info, info_span};
+
+/// Metrics of every session, cheap to clone.
+#[derive(Debug, Clone, Default)]
+pub struct Metrics {
+    inner: Arc<Inner>,
+}
+
+#[derive(Debug, Default)]
+struct Inner {
+    commands: Rate,
+    /// From the client sending a command to its injection.
+    latency: Latency,
+    /// From decoding a command to its injection.
+    inject: Latency,
+    /// Commands skipped in the numbering that didn't arrive yet.
+    lost: AtomicU64,
+    reordered: AtomicU64,
+    pings: AtomicU64,
+}
+
+impl Metrics {
+    /// Counts a received command in its session numbering.
+    pub fn observe(&self, tracker: &mut SequenceTracker, id: u64) {
+        // Commands without a number are not counted.
+        if id == 0 {
+            return;
+        }
+
+        let observed = tracker.observe(id);
+        self.inner
+            .lost
+            .fetch_add(observed.skipped, Ordering::Relaxed);
+        if observed.late {
+            self.inner.lost.fetch_sub(1, Ordering::Relaxed);
+            self.inner.reordered.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
+    /// Records an injected command, `sent_at` is 0 when the client clock is
+    /// not synced yet.
+    pub fn injected(&self, decoded: Duration, sent_at: u64) {
+        self.inner.commands.add(1);
+        self.inner.inject.record(decoded);
+
+        if sent_at != 0 {
+            let latency = lib_metrics::now_micros().saturating_sub(sent_at);
+            self.inner.latency.record(Duration::from_micros(latency));
+        }
+    }
+
+    pub fn ping(&self) {
+        self.inner.pings.fetch_add(1, Ordering::Relaxed);
+    }
+
+    pub fn latency(&self) -> &Latency {
+        &self.inner.latency
+    }
+
+    pub fn inject(&self) -> &Latency {
+        &self.inner.inject
+    }
+
+    pub fn lost(&self) -> u64 {
+        self.inner.lost.load(Ordering::Relaxed)
+    }
+
+    pub fn reordered(&self) -> u64 {
+        self.inner.reordered.load(Ordering::Relaxed)
+    }
+
+    pub fn render(&self) -> String {
+        let inner = &self.inner;
+        Exposition::default()
+            .counter(
+                "air_server_commands_total",
+                "Commands injected.",
+                inner.commands.total(),
+            )
+            .gauge(
+                "air_server_commands_per_second",
+                "Commands injected per second.",
+                inner.commands.per_second(),
+            )
+            .summary(
+                "air_server_latency_seconds",
+                "From the client sending a command to its injection.",
+                &inner.latency,
+            )
+            .summary(
+                "air_server_inject_seconds",
+                "From decoding a command to its injection.",
+                &inner.inject,
+            )
+            .gauge(
+                "air_server_commands_lost",
+                "Commands missing from the numbering, mostly lost datagrams.",
+                self.lost() as f64,
+            )
+            .counter(
+                "air_server_commands_reordered_total",
+                "Commands that arrived after a later one.",
+                self.reordered(),
+            )
+            .counter(
+                "air_server_pings_total",
+                "Pings answered.",
+                inner.pings.load(Ordering::Relaxed),
+            )
+            .finish()
+    }
+
+    /// Logs the metrics every `period`, never returns.
+    pub async fn report_every(self, period: Duration) {
+        let mut interval = tokio::time::interval(period);
+        interval.tick().await;
+
+        loop {
+            interval.tick().await;
+
+            let _span = info_span!("metrics").entered();
+            info!(
+                commands_per_second = self.inner.commands.per_second(),
+                latency_p50 = ?self.inner.latency.p50(),
+                latency_p99 = ?self.inner.latency.p99(),
+                inject_p50 = ?self.inner.inject.p50(),
+                inject_p99 = ?self.inner.inject.p99(),
+                lost = self.lost(),
+                reordered = self.reordered(),
+                "📊 Session metrics"
+            );
+        }
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_loss_and_reorder() -> Result<()> {
+        let metrics = Metrics::default();
+        let mut tracker = SequenceTracker::default();
+
+        for id in [1, 2, 5, 4, 0, 6] {
+            metrics.observe(&mut tracker, id);
+        }
+
+        // 3 never came, 4 came after 5.
+        assert_eq!(metrics.lost(), 1);
+        assert_eq!(metrics.reordered(), 1);
+        assert!(metrics.render().contains("air_server_commands_lost 1\n"));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
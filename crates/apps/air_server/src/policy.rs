@@ -0,0 +1,491 @@
+//! What each client may do once connected, from a file of rules keyed by
+//! device:
+//!
+//! ```text
+//! # device      rule   value
+//! *             deny   text,clipboard-write
+//! laptop        allow  text
+//! *             block  Control+Alt+Delete
+//! *             block  Super
+//! 3A:F1:...:07  rate   200
+//! ```
+//!
+//! A device is its fingerprint or its name in the trusted devices, `*` is
+//! every device. Rules apply in file order, so a later `allow` undoes an
+//! earlier `deny`. `rate` is in commands per second, `off` lifts it.
+//!
+//! The file is reloaded when it changes and applies to the connected clients
+//! right away. Command kinds a client wasn't offered at its handshake stay
+//! off until it connects again.
+
+use crate::{Error, Result};
+use lib_models::{Command, CommandKind, DenyReason, Key};
+use lib_tls::{trust::Device, Fingerprint};
+use std::{fs, path::Path, str::FromStr, sync::Arc, time::Instant};
+use tokio::sync::watch;
+
+/// Categories of commands a client can be allowed or denied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Permission {
+    Pointer,
+    Keyboard,
+    /// Text typed by the server, [`Command::InputText`].
+    Text,
+    /// The client receives the server clipboard.
+    ClipboardRead,
+    /// The client sets the server clipboard.
+    ClipboardWrite,
+}
+
+impl Permission {
+    pub const ALL: [Self; 5] = [
+        Self::Pointer,
+        Self::Keyboard,
+        Self::Text,
+        Self::ClipboardRead,
+        Self::ClipboardWrite,
+    ];
+
+    /// Permission a command needs, none for the ones always allowed.
+    pub fn of(kind: CommandKind) -> Option<Self> {
+        match kind {
+            CommandKind::SetMouse
+            | CommandKind::MoveMouse
+            | CommandKind::MouseButtonPressed
+            | CommandKind::MouseButtonReleased
+            | CommandKind::MouseScroll => Some(Self::Pointer),
+            CommandKind::KeyPressed
+            | CommandKind::KeyReleased
+            | CommandKind::FocusEntered
+            | CommandKind::Modifiers
+            | CommandKind::Keymap => Some(Self::Keyboard),
+            CommandKind::InputText => Some(Self::Text),
+            CommandKind::ClipboardFormats => Some(Self::ClipboardRead),
+            CommandKind::ClipboardChunk => Some(Self::ClipboardWrite),
+            CommandKind::FocusLost | CommandKind::Batch | CommandKind::Ping => None,
+        }
+    }
+}
+
+impl FromStr for Permission {
+    type Err = ();
+
+    fn from_str(s: &str) -> core::result::Result<Self, ()> {
+        match s.trim() {
+            "pointer" => Ok(Self::Pointer),
+            "keyboard" => Ok(Self::Keyboard),
+            "text" => Ok(Self::Text),
+            "clipboard-read" => Ok(Self::ClipboardRead),
+            "clipboard-write" => Ok(Self::ClipboardWrite),
+            _ => Err(()),
+        }
+    }
+}
+
+/// Keys held together, written as `Control+Alt+Delete`. `Control`, `Alt`,
+/// `Shift` and `Super` stand for either side.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct KeyCombo(Vec<Vec<Key>>);
+
+impl KeyCombo {
+    /// Every part of the combo is in `keys`.
+    pub fn held_in(&self, keys: &[Key]) -> bool {
+        self.0
+            .iter()
+            .all(|part| part.iter().any(|key| keys.contains(key)))
+    }
+
+    pub fn contains(&self, key: &Key) -> bool {
+        self.0.iter().any(|part| part.contains(key))
+    }
+}
+
+impl FromStr for KeyCombo {
+    /// The unknown key name.
+    type Err = String;
+
+    fn from_str(s: &str) -> core::result::Result<Self, String> {
+        s.split('+')
+            .map(|name| {
+                let name = name.trim();
+                let keys = match name {
+                    "Control" | "Ctrl" => vec![Key::LeftControl, Key::RightControl],
+                    "Alt" => vec![Key::LeftAlt, Key::RightAlt],
+                    "Shift" => vec![Key::LeftShift, Key::RightShift],
+                    "Super" | "Meta" => vec![Key::LeftMeta, Key::RightMeta],
+                    "Del" => vec![Key::Delete],
+                    name => vec![Key::from_name(name).ok_or_else(|| name.to_string())?],
+                };
+                Ok(keys)
+            })
+            .collect::<core::result::Result<_, _>>()
+            .map(Self)
+    }
+}
+
+/// Policy of one client, everything is allowed by default.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ClientPolicy {
+    pub denied: Vec<Permission>,
+    pub blocked: Vec<KeyCombo>,
+    /// Commands per second.
+    pub rate: Option<u32>,
+}
+
+impl ClientPolicy {
+    pub fn allows(&self, permission: Permission) -> bool {
+        !self.denied.contains(&permission)
+    }
+
+    pub fn allows_kind(&self, kind: CommandKind) -> bool {
+        match Permission::of(kind) {
+            Some(permission) => self.allows(permission),
+            None => true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Target {
+    Any,
+    Fingerprint(Fingerprint),
+    Name(String),
+}
+
+impl Target {
+    fn matches(&self, device: &Device) -> bool {
+        match self {
+            Self::Any => true,
+            Self::Fingerprint(fingerprint) => *fingerprint == device.fingerprint,
+            Self::Name(name) => *name == device.name,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Rule {
+    Allow(Vec<Permission>),
+    Deny(Vec<Permission>),
+    Block(KeyCombo),
+    Rate(Option<u32>),
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Policies {
+    rules: Vec<(Target, Rule)>,
+}
+
+impl Policies {
+    pub fn load(path: &Path) -> Result<Self> {
+        Self::parse(&fs::read_to_string(path)?)
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut rules = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let wrong_format = || Error::PolicyWrongFormat {
+                line: index + 1,
+                content: line.to_string(),
+            };
+            let mut fields = line.split_whitespace();
+            let (Some(target), Some(rule), Some(value), None) =
+                (fields.next(), fields.next(), fields.next(), fields.next())
+            else {
+                return Err(wrong_format());
+            };
+
+            let target = match target {
+                "*" => Target::Any,
+                target => match target.parse() {
+                    Ok(fingerprint) => Target::Fingerprint(fingerprint),
+                    Err(_) => Target::Name(target.to_string()),
+                },
+            };
+            let rule = match rule {
+                "allow" => Rule::Allow(parse_permissions(value).ok_or_else(wrong_format)?),
+                "deny" => Rule::Deny(parse_permissions(value).ok_or_else(wrong_format)?),
+                "block" => Rule::Block(value.parse().map_err(|_| wrong_format())?),
+                "rate" if value == "off" => Rule::Rate(None),
+                "rate" => Rule::Rate(Some(value.parse().map_err(|_| wrong_format())?)),
+                _ => return Err(wrong_format()),
+            };
+
+            rules.push((target, rule));
+        }
+
+        Ok(Self { rules })
+    }
+
+    pub fn for_device(&self, device: &Device) -> ClientPolicy {
+        let mut policy = ClientPolicy::default();
+        let rules = self
+            .rules
+            .iter()
+            .filter(|(target, _)| target.matches(device));
+        for (_, rule) in rules {
+            match rule {
+                Rule::Allow(permissions) => policy
+                    .denied
+                    .retain(|permission| !permissions.contains(permission)),
+                Rule::Deny(permissions) => {
+                    for permission in permissions {
+                        if !policy.denied.contains(permission) {
+                            policy.denied.push(*permission);
+                        }
+                    }
+                }
+                Rule::Block(combo) => policy.blocked.push(combo.clone()),
+                Rule::Rate(rate) => policy.rate = *rate,
+            }
+        }
+
+        policy
+    }
+}
+
+/// Policies every connection follows, replaced when the file is reloaded.
+#[derive(Debug, Clone)]
+pub struct SharedPolicies(Arc<watch::Sender<Policies>>);
+
+impl SharedPolicies {
+    pub fn new(policies: Policies) -> Self {
+        Self(Arc::new(watch::Sender::new(policies)))
+    }
+
+    pub fn replace(&self, policies: Policies) {
+        self.0.send_replace(policies);
+    }
+
+    /// Receives the current policies, then each replacement.
+    pub fn subscribe(&self) -> watch::Receiver<Policies> {
+        self.0.subscribe()
+    }
+}
+
+impl From<Policies> for SharedPolicies {
+    fn from(policies: Policies) -> Self {
+        Self::new(policies)
+    }
+}
+
+/// `all` or permissions separated by commas.
+fn parse_permissions(value: &str) -> Option<Vec<Permission>> {
+    match value {
+        "all" => Some(Permission::ALL.to_vec()),
+        value => value.split(',').map(|name| name.parse().ok()).collect(),
+    }
+}
+
+/// Checks the commands of one connection against the policy of its client.
+#[derive(Debug)]
+pub struct PolicyGuard {
+    policy: ClientPolicy,
+    /// Commands the client may send right away, refilled at the rate.
+    tokens: f64,
+    refilled: Instant,
+}
+
+impl PolicyGuard {
+    pub fn new(policy: ClientPolicy) -> Self {
+        Self {
+            tokens: policy.rate.unwrap_or_default().into(),
+            refilled: Instant::now(),
+            policy,
+        }
+    }
+
+    pub fn policy(&self) -> &ClientPolicy {
+        &self.policy
+    }
+
+    /// Follows a reloaded policy, the rate keeps counting the commands
+    /// already let through.
+    pub fn set_policy(&mut self, policy: ClientPolicy) {
+        self.policy = policy;
+    }
+
+    /// Why `command` can't be applied, if it can't. `held` are the keys held
+    /// now and `after` the ones held once the command is applied.
+    ///
+    /// Releases always pass, a denied one would leave the key held.
+    pub fn check(&mut self, command: &Command, held: &[Key], after: &[Key]) -> Option<DenyReason> {
+        if matches!(
+            command,
+            Command::KeyReleased(_) | Command::MouseButtonReleased(_) | Command::FocusLost
+        ) {
+            return None;
+        }
+
+        if !self.take_token(Instant::now()) {
+            return Some(DenyReason::RateLimited);
+        }
+
+        if !self.policy.allows_kind(command.kind()) {
+            return Some(DenyReason::NotAllowed);
+        }
+
+        let pressed: Vec<&Key> = after.iter().filter(|key| !held.contains(key)).collect();
+        let blocked = self
+            .policy
+            .blocked
+            .iter()
+            .any(|combo| combo.held_in(after) && pressed.iter().any(|key| combo.contains(key)));
+        if blocked {
+            return Some(DenyReason::BlockedKeys);
+        }
+
+        None
+    }
+
+    fn take_token(&mut self, now: Instant) -> bool {
+        let Some(rate) = self.policy.rate else {
+            return true;
+        };
+
+        let rate = f64::from(rate);
+        let elapsed = now.saturating_duration_since(self.refilled).as_secs_f64();
+        self.tokens = (self.tokens + elapsed * rate).min(rate);
+        self.refilled = now;
+        if self.tokens < 1.0 {
+            return false;
+        }
+
+        self.tokens -= 1.0;
+        true
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use lib_tls::trust::DeviceStatus;
+    use std::time::Duration;
+
+    fn device(name: &str) -> Device {
+        Device {
+            fingerprint: Fingerprint::of(name.as_bytes()),
+            status: DeviceStatus::Trusted,
+            name: name.to_string(),
+        }
+    }
+
+    #[test]
+    fn test_for_device() -> Result<()> {
+        let desktop = device("desktop");
+        let policies = Policies::parse(&format!(
+            "# device rule value\n\
+             *        deny  text,clipboard-write\n\
+             laptop   allow text\n\
+             *        block Control+Alt+Delete\n\
+             {}       rate  100\n",
+            desktop.fingerprint
+        ))?;
+
+        let laptop = policies.for_device(&device("laptop"));
+        assert_eq!(laptop.denied, [Permission::ClipboardWrite]);
+        assert_eq!(laptop.rate, None);
+        assert!(!laptop.allows_kind(CommandKind::ClipboardChunk));
+        assert!(laptop.allows_kind(CommandKind::InputText));
+        assert!(laptop.allows_kind(CommandKind::Ping));
+
+        assert_eq!(
+            Policies::default().for_device(&desktop),
+            ClientPolicy::default()
+        );
+        let desktop = policies.for_device(&desktop);
+        assert_eq!(
+            desktop.denied,
+            [Permission::Text, Permission::ClipboardWrite]
+        );
+        assert_eq!(desktop.blocked, ["Control+Alt+Delete".parse()?]);
+        assert_eq!(desktop.rate, Some(100));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() -> Result<()> {
+        for (text, line) in [
+            ("* deny typing", 1),
+            ("\n* block Control+Nope", 2),
+            ("* rate fast", 1),
+            ("* deny", 1),
+            ("* forbid text", 1),
+        ] {
+            assert!(
+                matches!(
+                    Policies::parse(text),
+                    Err(Error::PolicyWrongFormat { line: l, .. }) if l == line
+                ),
+                "{text}"
+            );
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_blocked_keys() -> Result<()> {
+        let mut guard = PolicyGuard::new(ClientPolicy {
+            blocked: vec!["Control+Alt+Delete".parse()?, "Super".parse()?],
+            ..Default::default()
+        });
+        let held = [Key::RightControl, Key::LeftAlt];
+        let delete = Command::KeyPressed(Key::Delete);
+
+        assert_eq!(
+            guard.check(
+                &delete,
+                &held,
+                &[Key::RightControl, Key::LeftAlt, Key::Delete]
+            ),
+            Some(DenyReason::BlockedKeys)
+        );
+        assert_eq!(guard.check(&delete, &[], &[Key::Delete]), None);
+        assert_eq!(
+            guard.check(&Command::KeyPressed(Key::LeftMeta), &[], &[Key::LeftMeta]),
+            Some(DenyReason::BlockedKeys)
+        );
+        // Releasing a key of a blocked combo is fine.
+        assert_eq!(
+            guard.check(&Command::KeyReleased(Key::LeftMeta), &[Key::LeftMeta], &[]),
+            None
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_rate_limit() -> Result<()> {
+        let mut guard = PolicyGuard::new(ClientPolicy {
+            rate: Some(10),
+            ..Default::default()
+        });
+        let start = guard.refilled;
+
+        assert!((0..10).all(|_| guard.take_token(start)));
+        assert!(!guard.take_token(start));
+        assert!(guard.take_token(start + Duration::from_millis(100)));
+        assert!(!guard.take_token(start + Duration::from_millis(100)));
+
+        // Past the rate, only releases pass.
+        assert_eq!(
+            guard.check(&Command::MoveMouse { x: 1, y: 1 }, &[], &[]),
+            Some(DenyReason::RateLimited)
+        );
+        assert_eq!(guard.check(&Command::FocusLost, &[], &[]), None);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,55 @@
+//! Follows the config file and the policy file while the server runs.
+
+use crate::{config, Config, Policies, Result, SharedPolicies};
+use lib_config::{FileWatch, Settings};
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// How often the files are checked for changes.
+const PERIOD: Duration = Duration::from_secs(1);
+
+/// Reloads the config and the policy file when they change. The policies
+/// apply to the connected clients right away, the other settings are
+/// reported as needing a restart. A file that doesn't load keeps the
+/// current config.
+pub async fn watch_config(mut settings: Settings, policies: SharedPolicies) {
+    let mut files = watched(&settings);
+    let mut interval = tokio::time::interval(PERIOD);
+    loop {
+        interval.tick().await;
+        if !files.changed() {
+            continue;
+        }
+
+        match reload(&settings, &policies) {
+            Ok(reloaded) => settings = reloaded,
+            Err(e) => warn!("Config not reloaded, keeping the current one: {e}"),
+        }
+        // The policy file may have moved.
+        files = watched(&settings);
+    }
+}
+
+fn reload(settings: &Settings, policies: &SharedPolicies) -> Result<Settings> {
+    let settings = settings.reload()?;
+    let cfg = Config::load(&settings)?;
+    let reloaded = match &cfg.POLICY {
+        Some(path) => Policies::load(path)?,
+        None => Policies::default(),
+    };
+
+    info!("Config reloaded");
+    lib_config::report(&Config::reload(cfg));
+    policies.replace(reloaded);
+
+    Ok(settings)
+}
+
+fn watched(settings: &Settings) -> FileWatch {
+    FileWatch::new(
+        settings
+            .path()
+            .into_iter()
+            .chain(config().POLICY.as_deref()),
+    )
+}
@@ -0,0 +1,604 @@
+//! Connection handling: decodes commands from a client and injects them.
+
+use crate::{
+    config, handshake,
+    merge::{CommandMerger, Received},
+    ClientPolicy, Devices, ErrorClass, ErrorPolicies, ErrorPolicy, InputBackend, InputSimulator,
+    Metrics, Permission, Policies, PolicyGuard, PressedTracker, Recording, Result, SharedPolicies,
+    Simulator,
+};
+use lib_codec::{
+    frame,
+    stream::{Decoder, Encoder},
+};
+use lib_metrics::SequenceTracker;
+use lib_models::{
+    clipboard::{ClipboardBackend, ClipboardSync},
+    handshake::{ClientHello, ServerHello, UNTRUSTED_DEVICE},
+    keymap::{keysym, Keymap},
+    Answer, Command, CommandKind, DenyReason, DisplayParams, InputErrorKind, Key, MouseButton,
+    SequencedCommand,
+};
+use lib_quic::{
+    datagram::{Datagram, ReceivedDatagram},
+    quinn,
+};
+use lib_tls::{trust::Device, Fingerprint};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::{error, info, warn};
+
+/// State shared by every connection.
+#[derive(Debug, Clone)]
+pub struct ServerState {
+    pub backends: Vec<InputBackend>,
+    /// Sink for the `recording` backend.
+    pub recording: Recording,
+    pub error_policies: ErrorPolicies,
+    /// Screens reported to the clients, absolute positions are mapped to them.
+    pub displays: Vec<DisplayParams>,
+    pub clipboard: ClipboardBackend,
+    pub clipboard_max_size: usize,
+    pub clipboard_poll: Duration,
+    /// Releases held keys and buttons after this long without commands.
+    pub idle_release: Option<Duration>,
+    pub metrics: Metrics,
+    /// Clients allowed in.
+    pub devices: Devices,
+    /// What each of them may do.
+    pub policies: SharedPolicies,
+}
+
+impl ServerState {
+    /// `server` is the fingerprint of the server certificate.
+    pub fn from_config(server: Fingerprint) -> Result<Self> {
+        let recording = match &config().RECORDING_PATH {
+            Some(path) => Recording::to_file(path)?,
+            None => Recording::default(),
+        };
+        let policies = match &config().POLICY {
+            Some(path) => Policies::load(path)?,
+            None => Policies::default(),
+        };
+
+        Ok(Self {
+            backends: config().INPUT_BACKENDS.clone(),
+            recording,
+            error_policies: config().INPUT_ERROR_POLICY.clone(),
+            displays: config().SCREENS.clone(),
+            clipboard: config().CLIPBOARD.clone(),
+            clipboard_max_size: config().CLIPBOARD_MAX_SIZE,
+            clipboard_poll: config().CLIPBOARD_POLL,
+            idle_release: config().IDLE_RELEASE,
+            metrics: Metrics::default(),
+            devices: Devices::new(config().TRUSTED_DEVICES.clone(), server)
+                .with_pairing(config().PAIRING),
+            policies: policies.into(),
+        })
+    }
+}
+
+type Input = PressedTracker<Simulator>;
+
+struct Handler {
+    datagram: Datagram,
+    answers: Encoder<quinn::SendStream>,
+    error_policies: ErrorPolicies,
+    metrics: Metrics,
+    displays: Vec<DisplayParams>,
+    clipboard: Option<ClipboardSync>,
+    /// Keymap of the client, keys are injected without it until it arrives.
+    keymap: Option<Keymap>,
+    /// Layout group active on the client.
+    group: u32,
+    device: Device,
+    guard: PolicyGuard,
+    /// Commands are denied for the rate, reported once until one passes.
+    rate_limited: bool,
+}
+
+impl Handler {
+    /// Runs the handshake, then opens the answer stream.
+    pub async fn new(
+        connection: quinn::Connection,
+        state: &ServerState,
+        device: Device,
+    ) -> Result<Self> {
+        let guard = PolicyGuard::new(state.policies.subscribe().borrow().for_device(&device));
+        let policy = guard.policy();
+        let mut clipboard = state
+            .clipboard
+            .open()
+            .unwrap_or_else(|e| {
+                warn!("Clipboard not synchronised, it can't be opened: {e}");
+                None
+            })
+            .filter(|_| {
+                policy.allows(Permission::ClipboardRead)
+                    || policy.allows(Permission::ClipboardWrite)
+            })
+            .map(|clipboard| ClipboardSync::new(clipboard, state.clipboard_max_size));
+
+        let hello = ServerHello {
+            name: handshake::SERVER_NAME.to_string(),
+            commands: CommandKind::ALL
+                .into_iter()
+                .filter(|command| clipboard.is_some() || !command.is_clipboard())
+                .filter(|command| policy.allows_kind(*command))
+                .collect(),
+            displays: state.displays.clone(),
+            clipboard_formats: clipboard
+                .as_ref()
+                .map(ClipboardSync::formats)
+                .unwrap_or_default(),
+        };
+        let client = handshake::accept(&connection, hello).await?;
+        info!("Client {} at {}", client.name, connection.remote_address());
+
+        // Without a clipboard on the client there is nothing to sync with.
+        if !has_clipboard(&client) {
+            clipboard = None;
+        }
+        if let Some(sync) = clipboard.as_mut() {
+            sync.set_peer_formats(client.clipboard_formats);
+            // The client clipboard wins on connect.
+            if let Err(e) = sync.mark_seen() {
+                warn!("Can't read the clipboard: {e}");
+            }
+        }
+
+        let answers = Encoder::new(connection.open_uni().await?);
+        let datagram = Datagram::new(connection);
+
+        Ok(Self {
+            datagram,
+            answers,
+            error_policies: state.error_policies.clone(),
+            metrics: state.metrics.clone(),
+            displays: state.displays.clone(),
+            clipboard,
+            keymap: None,
+            group: 0,
+            device,
+            guard,
+            rate_limited: false,
+        })
+    }
+
+    /// Follows a reloaded policy.
+    fn set_policy(&mut self, policy: ClientPolicy) {
+        if policy != *self.guard.policy() {
+            info!("Policy of {} reloaded: {:?}", self.device.name, policy);
+            self.guard.set_policy(policy);
+        }
+    }
+
+    async fn receive(&self) -> Option<ReceivedDatagram> {
+        self.datagram.receive().await
+    }
+
+    fn process(&mut self, input: &mut Input, command: &Command) -> Result<()> {
+        // info!("Reveived command: {:?}", command);
+
+        let result = match command {
+            Command::SetMouse { display, x, y } => match self.displays.get(*display as usize) {
+                Some(params) => {
+                    let (x, y) = params.to_desktop(*x, *y);
+                    input.set_mouse(x, y)
+                }
+                None => Err(crate::Error::input(InputErrorKind::UnknownDisplay(
+                    *display,
+                ))),
+            },
+            Command::MoveMouse { x, y } => input.move_mouse(*x, *y),
+            Command::MouseButtonPressed(button) => input.mouse_press(*button),
+            Command::MouseButtonReleased(button) => input.mouse_release(*button),
+            Command::MouseScroll(scroll) => input.scroll(*scroll),
+            Command::InputText(text) => input.text(text),
+            Command::KeyPressed(key) => match self.keymap.as_ref() {
+                Some(keymap) => input.key_press_translated(keymap.translate(*key, self.group)),
+                None => input.key_press(*key),
+            },
+            Command::KeyReleased(key) => input.key_release(*key),
+            Command::FocusEntered { pressed } => input.sync_keys(pressed),
+            Command::FocusLost => input.release_all(),
+            Command::Modifiers(state) => {
+                self.group = state.group;
+                input.sync_modifiers(state)
+            }
+            Command::Keymap(text) => Keymap::parse(text)
+                .map(|keymap| self.keymap = Some(keymap))
+                .map_err(Into::into),
+            Command::ClipboardChunk(chunk) => match self.clipboard.as_mut() {
+                Some(sync) => sync.receive(chunk.clone()).map(|_| ()).map_err(Into::into),
+                None => Err(crate::Error::input(InputErrorKind::UnsupportedCommand)),
+            },
+            Command::ClipboardFormats(formats) => match self.clipboard.as_mut() {
+                Some(sync) => {
+                    sync.set_peer_formats(formats.clone());
+                    Ok(())
+                }
+                None => Err(crate::Error::input(InputErrorKind::UnsupportedCommand)),
+            },
+            // Handled in `apply`, the client never nests batches.
+            Command::Batch(_) | Command::Ping { .. } => {
+                Err(crate::Error::input(InputErrorKind::UnsupportedCommand))
+            }
+        };
+
+        result.map_err(|e| e.for_command(command.kind()))
+    }
+
+    /// Applies a command, the commands of a batch back to back so nothing from
+    /// the other channel comes between them.
+    ///
+    /// Returns an error only when the connection should be closed.
+    async fn apply(&mut self, input: &mut Input, command: Command) -> Result<()> {
+        match command {
+            Command::Batch(commands) => self.apply_batch(input, commands).await,
+            command => self.apply_one(input, command).await,
+        }
+    }
+
+    /// Applies a batch whole or not at all: it is checked before anything is
+    /// injected, and when a command fails the rest is dropped and the buttons
+    /// it pressed are released. Motion already made stays.
+    async fn apply_batch(&mut self, input: &mut Input, commands: Vec<Command>) -> Result<()> {
+        if commands.iter().any(|command| !command.is_batchable()) {
+            let error = InputErrorKind::UnsupportedCommand;
+            warn!("Failed to apply {:?}: {error:?}", CommandKind::Batch);
+            self.answer(&Answer::CommandFailed {
+                command: CommandKind::Batch,
+                error,
+            })
+            .await;
+            return Ok(());
+        }
+
+        for command in &commands {
+            if let Some(reason) = self.admit(input, command) {
+                self.deny(CommandKind::Batch, reason).await;
+                return Ok(());
+            }
+        }
+
+        let held = input.pressed_buttons().to_vec();
+        for command in commands {
+            if self.execute(input, command).await? {
+                continue;
+            }
+
+            let pressed: Vec<MouseButton> = input
+                .pressed_buttons()
+                .iter()
+                .copied()
+                .filter(|button| !held.contains(button))
+                .collect();
+            for button in pressed {
+                if let Err(e) = input.mouse_release(button) {
+                    warn!("Failed to release {button:?} of a dropped batch: {e:?}");
+                }
+            }
+            break;
+        }
+
+        Ok(())
+    }
+
+    /// Processes a command following the error policy of its failure class.
+    async fn apply_one(&mut self, input: &mut Input, command: Command) -> Result<()> {
+        if let Command::Ping { id, sent_at } = command {
+            let received_at = lib_metrics::now_micros();
+            self.metrics.ping();
+            self.answer(&Answer::Pong {
+                id,
+                sent_at,
+                received_at,
+            })
+            .await;
+            return Ok(());
+        }
+
+        if let Some(reason) = self.admit(input, &command) {
+            self.deny(command.kind(), reason).await;
+            return Ok(());
+        }
+
+        self.execute(input, command).await.map(|_| ())
+    }
+
+    /// Checks a command against the policy of the client, the reason when it
+    /// is denied.
+    fn admit(&mut self, input: &Input, command: &Command) -> Option<DenyReason> {
+        let held = self.injected_keys(input.pressed_keys());
+        let after = self.injected_keys(&input.held_after(command));
+        let reason = self.guard.check(command, &held, &after);
+        if reason.is_none() {
+            self.rate_limited = false;
+        }
+        reason
+    }
+
+    /// Keys the backend may inject for the physical `keys`: the keys
+    /// themselves, and the keys of their keysyms through the keymap of the
+    /// client. Blocked combos are checked on both, so a keymap can't turn a
+    /// harmless key into a blocked one.
+    fn injected_keys(&self, keys: &[Key]) -> Vec<Key> {
+        let mut injected = keys.to_vec();
+        let Some(keymap) = self.keymap.as_ref() else {
+            return injected;
+        };
+
+        for &key in keys {
+            let translated = match keymap.translate(key, self.group).keysym {
+                Some(keysym::META_L) => Some(Key::LeftMeta),
+                Some(keysym::META_R) => Some(Key::RightMeta),
+                Some(keysym) => Key::from_keysym(keysym),
+                None => None,
+            };
+            if let Some(translated) = translated.filter(|key| !injected.contains(key)) {
+                injected.push(translated);
+            }
+        }
+        injected
+    }
+
+    /// Injects a command, retried as its error policy says. Returns whether it
+    /// was applied.
+    async fn execute(&mut self, input: &mut Input, command: Command) -> Result<bool> {
+        let mut attempt = 0;
+
+        loop {
+            let error = match self.process(input, &command) {
+                Ok(()) => return Ok(true),
+                Err(crate::Error::Input { kind, .. }) => kind,
+                Err(e) => return Err(e),
+            };
+
+            let policy = self
+                .error_policies
+                .for_command(ErrorClass::of(&error), command.kind());
+            if policy == ErrorPolicy::Retry && attempt < self.error_policies.retries {
+                attempt += 1;
+                warn!("Retrying {:?} ({attempt}): {error:?}", command.kind());
+                continue;
+            }
+
+            warn!("Failed to apply {:?}: {error:?}", command.kind());
+            self.answer(&Answer::CommandFailed {
+                command: command.kind(),
+                error: error.clone(),
+            })
+            .await;
+
+            return match policy {
+                ErrorPolicy::Disconnect => Err(crate::Error::Input {
+                    kind: error,
+                    command: Some(command.kind()),
+                }),
+                ErrorPolicy::Skip | ErrorPolicy::Retry => Ok(false),
+            };
+        }
+    }
+
+    /// Reports a denied command, a flood past the rate only once.
+    async fn deny(&mut self, command: CommandKind, reason: DenyReason) {
+        if reason == DenyReason::RateLimited {
+            if self.rate_limited {
+                return;
+            }
+            self.rate_limited = true;
+        }
+
+        warn!(
+            "Denied {command:?} from {} ({}): {reason:?}",
+            self.device.name, self.device.fingerprint
+        );
+        self.answer(&Answer::CommandDenied { command, reason })
+            .await;
+    }
+
+    /// Sends the server clipboard to the client when it changed.
+    async fn poll_clipboard(&mut self) {
+        if !self.guard.policy().allows(Permission::ClipboardRead) {
+            return;
+        }
+        let Some(sync) = self.clipboard.as_mut() else {
+            return;
+        };
+
+        let chunks = match sync.poll_chunks() {
+            Ok(chunks) => chunks,
+            Err(e) => {
+                warn!("Clipboard not sent: {e}");
+                return;
+            }
+        };
+
+        for chunk in chunks {
+            self.answer(&Answer::ClipboardChunk(chunk)).await;
+        }
+    }
+
+    /// Sends an answer to the client, failures are only logged.
+    async fn answer(&mut self, answer: &Answer) {
+        if let Err(e) = self.answers.send(answer).await {
+            warn!("Failed to send answer: {e}");
+        }
+    }
+}
+
+/// Accepts connections until the endpoint closes, each runs [`handler`].
+pub async fn serve(endpoint: quinn::Endpoint, state: ServerState) {
+    while let Some(incoming) = endpoint.accept().await {
+        let state = state.clone();
+        tokio::spawn(async move {
+            match incoming.await {
+                Ok(connection) => {
+                    if let Err(e) = handler(connection, state).await {
+                        error!("Connection failed: {e}");
+                    }
+                }
+                Err(e) => warn!("Incoming connection failed: {e}"),
+            }
+        });
+    }
+}
+
+pub async fn handler(connection: quinn::Connection, state: ServerState) -> lib_quic::Result<()> {
+    let address = connection.remote_address();
+    info!("New connection: {}", address);
+
+    // Nothing is read from a device before it is trusted.
+    let device = match state.devices.authorize(&connection).await {
+        Ok(device) => {
+            info!("Device {} ({}) authorized", device.name, device.fingerprint);
+            device
+        }
+        Err(e) => {
+            warn!("Connection from {address} rejected: {e}");
+            connection.close(UNTRUSTED_DEVICE.into(), b"untrusted device");
+            return Ok(());
+        }
+    };
+
+    let mut input = match Simulator::from_backends(&state.backends, &state.recording) {
+        Ok(input) => PressedTracker::new(input),
+        Err(e) => {
+            error!("Can't inject input for {address}: {e}");
+            connection.close(0u32.into(), b"no input backend");
+            return Ok(());
+        }
+    };
+
+    // Commands that must not be lost come on uni streams opened by the client.
+    let (stream_tx, stream_rx) = flume::bounded(16);
+    let stream_task = tokio::spawn(receive_streams(connection.clone(), stream_tx));
+
+    let mut policies = state.policies.subscribe();
+    let mut handler = match Handler::new(connection.clone(), &state, device).await {
+        Ok(handler) => handler,
+        Err(e) => {
+            error!("Can't start the session with {address}: {e}");
+            stream_task.abort();
+            if let crate::Error::HandshakeRejected(_) = e {
+                handshake::linger(&connection).await;
+            }
+            connection.close(1u32.into(), b"handshake failed");
+            return Ok(());
+        }
+    };
+
+    let has_clipboard = handler.clipboard.is_some();
+    let mut clipboard_poll = tokio::time::interval(state.clipboard_poll);
+    let mut merger = CommandMerger::default();
+    let mut sequence = SequenceTracker::default();
+    let mut last_command = Instant::now();
+
+    'connection: loop {
+        let ready = tokio::select! {
+            data = handler.receive() => {
+                let Some(data) = data else {
+                    break;
+                };
+                let command = match frame::decode_message::<SequencedCommand>(&data.data) {
+                    Ok(command) => command,
+                    Err(e) => {
+                        error!("Datagram dropped, bad frame: {e}");
+                        continue;
+                    }
+                };
+                state.metrics.observe(&mut sequence, command.id);
+                merger.datagram(Received::now(command))
+            }
+            Ok(received) = stream_rx.recv_async() => {
+                state.metrics.observe(&mut sequence, received.command.id);
+                // Clipboard transfers aren't numbered, input doesn't wait for them.
+                match received.command.command.kind().is_clipboard() {
+                    true => vec![received],
+                    false => merger.reliable(received),
+                }
+            }
+            _ = clipboard_poll.tick(), if has_clipboard => {
+                handler.poll_clipboard().await;
+                continue;
+            }
+            Ok(()) = policies.changed() => {
+                handler.set_policy(policies.borrow_and_update().for_device(&handler.device));
+                continue;
+            }
+            _ = idle_timeout(last_command, state.idle_release), if input.has_pressed() => {
+                info!("No command from {address} for a while");
+                if let Err(e) = input.release_all() {
+                    warn!("Failed to release held input: {e}");
+                }
+                continue;
+            }
+        };
+        last_command = Instant::now();
+
+        for Received { command, at } in ready {
+            let sent_at = command.sent_at;
+            if let Err(e) = handler.apply(&mut input, command.command).await {
+                error!("Error occured: {}", e);
+                break 'connection;
+            }
+            state.metrics.injected(at.elapsed(), sent_at);
+        }
+    }
+
+    stream_task.abort();
+    if let Err(e) = input.release_all() {
+        warn!("Failed to release held input: {e}");
+    }
+    info!("👋 Client disconnected: {}", address);
+
+    Ok(())
+}
+
+fn has_clipboard(client: &ClientHello) -> bool {
+    !client.clipboard_formats.is_empty() && client.commands.contains(&CommandKind::ClipboardChunk)
+}
+
+/// Completes `timeout` after `since`, never without a timeout.
+async fn idle_timeout(since: Instant, timeout: Option<Duration>) {
+    match timeout {
+        Some(timeout) => tokio::time::sleep_until(since + timeout).await,
+        None => std::future::pending().await,
+    }
+}
+
+/// Forwards the commands of the client uni streams to `tx`. Input and
+/// clipboard come on streams of their own, and input on a new stream after a
+/// failed write, so every stream is read side by side. The
+/// [`CommandMerger`] puts the input back in order.
+async fn receive_streams(connection: quinn::Connection, tx: flume::Sender<Received>) {
+    let mut streams = tokio::task::JoinSet::new();
+    loop {
+        let stream = match connection.accept_uni().await {
+            Ok(stream) => stream,
+            Err(e) => {
+                info!("Command stream not opened: {e}");
+                return;
+            }
+        };
+        streams.spawn(receive_stream(Decoder::new(stream), tx.clone()));
+    }
+}
+
+async fn receive_stream(mut commands: Decoder<quinn::RecvStream>, tx: flume::Sender<Received>) {
+    loop {
+        match commands.recv::<SequencedCommand>().await {
+            Ok(Some(command)) => {
+                if tx.send_async(Received::now(command)).await.is_err() {
+                    return;
+                }
+            }
+            Ok(None) => return,
+            Err(e) => {
+                warn!("Command stream closed: {e}");
+                return;
+            }
+        }
+    }
+}
@@ -0,0 +1,1056 @@
+//! Runs the connection handler over a loopback QUIC connection with the
+//! recording backend and checks what would have been injected.
+
+use air_server::{
+    serve, Devices, ErrorPolicies, InputBackend, Metrics, Policies, RecordedEvent, Recording,
+    ServerState, SERVER_NAME,
+};
+use lib_codec::{
+    frame::{self, Flags},
+    stream::{Decoder, Encoder},
+};
+use lib_models::{
+    clipboard::{
+        ChunkAssembler, Clipboard, ClipboardBackend, ClipboardPayload, MemoryClipboard, CHUNK_SIZE,
+        DEFAULT_MAX_SIZE, IMAGE_PNG, TEXT_HTML, TEXT_PLAIN,
+    },
+    handshake::{
+        ClientHello, HandshakeError, HandshakeReply, ProtocolVersion, MIN_PROTOCOL_VERSION,
+        PROTOCOL_VERSION, UNTRUSTED_DEVICE,
+    },
+    Answer, Command, CommandBatch, CommandKind, Delivery, DenyReason, DisplayParams,
+    InputErrorKind, Key, ModifierState, MouseButton, MouseScroll, ScrollDelta, SequencedCommand,
+    NORMALIZED_ONE,
+};
+use lib_quic::{
+    datagram::{Datagram, DatagramType},
+    tls::TlsLoader,
+    Ssrc,
+};
+use lib_tls::{trust::TrustStore, Identity};
+use std::{
+    path::PathBuf,
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+    time::{Duration, Instant},
+};
+
+type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+fn cert_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("../../../certs")
+        .join(name)
+}
+
+fn server_identity() -> &'static Identity {
+    static IDENTITY: OnceLock<Identity> = OnceLock::new();
+    IDENTITY.get_or_init(|| {
+        Identity::load(&cert_path("cert.pem"), &cert_path("key.pem")).expect("server identity")
+    })
+}
+
+/// Device of the loopback client, created once per test run.
+fn client_identity() -> &'static Identity {
+    static IDENTITY: OnceLock<Identity> = OnceLock::new();
+    IDENTITY.get_or_init(|| {
+        let dir = temp_dir("client");
+        Identity::create(
+            &dir.join("cert.pem"),
+            &dir.join("key.pem"),
+            vec!["loopback".to_string()],
+        )
+        .expect("client identity")
+    })
+}
+
+fn temp_dir(name: &str) -> PathBuf {
+    static NEXT: AtomicUsize = AtomicUsize::new(0);
+    std::env::temp_dir().join(format!(
+        "air-loopback-{}-{name}-{}",
+        std::process::id(),
+        NEXT.fetch_add(1, Ordering::Relaxed)
+    ))
+}
+
+/// Devices of a server whose store holds `store`.
+fn devices(store: TrustStore) -> Devices {
+    let path = temp_dir("devices").join("trusted_devices");
+    store.save(&path).expect("trust store written");
+    Devices::new(path, server_identity().fingerprint())
+}
+
+/// A store trusting the loopback client.
+fn trusted_store() -> TrustStore {
+    let mut store = TrustStore::default();
+    store.trust(client_identity().fingerprint(), "loopback");
+    store
+}
+
+/// Waits until `recording` holds `count` events or the timeout expires.
+async fn wait_for(recording: &Recording, count: usize) -> Vec<RecordedEvent> {
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while recording.events().len() < count && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    recording.events()
+}
+
+fn state(recording: &Recording, clipboard: ClipboardBackend) -> ServerState {
+    ServerState {
+        backends: vec![InputBackend::Recording],
+        recording: recording.clone(),
+        error_policies: ErrorPolicies::default(),
+        displays: vec![DisplayParams::new(2048, 1024)],
+        clipboard,
+        clipboard_max_size: DEFAULT_MAX_SIZE,
+        clipboard_poll: Duration::from_millis(10),
+        idle_release: None,
+        metrics: Metrics::default(),
+        devices: devices(trusted_store()),
+        policies: Policies::default().into(),
+    }
+}
+
+async fn read_answer(answers: &mut lib_quic::quinn::RecvStream) -> Result<Option<Answer>> {
+    let answer = tokio::time::timeout(
+        Duration::from_secs(5),
+        Decoder::new(answers).recv::<Answer>(),
+    )
+    .await??;
+
+    Ok(answer)
+}
+
+/// Sends commands the way the client event handler does.
+struct Sender {
+    connection: lib_quic::quinn::Connection,
+    datagram: Datagram,
+    stream: Encoder<lib_quic::quinn::SendStream>,
+    /// Opened on the first clipboard command.
+    clipboard: Option<Encoder<lib_quic::quinn::SendStream>>,
+    seq: u64,
+    id: u64,
+}
+
+impl Sender {
+    async fn new(connection: lib_quic::quinn::Connection) -> Result<Self> {
+        Ok(Self {
+            stream: Encoder::new(connection.open_uni().await?),
+            datagram: Datagram::new(connection.clone()),
+            connection,
+            clipboard: None,
+            seq: 0,
+            id: 0,
+        })
+    }
+
+    async fn send(&mut self, command: Command) -> Result<()> {
+        match command.delivery() {
+            Delivery::Datagram => self.send_datagram(self.seq, command),
+            Delivery::Reliable if command.kind().is_clipboard() => {
+                let command = self.stamp(self.seq, command);
+                let stream = match self.clipboard.as_mut() {
+                    Some(stream) => stream,
+                    None => self
+                        .clipboard
+                        .insert(Encoder::new(self.connection.open_uni().await?)),
+                };
+                stream.send(&command).await?;
+                Ok(())
+            }
+            Delivery::Reliable => {
+                self.seq += 1;
+                let command = self.stamp(self.seq, command);
+                self.stream.send(&command).await?;
+                Ok(())
+            }
+        }
+    }
+
+    /// Numbers the command, both peers share the clock.
+    fn stamp(&mut self, seq: u64, command: Command) -> SequencedCommand {
+        self.id += 1;
+        SequencedCommand::new(seq, command).with_stamp(self.id, lib_metrics::now_micros())
+    }
+
+    fn send_datagram(&mut self, seq: u64, command: Command) -> Result<()> {
+        let encoded = frame::encode(&self.stamp(seq, command), Flags::CHECKSUM)?;
+        self.datagram
+            .send(&encoded, 0, DatagramType::Command, Ssrc(1))?;
+        Ok(())
+    }
+}
+
+/// Starts a server, connects a client and runs the handshake the way the
+/// client does.
+async fn connect(
+    state: ServerState,
+) -> Result<(tokio::task::JoinHandle<()>, lib_quic::quinn::Connection)> {
+    let (server, connection) = connect_raw(state).await?;
+    match handshake(&connection, PROTOCOL_VERSION, fx_hello()).await? {
+        Some(HandshakeReply::Accepted(_)) => Ok((server, connection)),
+        other => Err(format!("handshake failed: {other:?}").into()),
+    }
+}
+
+fn fx_hello() -> ClientHello {
+    ClientHello {
+        name: "loopback".to_string(),
+        commands: CommandKind::ALL.to_vec(),
+        clipboard_formats: vec![TEXT_PLAIN.to_string()],
+    }
+}
+
+/// Writes `version` and `hello` on a new control stream, returns the reply
+/// when the server speaks the same version.
+async fn handshake(
+    connection: &lib_quic::quinn::Connection,
+    version: u32,
+    hello: ClientHello,
+) -> Result<Option<HandshakeReply>> {
+    let (send, recv) = connection.open_bi().await?;
+    let mut encoder = Encoder::new(send);
+    encoder.send(&ProtocolVersion(version)).await?;
+    encoder.send(&hello).await?;
+    encoder.get_mut().finish()?;
+
+    let mut decoder = Decoder::new(recv);
+    let server_version: Option<ProtocolVersion> = decoder.recv().await?;
+    assert_eq!(server_version, Some(ProtocolVersion(PROTOCOL_VERSION)));
+
+    Ok(decoder.recv().await?)
+}
+
+/// Starts a server on a free loopback port and connects a client to it.
+async fn connect_raw(
+    state: ServerState,
+) -> Result<(tokio::task::JoinHandle<()>, lib_quic::quinn::Connection)> {
+    TlsLoader::init_provider();
+
+    let endpoint = lib_tls::endpoint::server("127.0.0.1:0".parse()?, server_identity())?;
+    let address = endpoint.local_addr()?;
+    let server = tokio::spawn(serve(endpoint, state));
+
+    let client = lib_tls::endpoint::client(server_identity().certs.clone(), client_identity())?;
+    let connection = client.connect(address, "localhost")?.await?;
+
+    Ok((server, connection))
+}
+
+#[tokio::test]
+async fn test_scripted_commands() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    let scroll = MouseScroll {
+        vertical: ScrollDelta {
+            value120: 120,
+            pixels: 15.0,
+            stop: false,
+        },
+        ..Default::default()
+    };
+    let script = [
+        Command::SetMouse {
+            display: 0,
+            x: NORMALIZED_ONE / 4,
+            y: NORMALIZED_ONE / 2,
+        },
+        Command::MouseButtonPressed(MouseButton::Left),
+        Command::MouseButtonReleased(MouseButton::Left),
+        Command::MoveMouse { x: -5, y: 3 },
+        Command::MouseScroll(scroll),
+        Command::KeyPressed(Key::A),
+        Command::KeyReleased(Key::A),
+        Command::InputText("hello".to_string()),
+    ];
+
+    for command in script.iter().cloned() {
+        sender.send(command).await?;
+        // Keep the datagrams apart so none is dropped by the receive buffer.
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let events = wait_for(&recording, script.len()).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::SetMouse { x: 512.0, y: 512.0 },
+            RecordedEvent::MousePress(MouseButton::Left),
+            RecordedEvent::MouseRelease(MouseButton::Left),
+            RecordedEvent::MoveMouse { x: -5, y: 3 },
+            RecordedEvent::Scroll(scroll),
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::KeyRelease(Key::A),
+            RecordedEvent::Text("hello".to_string()),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_datagram_after_reliable_waits() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    // A drag whose motion overtakes the button press.
+    sender.send_datagram(1, Command::MoveMouse { x: 5, y: 0 })?;
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    sender
+        .send(Command::MouseButtonPressed(MouseButton::Left))
+        .await?;
+
+    let events = wait_for(&recording, 2).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::MousePress(MouseButton::Left),
+            RecordedEvent::MoveMouse { x: 5, y: 0 },
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_bad_datagram_dropped() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    // Bare message, then a frame damaged on the way.
+    let command = sender.stamp(0, Command::MoveMouse { x: 1, y: 1 });
+    let bare = lib_codec::encode(&command)?;
+    sender
+        .datagram
+        .send(&bare, 0, DatagramType::Command, Ssrc(1))?;
+    let mut damaged = frame::encode(&command, Flags::CHECKSUM)?;
+    damaged[frame::HEADER_LEN] ^= 1;
+    sender
+        .datagram
+        .send(&damaged, 0, DatagramType::Command, Ssrc(1))?;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    sender.send_datagram(0, Command::MoveMouse { x: 2, y: 2 })?;
+
+    wait_for(&recording, 1).await;
+    // Nothing else arrives late.
+    tokio::time::sleep(Duration::from_millis(20)).await;
+    let events = recording.events();
+    server.abort();
+
+    assert_eq!(events, [RecordedEvent::MoveMouse { x: 2, y: 2 }]);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_resent_on_new_stream() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    sender.send(Command::KeyPressed(Key::A)).await?;
+    wait_for(&recording, 1).await;
+
+    // Command 3 is resent on a new stream and overtakes command 2, still on
+    // the old one.
+    let late = sender.stamp(2, Command::KeyReleased(Key::A));
+    let ahead = sender.stamp(3, Command::KeyPressed(Key::B));
+    let mut resent = Encoder::new(connection.open_uni().await?);
+    resent.send(&ahead).await?;
+    tokio::time::sleep(Duration::from_millis(50)).await;
+    sender.stream.send(&late).await?;
+
+    let events = wait_for(&recording, 3).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::KeyRelease(Key::A),
+            RecordedEvent::KeyPress(Key::B),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    // A pointer frame with motion only goes as a datagram, one with a press
+    // on the stream.
+    let mut batch = CommandBatch::default();
+    batch.push(Command::MoveMouse { x: 1, y: 1 });
+    batch.push(Command::MoveMouse { x: 2, y: 2 });
+    sender.send(batch.take().ok_or("empty batch")?).await?;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    sender
+        .send(Command::Batch(vec![
+            Command::MouseButtonPressed(MouseButton::Left),
+            Command::MoveMouse { x: 4, y: 0 },
+            Command::MouseButtonReleased(MouseButton::Left),
+        ]))
+        .await?;
+
+    let events = wait_for(&recording, 4).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::MoveMouse { x: 3, y: 3 },
+            RecordedEvent::MousePress(MouseButton::Left),
+            RecordedEvent::MoveMouse { x: 4, y: 0 },
+            RecordedEvent::MouseRelease(MouseButton::Left),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_batch_whole_or_nothing() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    // Keys are never batched, nothing of the batch is applied.
+    sender
+        .send(Command::Batch(vec![
+            Command::MouseButtonPressed(MouseButton::Left),
+            Command::KeyPressed(Key::A),
+        ]))
+        .await?;
+    // The press is taken back when a later command fails, the rest is dropped.
+    sender
+        .send(Command::Batch(vec![
+            Command::MouseButtonPressed(MouseButton::Left),
+            Command::SetMouse {
+                display: 9,
+                x: 0,
+                y: 0,
+            },
+            Command::MoveMouse { x: 1, y: 1 },
+        ]))
+        .await?;
+    sender.send(Command::MoveMouse { x: 7, y: 0 }).await?;
+
+    let events = wait_for(&recording, 3).await;
+    let mut answers = connection.accept_uni().await?;
+    let unsupported = read_answer(&mut answers).await?;
+    let failed = read_answer(&mut answers).await?;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::MousePress(MouseButton::Left),
+            RecordedEvent::MouseRelease(MouseButton::Left),
+            RecordedEvent::MoveMouse { x: 7, y: 0 },
+        ]
+    );
+    assert!(matches!(
+        unsupported,
+        Some(Answer::CommandFailed {
+            command: CommandKind::Batch,
+            error: InputErrorKind::UnsupportedCommand,
+        })
+    ));
+    assert!(matches!(
+        failed,
+        Some(Answer::CommandFailed {
+            command: CommandKind::SetMouse,
+            error: InputErrorKind::UnknownDisplay(9),
+        })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_ping_and_metrics() -> Result<()> {
+    let recording = Recording::default();
+    let state = state(&recording, ClipboardBackend::Disabled);
+    let metrics = state.metrics.clone();
+    let (server, connection) = connect(state).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    sender.send(Command::Ping { id: 1, sent_at: 42 }).await?;
+    let mut answers = connection.accept_uni().await?;
+    let answer = read_answer(&mut answers).await?;
+
+    sender.send(Command::MoveMouse { x: 1, y: 0 }).await?;
+    tokio::time::sleep(Duration::from_millis(5)).await;
+    // Command 3 is lost on the way.
+    sender.id += 1;
+    sender.send(Command::MoveMouse { x: 2, y: 0 }).await?;
+    wait_for(&recording, 2).await;
+    server.abort();
+
+    assert!(matches!(
+        answer,
+        Some(Answer::Pong {
+            id: 1,
+            sent_at: 42,
+            received_at
+        }) if received_at > 42
+    ));
+    assert_eq!(metrics.lost(), 1);
+    assert_eq!(metrics.reordered(), 0);
+    assert_eq!(metrics.latency().len(), 3);
+    assert!(metrics.inject().p99().is_some());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_held_keys_released_on_disconnect() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    sender.send(Command::KeyPressed(Key::LeftShift)).await?;
+    sender
+        .send(Command::MouseButtonPressed(MouseButton::Left))
+        .await?;
+    wait_for(&recording, 2).await;
+    connection.close(0u32.into(), b"bye");
+
+    let events = wait_for(&recording, 4).await;
+    server.abort();
+
+    assert_eq!(
+        events[2..],
+        [
+            RecordedEvent::MouseRelease(MouseButton::Left),
+            RecordedEvent::KeyRelease(Key::LeftShift),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_held_keys_released_when_idle() -> Result<()> {
+    let recording = Recording::default();
+    let state = ServerState {
+        idle_release: Some(Duration::from_millis(50)),
+        ..state(&recording, ClipboardBackend::Disabled)
+    };
+    let (server, connection) = connect(state).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    sender.send(Command::KeyPressed(Key::LeftShift)).await?;
+
+    let events = wait_for(&recording, 2).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        [
+            RecordedEvent::KeyPress(Key::LeftShift),
+            RecordedEvent::KeyRelease(Key::LeftShift)
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_focus_lost_releases() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    sender.send(Command::KeyPressed(Key::LeftShift)).await?;
+    sender.send(Command::FocusLost).await?;
+
+    let events = wait_for(&recording, 2).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        [
+            RecordedEvent::KeyPress(Key::LeftShift),
+            RecordedEvent::KeyRelease(Key::LeftShift)
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_focus_and_modifiers() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection).await?;
+
+    sender
+        .send(Command::FocusEntered {
+            pressed: vec![Key::A],
+        })
+        .await?;
+    sender
+        .send(Command::Modifiers(ModifierState {
+            depressed: ModifierState::SHIFT,
+            ..Default::default()
+        }))
+        .await?;
+    sender.send(Command::FocusLost).await?;
+
+    let events = wait_for(&recording, 4).await;
+    server.abort();
+
+    assert_eq!(
+        events,
+        [
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::KeyPress(Key::LeftShift),
+            RecordedEvent::KeyRelease(Key::LeftShift),
+            RecordedEvent::KeyRelease(Key::A),
+        ]
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_keymap() -> Result<()> {
+    let recording = Recording::default();
+    let (server, connection) = connect(state(&recording, ClipboardBackend::Disabled)).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    sender
+        .send(Command::Keymap("not a keymap".to_string()))
+        .await?;
+    let keymap = r#"xkb_keymap {
+        xkb_keycodes { <AC01> = 38; };
+        xkb_symbols { key <AC01> { [ a, A ] }; };
+    };"#;
+    sender.send(Command::Keymap(keymap.to_string())).await?;
+    // The recording backend injects the physical key either way.
+    sender.send(Command::KeyPressed(Key::A)).await?;
+    sender.send(Command::KeyReleased(Key::A)).await?;
+
+    let events = wait_for(&recording, 2).await;
+    // The answers stream shows up with the first answer.
+    let mut answers = connection.accept_uni().await?;
+    let answer = read_answer(&mut answers).await?;
+    server.abort();
+
+    assert_eq!(
+        events,
+        [
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::KeyRelease(Key::A)
+        ]
+    );
+    assert!(matches!(
+        answer,
+        Some(Answer::CommandFailed {
+            command: CommandKind::Keymap,
+            ..
+        })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_displays() -> Result<()> {
+    let recording = Recording::default();
+    let fx_displays = vec![
+        DisplayParams::new(1920, 1080),
+        DisplayParams::new(3840, 2160)
+            .with_position(1920, 0)
+            .with_scale(2.0),
+    ];
+    let mut state = state(&recording, ClipboardBackend::Disabled);
+    state.displays = fx_displays.clone();
+    let (server, connection) = connect_raw(state).await?;
+
+    let reply = handshake(&connection, PROTOCOL_VERSION, fx_hello()).await?;
+    let Some(HandshakeReply::Accepted(hello)) = reply else {
+        return Err(format!("not accepted: {reply:?}").into());
+    };
+    assert_eq!(hello.displays, fx_displays);
+
+    let mut sender = Sender::new(connection.clone()).await?;
+    let center = NORMALIZED_ONE / 2;
+    for display in [1, 2] {
+        let command = Command::SetMouse {
+            display,
+            x: center,
+            y: center,
+        };
+        sender.send_datagram(0, command)?;
+        tokio::time::sleep(Duration::from_millis(5)).await;
+    }
+
+    let events = wait_for(&recording, 1).await;
+    let mut answers = connection.accept_uni().await?;
+    let answer = read_answer(&mut answers).await?;
+    server.abort();
+
+    // Center of the HiDPI screen in logical pixels.
+    assert_eq!(
+        events,
+        [RecordedEvent::SetMouse {
+            x: 2880.0,
+            y: 540.0
+        }]
+    );
+    assert!(matches!(
+        answer,
+        Some(Answer::CommandFailed {
+            command: CommandKind::SetMouse,
+            error: InputErrorKind::UnknownDisplay(2),
+        })
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handshake() -> Result<()> {
+    let (server, connection) =
+        connect_raw(state(&Recording::default(), ClipboardBackend::Disabled)).await?;
+
+    let reply = handshake(&connection, PROTOCOL_VERSION, fx_hello()).await?;
+    server.abort();
+
+    let Some(HandshakeReply::Accepted(hello)) = reply else {
+        return Err(format!("not accepted: {reply:?}").into());
+    };
+    assert_eq!(hello.name, SERVER_NAME);
+    // No clipboard on the server, the client must not send clipboard commands.
+    assert!(hello.supports(CommandKind::KeyPressed));
+    assert!(!hello.supports(CommandKind::ClipboardChunk));
+    assert!(hello.clipboard_formats.is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handshake_version_mismatch() -> Result<()> {
+    let (server, connection) =
+        connect_raw(state(&Recording::default(), ClipboardBackend::Disabled)).await?;
+
+    let reply = handshake(&connection, MIN_PROTOCOL_VERSION - 1, fx_hello()).await?;
+    connection.close(0u32.into(), b"rejected");
+    server.abort();
+
+    assert_eq!(
+        reply,
+        Some(HandshakeReply::Rejected(HandshakeError::VersionMismatch {
+            client: MIN_PROTOCOL_VERSION - 1,
+            server: PROTOCOL_VERSION,
+        }))
+    );
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_handshake_newer_client() -> Result<()> {
+    let (server, connection) =
+        connect_raw(state(&Recording::default(), ClipboardBackend::Disabled)).await?;
+
+    // Answered with our version, the client speaks it.
+    let reply = handshake(&connection, PROTOCOL_VERSION + 1, fx_hello()).await?;
+    server.abort();
+
+    assert!(matches!(reply, Some(HandshakeReply::Accepted(_))));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clipboard_both_ways() -> Result<()> {
+    let mut server_clipboard = MemoryClipboard::default();
+    let state = state(
+        &Recording::default(),
+        ClipboardBackend::Memory(server_clipboard.clone()),
+    );
+    let (server, connection) = connect(state).await?;
+
+    // Client -> server over the reliable stream, big enough to need chunks.
+    let image = ClipboardPayload::text("screenshot").with(IMAGE_PNG, vec![42u8; CHUNK_SIZE * 2]);
+    let mut sender = Sender::new(connection.clone()).await?;
+    sender
+        .send(Command::ClipboardFormats(vec![TEXT_PLAIN.to_string()]))
+        .await?;
+    for chunk in image.to_chunks(0, CHUNK_SIZE)? {
+        sender.send(Command::ClipboardChunk(chunk)).await?;
+    }
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while server_clipboard.contents() != image && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    assert_eq!(server_clipboard.contents(), image);
+
+    // Server -> client as answers, only in the format the client accepts.
+    server_clipboard
+        .write(&ClipboardPayload::text("from server").with(TEXT_HTML, "<i>from server</i>"))?;
+    let mut answers = connection.accept_uni().await?;
+    let mut assembler = ChunkAssembler::new(DEFAULT_MAX_SIZE);
+    let received = loop {
+        match read_answer(&mut answers).await? {
+            Some(Answer::ClipboardChunk(chunk)) => {
+                if let Some(payload) = assembler.push(chunk)? {
+                    break payload;
+                }
+            }
+            other => panic!("unexpected answer: {other:?}"),
+        }
+    };
+    server.abort();
+
+    assert_eq!(received, ClipboardPayload::text("from server"));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_clipboard_beside_input() -> Result<()> {
+    let recording = Recording::default();
+    let server_clipboard = MemoryClipboard::default();
+    let state = state(
+        &recording,
+        ClipboardBackend::Memory(server_clipboard.clone()),
+    );
+    let (server, connection) = connect(state).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    // Input goes through while a transfer is halfway.
+    let image = ClipboardPayload::default().with(IMAGE_PNG, vec![7u8; CHUNK_SIZE * 2]);
+    let mut chunks = image.to_chunks(0, CHUNK_SIZE)?.into_iter();
+    sender
+        .send(Command::ClipboardChunk(chunks.next().ok_or("no chunk")?))
+        .await?;
+    sender.send(Command::KeyPressed(Key::A)).await?;
+    sender.send(Command::MoveMouse { x: 3, y: 0 }).await?;
+    let events = wait_for(&recording, 2).await;
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::MoveMouse { x: 3, y: 0 },
+        ]
+    );
+
+    for chunk in chunks {
+        sender.send(Command::ClipboardChunk(chunk)).await?;
+    }
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while server_clipboard.contents() != image && Instant::now() < deadline {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+    server.abort();
+
+    assert_eq!(server_clipboard.contents(), image);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_untrusted_device() -> Result<()> {
+    let mut revoked = trusted_store();
+    revoked.revoke(&client_identity().fingerprint());
+
+    for store in [TrustStore::default(), revoked] {
+        let recording = Recording::default();
+        let state = ServerState {
+            devices: devices(store),
+            ..state(&recording, ClipboardBackend::Disabled)
+        };
+        let (server, connection) = connect_raw(state).await?;
+
+        // Closed before the hello is read.
+        let reason = tokio::time::timeout(Duration::from_secs(5), connection.closed()).await?;
+        server.abort();
+
+        match reason {
+            lib_quic::quinn::ConnectionError::ApplicationClosed(close) => {
+                assert_eq!(close.error_code, UNTRUSTED_DEVICE.into())
+            }
+            other => return Err(format!("unexpected close: {other}").into()),
+        }
+    }
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_policy() -> Result<()> {
+    let recording = Recording::default();
+    let state = ServerState {
+        policies: Policies::parse("loopback deny text\n* block Control+Alt+Delete")?.into(),
+        ..state(&recording, ClipboardBackend::Disabled)
+    };
+    let (server, connection) = connect_raw(state).await?;
+    match handshake(&connection, PROTOCOL_VERSION, fx_hello()).await? {
+        Some(HandshakeReply::Accepted(hello)) => {
+            assert!(!hello.commands.contains(&CommandKind::InputText))
+        }
+        other => return Err(format!("handshake failed: {other:?}").into()),
+    }
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    for command in [
+        Command::InputText("denied".to_string()),
+        Command::KeyPressed(Key::LeftControl),
+        Command::KeyPressed(Key::RightAlt),
+        Command::KeyPressed(Key::Delete),
+        Command::KeyReleased(Key::RightAlt),
+        Command::KeyPressed(Key::Delete),
+    ] {
+        sender.send(command).await?;
+    }
+
+    let events = wait_for(&recording, 4).await;
+    let mut answers = connection.accept_uni().await?;
+    let denied = [
+        read_answer(&mut answers).await?,
+        read_answer(&mut answers).await?,
+    ];
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::KeyPress(Key::LeftControl),
+            RecordedEvent::KeyPress(Key::RightAlt),
+            RecordedEvent::KeyRelease(Key::RightAlt),
+            RecordedEvent::KeyPress(Key::Delete),
+        ]
+    );
+    assert!(matches!(
+        denied,
+        [
+            Some(Answer::CommandDenied {
+                command: CommandKind::InputText,
+                reason: DenyReason::NotAllowed,
+            }),
+            Some(Answer::CommandDenied {
+                command: CommandKind::KeyPressed,
+                reason: DenyReason::BlockedKeys,
+            }),
+        ]
+    ));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_policy_remapped_keys() -> Result<()> {
+    let recording = Recording::default();
+    let state = ServerState {
+        policies: Policies::parse("* block Control+Alt+Delete\n* block Super")?.into(),
+        ..state(&recording, ClipboardBackend::Disabled)
+    };
+    let (server, connection) = connect(state).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    // A and S type Delete and Super through the keymap of the client.
+    let keymap = r#"xkb_keymap {
+        xkb_keycodes { <AC01> = 38; <AC02> = 39; };
+        xkb_symbols {
+            key <AC01> { [ Delete ] };
+            key <AC02> { [ Super_L ] };
+        };
+    };"#;
+    for command in [
+        Command::Keymap(keymap.to_string()),
+        Command::KeyPressed(Key::LeftControl),
+        Command::KeyPressed(Key::RightAlt),
+        Command::KeyPressed(Key::A),
+        Command::KeyPressed(Key::S),
+        Command::KeyPressed(Key::B),
+    ] {
+        sender.send(command).await?;
+    }
+
+    let events = wait_for(&recording, 3).await;
+    let mut answers = connection.accept_uni().await?;
+    let denied = [
+        read_answer(&mut answers).await?,
+        read_answer(&mut answers).await?,
+    ];
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::KeyPress(Key::LeftControl),
+            RecordedEvent::KeyPress(Key::RightAlt),
+            RecordedEvent::KeyPress(Key::B),
+        ]
+    );
+    assert!(denied.iter().all(|answer| matches!(
+        answer,
+        Some(Answer::CommandDenied {
+            command: CommandKind::KeyPressed,
+            reason: DenyReason::BlockedKeys,
+        })
+    )));
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_policy_reload() -> Result<()> {
+    let recording = Recording::default();
+    let state = state(&recording, ClipboardBackend::Disabled);
+    let policies = state.policies.clone();
+    let (server, connection) = connect(state).await?;
+    let mut sender = Sender::new(connection.clone()).await?;
+
+    sender.send(Command::KeyPressed(Key::A)).await?;
+    wait_for(&recording, 1).await;
+
+    // Applies to the connected client, the held key can still be released.
+    policies.replace(Policies::parse("loopback deny keyboard")?);
+    tokio::time::sleep(Duration::from_millis(100)).await;
+    sender.send(Command::KeyPressed(Key::B)).await?;
+    sender.send(Command::KeyReleased(Key::A)).await?;
+
+    let events = wait_for(&recording, 2).await;
+    let mut answers = connection.accept_uni().await?;
+    let denied = read_answer(&mut answers).await?;
+    server.abort();
+
+    assert_eq!(
+        events,
+        vec![
+            RecordedEvent::KeyPress(Key::A),
+            RecordedEvent::KeyRelease(Key::A),
+        ]
+    );
+    assert!(matches!(
+        denied,
+        Some(Answer::CommandDenied {
+            command: CommandKind::KeyPressed,
+            reason: DenyReason::NotAllowed,
+        })
+    ));
+
+    Ok(())
+}
@@ -2,8 +2,34 @@ pub type Result<T> = core::result::Result<T, Error>;
 
 #[derive(Debug)]
 pub enum Error {
-    Encode,
-    Decode,
+    Encode(bincode::error::EncodeError),
+    Decode(bincode::error::DecodeError),
+
+    // -- Frame
+    BadMagic([u8; 2]),
+    VersionMismatch {
+        expected: u8,
+        found: u8,
+    },
+    UnknownType {
+        expected: u8,
+        found: u8,
+    },
+    UnknownFlags(u8),
+    FrameTooLarge(usize),
+    Truncated {
+        needed: usize,
+        available: usize,
+    },
+    ChecksumMismatch {
+        expected: u32,
+        found: u32,
+    },
+    /// The payload has bytes left after the message.
+    TrailingBytes(usize),
+
+    // -- Stream
+    Io(std::io::Error),
 }
 
 // region:    --- Error Boilerplate
@@ -0,0 +1,478 @@
+//! Self-describing frames around encoded messages.
+//!
+//! A frame is a fixed header followed by the payload encoded with
+//! [`crate::encode`] and, when [`Flags::CHECKSUM`] is set, the CRC-32 of the
+//! payload:
+//!
+//! | bytes | field                            |
+//! |-------|----------------------------------|
+//! | 2     | magic, `b"AL"`                   |
+//! | 1     | frame format version             |
+//! | 1     | message type, [`Message::TYPE`]  |
+//! | 1     | flags                            |
+//! | 4     | payload length, little-endian    |
+//! | len   | payload                          |
+//! | 4     | CRC-32 of the payload, optional  |
+
+use crate::{Error, Result};
+use bincode::{Decode, Encode};
+
+/// First bytes of every frame.
+pub const MAGIC: [u8; 2] = *b"AL";
+
+/// Version of the frame layout, bumped on any incompatible change.
+pub const VERSION: u8 = 1;
+
+pub const HEADER_LEN: usize = 9;
+
+pub const CHECKSUM_LEN: usize = 4;
+
+/// Largest payload accepted by [`decode`] and [`crate::stream::Decoder`].
+pub const MAX_PAYLOAD_LEN: usize = 16 * 1024 * 1024;
+
+/// A value sent in a frame of its own.
+pub trait Message: Encode + Decode<()> {
+    /// Identifies the message in the frame header, unique among the messages
+    /// of a protocol.
+    const TYPE: u8;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Flags(u8);
+
+impl Flags {
+    /// The payload is followed by its CRC-32.
+    pub const CHECKSUM: Self = Self(1);
+
+    const KNOWN: u8 = Self::CHECKSUM.0;
+
+    pub fn from_bits(bits: u8) -> Result<Self> {
+        match bits & !Self::KNOWN {
+            0 => Ok(Self(bits)),
+            unknown => Err(Error::UnknownFlags(unknown)),
+        }
+    }
+
+    pub fn bits(self) -> u8 {
+        self.0
+    }
+
+    pub fn contains(self, other: Self) -> bool {
+        self.0 & other.0 == other.0
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Header {
+    pub message_type: u8,
+    pub flags: Flags,
+    pub len: u32,
+}
+
+impl Header {
+    pub fn to_bytes(&self) -> [u8; HEADER_LEN] {
+        let mut bytes = [0u8; HEADER_LEN];
+        bytes[..2].copy_from_slice(&MAGIC);
+        bytes[2] = VERSION;
+        bytes[3] = self.message_type;
+        bytes[4] = self.flags.bits();
+        bytes[5..].copy_from_slice(&self.len.to_le_bytes());
+        bytes
+    }
+
+    /// Reads and checks a header, the payload length against `max_len`.
+    pub fn from_bytes(bytes: &[u8; HEADER_LEN], max_len: usize) -> Result<Self> {
+        if bytes[..2] != MAGIC {
+            return Err(Error::BadMagic([bytes[0], bytes[1]]));
+        }
+        if bytes[2] != VERSION {
+            return Err(Error::VersionMismatch {
+                expected: VERSION,
+                found: bytes[2],
+            });
+        }
+        let flags = Flags::from_bits(bytes[4])?;
+        let len = u32::from_le_bytes([bytes[5], bytes[6], bytes[7], bytes[8]]);
+        if len as usize > max_len {
+            return Err(Error::FrameTooLarge(len as usize));
+        }
+
+        Ok(Self {
+            message_type: bytes[3],
+            flags,
+            len,
+        })
+    }
+
+    /// Bytes after the header: payload and checksum.
+    pub fn body_len(&self) -> usize {
+        match self.flags.contains(Flags::CHECKSUM) {
+            true => self.len as usize + CHECKSUM_LEN,
+            false => self.len as usize,
+        }
+    }
+}
+
+/// A frame read from the wire, its message not decoded yet.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Frame {
+    pub header: Header,
+    pub payload: Vec<u8>,
+}
+
+impl Frame {
+    /// Checks the body read after `header` and keeps the payload.
+    pub fn from_body(header: Header, mut body: Vec<u8>) -> Result<Self> {
+        let needed = header.body_len();
+        if body.len() < needed {
+            return Err(Error::Truncated {
+                needed,
+                available: body.len(),
+            });
+        }
+        if body.len() > needed {
+            return Err(Error::TrailingBytes(body.len() - needed));
+        }
+
+        if header.flags.contains(Flags::CHECKSUM) {
+            let split = body.len() - CHECKSUM_LEN;
+            let expected = u32::from_le_bytes([
+                body[split],
+                body[split + 1],
+                body[split + 2],
+                body[split + 3],
+            ]);
+            body.truncate(split);
+
+            let found = crc32(&body);
+            if found != expected {
+                return Err(Error::ChecksumMismatch { expected, found });
+            }
+        }
+
+        Ok(Self {
+            header,
+            payload: body,
+        })
+    }
+
+    pub fn message_type(&self) -> u8 {
+        self.header.message_type
+    }
+
+    /// Decodes the payload as `M`, the whole payload must be used.
+    pub fn decode<M: Message>(&self) -> Result<M> {
+        if self.header.message_type != M::TYPE {
+            return Err(Error::UnknownType {
+                expected: M::TYPE,
+                found: self.header.message_type,
+            });
+        }
+
+        let (message, used) =
+            bincode::decode_from_slice(&self.payload, bincode::config::standard())
+                .map_err(Error::Decode)?;
+        if used != self.payload.len() {
+            return Err(Error::TrailingBytes(self.payload.len() - used));
+        }
+
+        Ok(message)
+    }
+}
+
+/// Encodes `message` in a frame.
+pub fn encode<M: Message>(message: &M, flags: Flags) -> Result<Vec<u8>> {
+    let mut frame = vec![0u8; HEADER_LEN];
+    bincode::encode_into_std_write(message, &mut frame, bincode::config::standard())
+        .map_err(Error::Encode)?;
+
+    let len = frame.len() - HEADER_LEN;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(Error::FrameTooLarge(len));
+    }
+    let header = Header {
+        message_type: M::TYPE,
+        flags,
+        len: len as u32,
+    };
+    frame[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+
+    if flags.contains(Flags::CHECKSUM) {
+        let checksum = crc32(&frame[HEADER_LEN..]);
+        frame.extend_from_slice(&checksum.to_le_bytes());
+    }
+
+    Ok(frame)
+}
+
+/// Encodes `message` in a frame written at the start of `buf`, returns the
+/// frame size. Fails when it doesn't fit.
+pub fn encode_to_slice<M: Message>(message: &M, flags: Flags, buf: &mut [u8]) -> Result<usize> {
+    let available = buf.len();
+    let payload = buf.get_mut(HEADER_LEN..).ok_or(Error::Truncated {
+        needed: HEADER_LEN,
+        available,
+    })?;
+    let len = crate::encode_to_stack(message, payload)?;
+    if len > MAX_PAYLOAD_LEN {
+        return Err(Error::FrameTooLarge(len));
+    }
+    let header = Header {
+        message_type: M::TYPE,
+        flags,
+        len: len as u32,
+    };
+    buf[..HEADER_LEN].copy_from_slice(&header.to_bytes());
+
+    let size = HEADER_LEN + header.body_len();
+    if flags.contains(Flags::CHECKSUM) {
+        let checksum = crc32(&buf[HEADER_LEN..HEADER_LEN + len]);
+        buf.get_mut(HEADER_LEN + len..size)
+            .ok_or(Error::Truncated {
+                needed: size,
+                available,
+            })?
+            .copy_from_slice(&checksum.to_le_bytes());
+    }
+
+    Ok(size)
+}
+
+/// Decodes the message of a frame filling `bytes`, e.g. a datagram.
+pub fn decode_message<M: Message>(bytes: &[u8]) -> Result<M> {
+    let (frame, size) = decode(bytes)?;
+    if size != bytes.len() {
+        return Err(Error::TrailingBytes(bytes.len() - size));
+    }
+
+    frame.decode()
+}
+
+/// Reads the frame at the start of `bytes`, returns it with its size.
+pub fn decode(bytes: &[u8]) -> Result<(Frame, usize)> {
+    let header = bytes.first_chunk::<HEADER_LEN>().ok_or(Error::Truncated {
+        needed: HEADER_LEN,
+        available: bytes.len(),
+    })?;
+    let header = Header::from_bytes(header, MAX_PAYLOAD_LEN)?;
+
+    let size = HEADER_LEN + header.body_len();
+    let body = bytes.get(HEADER_LEN..size).ok_or(Error::Truncated {
+        needed: size,
+        available: bytes.len(),
+    })?;
+
+    Ok((Frame::from_body(header, body.to_vec())?, size))
+}
+
+/// CRC-32 (IEEE) as used by zip and ethernet.
+pub fn crc32(bytes: &[u8]) -> u32 {
+    let crc = bytes.iter().fold(!0u32, |crc, byte| {
+        CRC_TABLE[((crc ^ *byte as u32) & 0xff) as usize] ^ (crc >> 8)
+    });
+    !crc
+}
+
+const CRC_TABLE: [u32; 256] = {
+    let mut table = [0u32; 256];
+    let mut i = 0;
+    while i < 256 {
+        let mut crc = i as u32;
+        let mut bit = 0;
+        while bit < 8 {
+            crc = match crc & 1 {
+                1 => 0xedb8_8320 ^ (crc >> 1),
+                _ => crc >> 1,
+            };
+            bit += 1;
+        }
+        table[i] = crc;
+        i += 1;
+    }
+    table
+};
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    /// `Ping { id: 1, name: "name" }` without checksum.
+    const FX_FRAME: [u8; 15] = [65, 76, 1, 7, 0, 6, 0, 0, 0, 1, 4, 110, 97, 109, 101];
+
+    /// The same message with its checksum.
+    const FX_FRAME_CHECKSUM: [u8; 19] = [
+        65, 76, 1, 7, 1, 6, 0, 0, 0, 1, 4, 110, 97, 109, 101, 220, 117, 121, 240,
+    ];
+
+    #[derive(Debug, Encode, Decode, PartialEq)]
+    struct Ping {
+        id: u32,
+        name: String,
+    }
+
+    impl Message for Ping {
+        const TYPE: u8 = 7;
+    }
+
+    #[derive(Debug, Encode, Decode, PartialEq)]
+    struct Pong;
+
+    impl Message for Pong {
+        const TYPE: u8 = 8;
+    }
+
+    fn fx_ping() -> Ping {
+        Ping {
+            id: 1,
+            name: "name".to_string(),
+        }
+    }
+
+    #[test]
+    fn test_encode() -> Result<()> {
+        assert_eq!(encode(&fx_ping(), Flags::default())?, FX_FRAME);
+        assert_eq!(encode(&fx_ping(), Flags::CHECKSUM)?, FX_FRAME_CHECKSUM);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_decode() -> Result<()> {
+        for fx_frame in [&FX_FRAME[..], &FX_FRAME_CHECKSUM[..]] {
+            let (frame, size) = decode(fx_frame)?;
+            assert_eq!(size, fx_frame.len());
+            assert_eq!(frame.message_type(), Ping::TYPE);
+            assert_eq!(frame.decode::<Ping>()?, fx_ping());
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_crc32() -> Result<()> {
+        assert_eq!(crc32(b"123456789"), 0xcbf4_3926);
+        assert_eq!(crc32(&[]), 0);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() -> Result<()> {
+        assert!(matches!(
+            decode(&FX_FRAME[..4]),
+            Err(Error::Truncated {
+                needed: HEADER_LEN,
+                available: 4
+            })
+        ));
+        assert!(matches!(
+            decode(&FX_FRAME[..12]),
+            Err(Error::Truncated {
+                needed: 15,
+                available: 12
+            })
+        ));
+
+        let mut frame = FX_FRAME;
+        frame[0] = b'X';
+        assert!(matches!(decode(&frame), Err(Error::BadMagic([b'X', b'L']))));
+
+        let mut frame = FX_FRAME;
+        frame[2] = 2;
+        assert!(matches!(
+            decode(&frame),
+            Err(Error::VersionMismatch {
+                expected: VERSION,
+                found: 2
+            })
+        ));
+
+        let mut frame = FX_FRAME;
+        frame[4] = 0x80;
+        assert!(matches!(decode(&frame), Err(Error::UnknownFlags(0x80))));
+
+        let mut frame = FX_FRAME;
+        frame[5..9].copy_from_slice(&(MAX_PAYLOAD_LEN as u32 + 1).to_le_bytes());
+        assert!(matches!(decode(&frame), Err(Error::FrameTooLarge(_))));
+
+        let mut frame = FX_FRAME_CHECKSUM;
+        frame[10] ^= 1;
+        assert!(matches!(
+            decode(&frame),
+            Err(Error::ChecksumMismatch { .. })
+        ));
+
+        let (frame, _) = decode(&FX_FRAME)?;
+        assert!(matches!(
+            frame.decode::<Pong>(),
+            Err(Error::UnknownType {
+                expected: Pong::TYPE,
+                found: Ping::TYPE
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_to_slice() -> Result<()> {
+        let mut buf = [0u8; 64];
+        let size = encode_to_slice(&fx_ping(), Flags::CHECKSUM, &mut buf)?;
+        assert_eq!(buf[..size], FX_FRAME_CHECKSUM);
+        assert_eq!(decode_message::<Ping>(&buf[..size])?, fx_ping());
+
+        // No room for the payload, or for the checksum after it.
+        assert!(encode_to_slice(&fx_ping(), Flags::default(), &mut buf[..12]).is_err());
+        assert!(encode_to_slice(&fx_ping(), Flags::CHECKSUM, &mut buf[..16]).is_err());
+        assert!(matches!(
+            decode_message::<Ping>(&buf[..size + 1]),
+            Err(Error::TrailingBytes(1))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_short_body() -> Result<()> {
+        let header = Header {
+            message_type: Ping::TYPE,
+            flags: Flags::CHECKSUM,
+            len: 0,
+        };
+
+        assert!(matches!(
+            Frame::from_body(header, vec![1, 2]),
+            Err(Error::Truncated {
+                needed: CHECKSUM_LEN,
+                available: 2
+            })
+        ));
+        assert!(matches!(
+            Frame::from_body(header, vec![0; 6]),
+            Err(Error::TrailingBytes(2))
+        ));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_trailing_bytes() -> Result<()> {
+        let mut frame = encode(&Pong, Flags::default())?;
+        frame.push(0);
+        frame[5] += 1;
+
+        let (frame, _) = decode(&frame)?;
+        assert!(matches!(
+            frame.decode::<Pong>(),
+            Err(Error::TrailingBytes(1))
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
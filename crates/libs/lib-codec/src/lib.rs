@@ -1,27 +1,30 @@
 // region:    --- Modules
 
 mod error;
+pub mod frame;
+pub mod stream;
 
 use bincode::Decode;
 use bincode::Encode;
 pub use error::{Error, Result};
+pub use frame::Message;
 
 // endregion: --- Modules
 
 pub fn encode<D: Encode>(data: &D) -> Result<Vec<u8>> {
-    bincode::encode_to_vec(data, bincode::config::standard()).map_err(|_| Error::Encode)
+    bincode::encode_to_vec(data, bincode::config::standard()).map_err(Error::Encode)
 }
 
 pub fn encode_to_stack<D: Encode>(data: &D, buf: &mut [u8]) -> Result<usize> {
     let mut writer = std::io::Cursor::new(buf);
     bincode::encode_into_std_write(data, &mut writer, bincode::config::standard())
-        .map_err(|_| Error::Encode)?;
+        .map_err(Error::Encode)?;
     Ok(writer.position() as usize)
 }
 
 pub fn decode<D: Decode<()>>(data: &[u8]) -> Result<D> {
     let (decoded, _) =
-        bincode::decode_from_slice(data, bincode::config::standard()).map_err(|_| Error::Decode)?;
+        bincode::decode_from_slice(data, bincode::config::standard()).map_err(Error::Decode)?;
 
     Ok(decoded)
 }
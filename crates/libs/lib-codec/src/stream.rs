@@ -0,0 +1,194 @@
+//! Frames on reliable byte streams (QUIC streams, TCP...), see [`crate::frame`].
+
+use crate::{
+    frame::{self, Flags, Frame, Header, Message, HEADER_LEN, MAX_PAYLOAD_LEN},
+    Error, Result,
+};
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+/// Writes messages to a stream, one frame each.
+pub struct Encoder<W> {
+    writer: W,
+    flags: Flags,
+}
+
+impl<W: AsyncWrite + Unpin> Encoder<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            flags: Flags::default(),
+        }
+    }
+
+    /// Adds a checksum to every frame, for transports that don't have one.
+    pub fn with_checksum(mut self) -> Self {
+        self.flags = Flags::CHECKSUM;
+        self
+    }
+
+    pub async fn send<M: Message>(&mut self, message: &M) -> Result<()> {
+        let frame = frame::encode(message, self.flags)?;
+        self.writer.write_all(&frame).await.map_err(Error::Io)
+    }
+
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.writer
+    }
+
+    pub fn into_inner(self) -> W {
+        self.writer
+    }
+}
+
+/// Reads the frames of a stream.
+pub struct Decoder<R> {
+    reader: R,
+    max_len: usize,
+}
+
+impl<R: AsyncRead + Unpin> Decoder<R> {
+    pub fn new(reader: R) -> Self {
+        Self {
+            reader,
+            max_len: MAX_PAYLOAD_LEN,
+        }
+    }
+
+    /// Largest payload accepted, at most [`MAX_PAYLOAD_LEN`].
+    pub fn with_max_len(mut self, max_len: usize) -> Self {
+        self.max_len = max_len.min(MAX_PAYLOAD_LEN);
+        self
+    }
+
+    /// Reads the next message, `None` when the stream ended cleanly between
+    /// frames.
+    pub async fn recv<M: Message>(&mut self) -> Result<Option<M>> {
+        match self.next_frame().await? {
+            Some(frame) => frame.decode().map(Some),
+            None => Ok(None),
+        }
+    }
+
+    /// Reads the next frame without decoding it, for streams carrying several
+    /// message types.
+    pub async fn next_frame(&mut self) -> Result<Option<Frame>> {
+        let mut header = [0u8; HEADER_LEN];
+        match read_full(&mut self.reader, &mut header).await? {
+            0 => return Ok(None),
+            HEADER_LEN => {}
+            available => {
+                return Err(Error::Truncated {
+                    needed: HEADER_LEN,
+                    available,
+                })
+            }
+        }
+        let header = Header::from_bytes(&header, self.max_len)?;
+
+        let mut body = vec![0u8; header.body_len()];
+        let available = read_full(&mut self.reader, &mut body).await?;
+        if available != body.len() {
+            return Err(Error::Truncated {
+                needed: HEADER_LEN + body.len(),
+                available: HEADER_LEN + available,
+            });
+        }
+
+        Frame::from_body(header, body).map(Some)
+    }
+
+    pub fn get_mut(&mut self) -> &mut R {
+        &mut self.reader
+    }
+
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+/// Fills `buf` unless the stream ends first, returns the bytes read.
+async fn read_full<R: AsyncRead + Unpin>(reader: &mut R, buf: &mut [u8]) -> Result<usize> {
+    let mut read = 0;
+    while read < buf.len() {
+        match reader.read(&mut buf[read..]).await.map_err(Error::Io)? {
+            0 => break,
+            n => read += n,
+        }
+    }
+
+    Ok(read)
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use bincode::{Decode, Encode};
+
+    #[derive(Debug, Encode, Decode, PartialEq)]
+    struct Note(u32, String);
+
+    impl Message for Note {
+        const TYPE: u8 = 1;
+    }
+
+    #[tokio::test]
+    async fn test_round_trip() -> Result<()> {
+        let mut encoder = Encoder::new(Vec::new()).with_checksum();
+        encoder.send(&Note(1, "one".to_string())).await?;
+        encoder.send(&Note(2, "two".to_string())).await?;
+        let buf = encoder.into_inner();
+
+        let mut decoder = Decoder::new(buf.as_slice());
+        let first: Option<Note> = decoder.recv().await?;
+        let second: Option<Note> = decoder.recv().await?;
+        let end: Option<Note> = decoder.recv().await?;
+
+        assert_eq!(first, Some(Note(1, "one".to_string())));
+        assert_eq!(second, Some(Note(2, "two".to_string())));
+        assert_eq!(end, None);
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_truncated() -> Result<()> {
+        let buf = frame::encode(&Note(1, "one".to_string()), Flags::default())?;
+
+        let result = Decoder::new(&buf[..buf.len() - 1]).recv::<Note>().await;
+        assert!(matches!(
+            result,
+            Err(Error::Truncated { needed, available }) if needed == buf.len() && available == buf.len() - 1
+        ));
+
+        let result = Decoder::new(&buf[..3]).recv::<Note>().await;
+        assert!(matches!(
+            result,
+            Err(Error::Truncated {
+                needed: HEADER_LEN,
+                available: 3
+            })
+        ));
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_oversized_frame() -> Result<()> {
+        let buf = frame::encode(&Note(1, "one".to_string()), Flags::default())?;
+
+        let result = Decoder::new(buf.as_slice())
+            .with_max_len(2)
+            .recv::<Note>()
+            .await;
+
+        assert!(matches!(result, Err(Error::FrameTooLarge(_))));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
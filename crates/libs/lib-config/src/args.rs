@@ -0,0 +1,24 @@
+use crate::{Result, Settings};
+use clap::Args;
+use std::path::PathBuf;
+
+/// Command line flags choosing the settings, shared by the apps.
+#[derive(Debug, Clone, Default, Args)]
+pub struct ConfigArgs {
+    /// Config file, `<app>.toml` in the XDG config dirs by default.
+    #[arg(long, global = true, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Profile of the config file.
+    #[arg(long, short, global = true)]
+    pub profile: Option<String>,
+    /// Any setting, over every other layer. Repeatable.
+    #[arg(long = "set", global = true, value_name = "KEY=VALUE")]
+    pub overrides: Vec<String>,
+}
+
+impl ConfigArgs {
+    pub fn settings(&self, app: &str) -> Result<Settings> {
+        Settings::load(app, self.config.as_deref(), self.profile.as_deref())?
+            .with_overrides(&self.overrides)
+    }
+}
@@ -0,0 +1,33 @@
+use derive_more::derive::From;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, From)]
+pub enum Error {
+    /// No `[profiles.<name>]` of this name in the config file.
+    ProfileUnknown(String),
+    /// A command line override without `=`.
+    OverrideWrongFormat(String),
+    ValueWrongFormat {
+        key: String,
+        value: String,
+    },
+    /// Tables other than `[profiles.<name>]` have no meaning.
+    TableUnexpected(String),
+
+    // -- Externals
+    #[from]
+    Toml(toml::de::Error),
+    #[from]
+    Io(std::io::Error),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
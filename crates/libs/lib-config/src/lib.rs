@@ -0,0 +1,35 @@
+//! Layered settings of the apps. A value comes from the first layer having
+//! it: command line overrides, environment variables, the selected profile of
+//! the config file, then the top of the file.
+//!
+//! ```toml
+//! # ~/.config/air-link/air_client.toml
+//! profile = "laptop"                   # without --profile
+//! server_ca = "/home/me/certs/ca.pem"
+//!
+//! [profiles.laptop]
+//! address = "192.168.0.10:54321"
+//! resolution = "2560x1600"
+//!
+//! [profiles.office-desktop]
+//! address = "10.0.0.2:54321"
+//! pointer_lock_hotkey = "LeftControl+LeftAlt+L"
+//! ```
+//!
+//! Keys are the names of the environment variables in lower case, lists
+//! are joined with commas. The apps watch the file and reload it when it
+//! changes, reporting the settings that only apply after a restart.
+
+// region:    --- Modules
+
+mod args;
+mod error;
+mod reload;
+mod settings;
+
+pub use args::ConfigArgs;
+pub use error::{Error, Result};
+pub use reload::{report, Apply, FileWatch};
+pub use settings::{find_file, Settings};
+
+// endregion: --- Modules
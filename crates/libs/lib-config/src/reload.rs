@@ -0,0 +1,98 @@
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    time::SystemTime,
+};
+use tracing::{info, warn};
+
+/// When a changed setting takes effect in a running app.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Apply {
+    Now,
+    /// Once the client connects again.
+    NextConnection,
+    Restart,
+}
+
+/// Tells when files change, by their modification time.
+#[derive(Debug, Default)]
+pub struct FileWatch {
+    files: Vec<(PathBuf, Option<SystemTime>)>,
+}
+
+impl FileWatch {
+    pub fn new<'a>(paths: impl IntoIterator<Item = &'a Path>) -> Self {
+        Self {
+            files: paths
+                .into_iter()
+                .map(|path| (path.to_path_buf(), modified(path)))
+                .collect(),
+        }
+    }
+
+    /// Whether a file changed, appeared or went away since the last call.
+    pub fn changed(&mut self) -> bool {
+        let mut changed = false;
+        for (path, last) in &mut self.files {
+            let now = modified(path);
+            if now != *last {
+                *last = now;
+                changed = true;
+            }
+        }
+
+        changed
+    }
+}
+
+/// Logs what became of each changed setting.
+pub fn report(changes: &[(&str, Apply)]) {
+    for (key, apply) in changes {
+        match apply {
+            Apply::Now => info!("{key} reloaded"),
+            Apply::NextConnection => info!("{key} reloaded, applies from the next connection"),
+            Apply::Restart => warn!("{key} changed, restart to apply it"),
+        }
+    }
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|meta| meta.modified()).ok()
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_file_watch() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("lib-config-watch-{}", std::process::id()));
+        let path = dir.join("air_server.toml");
+        fs::create_dir_all(&dir)?;
+
+        let mut watch = FileWatch::new([path.as_path()]);
+        assert!(!watch.changed());
+
+        fs::write(&path, "width = 1920")?;
+        assert!(watch.changed());
+        assert!(!watch.changed());
+
+        let file = fs::File::options().write(true).open(&path)?;
+        file.set_modified(SystemTime::now() + Duration::from_secs(1))?;
+        assert!(watch.changed());
+
+        fs::remove_file(&path)?;
+        assert!(watch.changed());
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,322 @@
+use crate::{Error, Result};
+use std::{
+    collections::HashMap,
+    env, fs,
+    path::{Path, PathBuf},
+    str::FromStr,
+};
+use toml::{Table, Value};
+use tracing::warn;
+
+/// Directory of the config files in each XDG config dir.
+const DIR: &str = "air-link";
+
+#[derive(Debug, Clone, Default)]
+pub struct Settings {
+    /// Config file the values were read from.
+    path: Option<PathBuf>,
+    /// Profile asked for, the file names one otherwise.
+    requested: Option<String>,
+    profile: Option<String>,
+    /// Values of the file, the ones of the profile over the top ones.
+    file: HashMap<String, String>,
+    overrides: HashMap<String, String>,
+}
+
+impl Settings {
+    /// Settings of the environment alone.
+    pub fn from_env() -> Self {
+        Self::default()
+    }
+
+    /// Reads `path`, or `<app>.toml` in the XDG config dirs when there is
+    /// one, with the values of `profile` or of the profile the file names.
+    pub fn load(app: &str, path: Option<&Path>, profile: Option<&str>) -> Result<Self> {
+        let Some(path) = path.map(Path::to_path_buf).or_else(|| find_file(app)) else {
+            return match profile {
+                Some(profile) => Err(Error::ProfileUnknown(profile.to_string())),
+                None => Ok(Self::from_env()),
+            };
+        };
+
+        let settings = Self::parse(&fs::read_to_string(&path)?, profile)?;
+        Ok(Self {
+            path: Some(path),
+            requested: profile.map(str::to_string),
+            ..settings
+        })
+    }
+
+    /// Reads the config file again, with the same profile and overrides.
+    pub fn reload(&self) -> Result<Self> {
+        let Some(path) = &self.path else {
+            return Ok(self.clone());
+        };
+
+        let settings = Self::parse(&fs::read_to_string(path)?, self.requested.as_deref())?;
+        Ok(Self {
+            path: self.path.clone(),
+            requested: self.requested.clone(),
+            overrides: self.overrides.clone(),
+            ..settings
+        })
+    }
+
+    /// Settings of the text of a config file.
+    pub fn parse(text: &str, profile: Option<&str>) -> Result<Self> {
+        let mut table: Table = text.parse()?;
+        let mut profiles = match table.remove("profiles") {
+            Some(Value::Table(profiles)) => profiles,
+            Some(_) => return Err(Error::TableUnexpected("profiles".to_string())),
+            None => Table::new(),
+        };
+        let profile = match (profile, table.remove("profile")) {
+            (Some(profile), _) => Some(profile.to_string()),
+            (None, Some(Value::String(profile))) => Some(profile),
+            (None, Some(value)) => return Err(wrong_format("profile", &value)),
+            (None, None) => None,
+        };
+
+        let mut file = values(table)?;
+        if let Some(profile) = &profile {
+            match profiles.remove(profile) {
+                Some(Value::Table(values_of_profile)) => file.extend(values(values_of_profile)?),
+                _ => return Err(Error::ProfileUnknown(profile.clone())),
+            }
+        }
+
+        Ok(Self {
+            path: None,
+            requested: None,
+            profile,
+            file,
+            overrides: HashMap::new(),
+        })
+    }
+
+    pub fn with_override(mut self, key: &str, value: impl Into<String>) -> Self {
+        self.overrides.insert(normalize(key), value.into());
+        self
+    }
+
+    /// Overrides written as `KEY=VALUE`.
+    pub fn with_overrides(mut self, pairs: &[String]) -> Result<Self> {
+        for pair in pairs {
+            let (key, value) = pair
+                .split_once('=')
+                .ok_or_else(|| Error::OverrideWrongFormat(pair.clone()))?;
+            self = self.with_override(key.trim(), value.trim());
+        }
+
+        Ok(self)
+    }
+
+    pub fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
+    }
+
+    pub fn profile(&self) -> Option<&str> {
+        self.profile.as_deref()
+    }
+
+    /// Value of `key`, the name of its environment variable.
+    pub fn get(&self, key: &str) -> Option<String> {
+        if let Some(value) = self.overrides.get(key) {
+            return Some(value.clone());
+        }
+        if let Ok(value) = env::var(key) {
+            if let Some(shadowed) = self.file.get(key).filter(|file| **file != value) {
+                warn!(
+                    "{key}={value} of the environment is used over {shadowed} of the config file"
+                );
+            }
+            return Some(value);
+        }
+
+        self.file.get(key).cloned()
+    }
+
+    /// Parsed value of `key`, an error when it is there but doesn't parse.
+    pub fn get_parse<T: FromStr>(&self, key: &str) -> Result<Option<T>> {
+        self.get(key)
+            .map(|value| {
+                value.trim().parse().map_err(|_| Error::ValueWrongFormat {
+                    key: key.to_string(),
+                    value,
+                })
+            })
+            .transpose()
+    }
+}
+
+/// `<app>.toml` in the first XDG config dir having it.
+pub fn find_file(app: &str) -> Option<PathBuf> {
+    let home = env::var_os("XDG_CONFIG_HOME")
+        .map(PathBuf::from)
+        .filter(|dir| dir.is_absolute())
+        .or_else(|| env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")));
+    let dirs = env::var("XDG_CONFIG_DIRS").unwrap_or("/etc/xdg".to_string());
+
+    home.into_iter()
+        .chain(
+            dirs.split(':')
+                .filter(|dir| !dir.is_empty())
+                .map(PathBuf::from),
+        )
+        .map(|dir| dir.join(DIR).join(format!("{app}.toml")))
+        .find(|path| path.is_file())
+}
+
+/// `pointer-lock-hotkey` is `POINTER_LOCK_HOTKEY`.
+fn normalize(key: &str) -> String {
+    key.to_uppercase().replace('-', "_")
+}
+
+fn values(table: Table) -> Result<HashMap<String, String>> {
+    table
+        .into_iter()
+        .map(|(key, value)| {
+            let text = to_text(&key, &value)?;
+            Ok((normalize(&key), text))
+        })
+        .collect()
+}
+
+fn to_text(key: &str, value: &Value) -> Result<String> {
+    match value {
+        Value::String(text) => Ok(text.clone()),
+        Value::Integer(number) => Ok(number.to_string()),
+        Value::Float(number) => Ok(number.to_string()),
+        Value::Boolean(flag) => Ok(flag.to_string()),
+        Value::Datetime(datetime) => Ok(datetime.to_string()),
+        Value::Array(items) => Ok(items
+            .iter()
+            .map(|item| match item {
+                Value::Array(_) | Value::Table(_) => Err(wrong_format(key, value)),
+                item => to_text(key, item),
+            })
+            .collect::<Result<Vec<_>>>()?
+            .join(",")),
+        Value::Table(_) => Err(Error::TableUnexpected(key.to_string())),
+    }
+}
+
+fn wrong_format(key: &str, value: &Value) -> Error {
+    Error::ValueWrongFormat {
+        key: normalize(key),
+        value: value.to_string(),
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    const FX_FILE: &str = r#"
+        profile = "laptop"
+        lib_config_test_address = "10.0.0.1:54321"
+        lib_config_test_backends = ["uinput", "dry-run"]
+
+        [profiles.laptop]
+        lib-config-test-resolution = "2560x1600"
+
+        [profiles.office-desktop]
+        lib_config_test_address = "10.0.0.2:54321"
+        lib_config_test_batch_ms = 8
+    "#;
+
+    #[test]
+    fn test_layers() -> Result<()> {
+        let settings = Settings::parse(FX_FILE, None)?;
+        assert_eq!(settings.profile(), Some("laptop"));
+        assert_eq!(
+            settings.get("LIB_CONFIG_TEST_ADDRESS").as_deref(),
+            Some("10.0.0.1:54321")
+        );
+        assert_eq!(
+            settings.get("LIB_CONFIG_TEST_RESOLUTION").as_deref(),
+            Some("2560x1600")
+        );
+        assert_eq!(
+            settings.get("LIB_CONFIG_TEST_BACKENDS").as_deref(),
+            Some("uinput,dry-run")
+        );
+
+        // The profile over the top, the overrides over everything.
+        let settings = Settings::parse(FX_FILE, Some("office-desktop"))?
+            .with_overrides(&["lib-config-test-batch-ms=4".to_string()])?;
+        assert_eq!(
+            settings.get_parse("LIB_CONFIG_TEST_ADDRESS")?,
+            Some("10.0.0.2:54321".parse::<std::net::SocketAddr>()?)
+        );
+        assert_eq!(settings.get_parse("LIB_CONFIG_TEST_BATCH_MS")?, Some(4));
+        assert_eq!(settings.get("LIB_CONFIG_TEST_RESOLUTION"), None);
+        assert_eq!(settings.get_parse::<u32>("LIB_CONFIG_TEST_MISSING")?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reload() -> Result<()> {
+        let dir = env::temp_dir().join(format!("lib-config-reload-{}", std::process::id()));
+        let path = dir.join("air_client.toml");
+        fs::create_dir_all(&dir)?;
+        fs::write(&path, FX_FILE)?;
+
+        let settings = Settings::load("air_client", Some(&path), Some("office-desktop"))?
+            .with_override("lib_config_test_batch_ms", "4");
+        fs::write(
+            &path,
+            FX_FILE
+                .replace("10.0.0.2:54321", "10.0.0.3:54321")
+                .replace("= 8", "= 16"),
+        )?;
+
+        // Same profile and overrides, the new values of the file.
+        let settings = settings.reload()?;
+        assert_eq!(settings.profile(), Some("office-desktop"));
+        assert_eq!(
+            settings.get("LIB_CONFIG_TEST_ADDRESS").as_deref(),
+            Some("10.0.0.3:54321")
+        );
+        assert_eq!(settings.get_parse("LIB_CONFIG_TEST_BATCH_MS")?, Some(4));
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_errors() -> Result<()> {
+        assert!(matches!(
+            Settings::parse(FX_FILE, Some("desk")),
+            Err(Error::ProfileUnknown(_))
+        ));
+        assert!(matches!(
+            Settings::parse("[screens]\nwidth = 1", None),
+            Err(Error::TableUnexpected(_))
+        ));
+        assert!(matches!(
+            Settings::parse("address = ", None),
+            Err(Error::Toml(_))
+        ));
+        assert!(matches!(
+            Settings::default().with_overrides(&["address".to_string()]),
+            Err(Error::OverrideWrongFormat(_))
+        ));
+
+        let settings = Settings::parse(FX_FILE, None)?;
+        assert!(matches!(
+            settings.get_parse::<u32>("LIB_CONFIG_TEST_RESOLUTION"),
+            Err(Error::ValueWrongFormat { key, .. }) if key == "LIB_CONFIG_TEST_RESOLUTION"
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
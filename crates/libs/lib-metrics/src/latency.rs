@@ -0,0 +1,112 @@
+use std::{collections::VecDeque, sync::Mutex, time::Duration};
+
+/// Samples kept by default, about ten seconds of a busy session.
+const DEFAULT_WINDOW: usize = 1024;
+
+/// Latencies of the most recent events, for percentiles that follow the
+/// current conditions.
+#[derive(Debug)]
+pub struct Latency {
+    samples: Mutex<VecDeque<Duration>>,
+    window: usize,
+}
+
+impl Default for Latency {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+impl Latency {
+    pub fn new(window: usize) -> Self {
+        Self {
+            samples: Mutex::new(VecDeque::with_capacity(window)),
+            window: window.max(1),
+        }
+    }
+
+    pub fn record(&self, latency: Duration) {
+        let mut samples = self.samples.lock().unwrap_or_else(|e| e.into_inner());
+        if samples.len() == self.window {
+            samples.pop_front();
+        }
+        samples.push_back(latency);
+    }
+
+    /// Latency under which `quantile` (0..=1) of the samples are, `None`
+    /// before the first sample.
+    pub fn percentile(&self, quantile: f64) -> Option<Duration> {
+        let mut samples: Vec<_> = self
+            .samples
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .iter()
+            .copied()
+            .collect();
+        if samples.is_empty() {
+            return None;
+        }
+
+        samples.sort_unstable();
+        let rank = (quantile.clamp(0.0, 1.0) * samples.len() as f64).ceil() as usize;
+        Some(samples[rank.saturating_sub(1)])
+    }
+
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(0.5)
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(0.99)
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.lock().unwrap_or_else(|e| e.into_inner()).len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_percentiles() -> Result<()> {
+        let latency = Latency::new(100);
+        assert_eq!(latency.p50(), None);
+
+        for ms in (1..=100).rev() {
+            latency.record(Duration::from_millis(ms));
+        }
+
+        assert_eq!(latency.p50(), Some(Duration::from_millis(50)));
+        assert_eq!(latency.p99(), Some(Duration::from_millis(99)));
+        assert_eq!(latency.percentile(1.0), Some(Duration::from_millis(100)));
+        assert_eq!(latency.percentile(0.0), Some(Duration::from_millis(1)));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_window() -> Result<()> {
+        let latency = Latency::new(2);
+        latency.record(Duration::from_secs(10));
+        latency.record(Duration::from_millis(1));
+        latency.record(Duration::from_millis(2));
+
+        // The oldest sample is gone.
+        assert_eq!(latency.len(), 2);
+        assert_eq!(latency.percentile(1.0), Some(Duration::from_millis(2)));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
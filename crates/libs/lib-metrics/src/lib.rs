@@ -0,0 +1,29 @@
+//! Live metrics shared by the client and the server: latency windows,
+//! sequence gaps, rates and their Prometheus text exposition.
+
+// region:    --- Modules
+
+mod error;
+mod latency;
+mod rate;
+mod sequence;
+
+pub mod prometheus;
+
+pub use error::{Error, Result};
+pub use latency::Latency;
+pub use rate::Rate;
+pub use sequence::{Observed, SequenceTracker};
+
+// endregion: --- Modules
+
+use std::time::{SystemTime, UNIX_EPOCH};
+
+/// Wall clock in microseconds since the Unix epoch, the time base of the
+/// timestamps exchanged by the peers.
+pub fn now_micros() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|since| since.as_micros() as u64)
+        .unwrap_or_default()
+}
@@ -0,0 +1,180 @@
+//! Prometheus text exposition and a minimal HTTP endpoint serving it.
+
+use crate::{Latency, Result};
+use std::{fmt::Write, net::SocketAddr};
+use tokio::{
+    io::{AsyncReadExt, AsyncWriteExt},
+    net::{TcpListener, TcpStream},
+};
+use tracing::{debug, info};
+
+/// Path the metrics are served on.
+pub const PATH: &str = "/metrics";
+
+/// Largest request read, the endpoint only answers `GET`s.
+const MAX_REQUEST_LEN: usize = 4096;
+
+/// Metrics in the Prometheus text format.
+#[derive(Debug, Default)]
+pub struct Exposition {
+    text: String,
+}
+
+impl Exposition {
+    pub fn counter(&mut self, name: &str, help: &str, value: u64) -> &mut Self {
+        self.header(name, help, "counter");
+        let _ = writeln!(self.text, "{name} {value}");
+        self
+    }
+
+    pub fn gauge(&mut self, name: &str, help: &str, value: f64) -> &mut Self {
+        self.header(name, help, "gauge");
+        let _ = writeln!(self.text, "{name} {value}");
+        self
+    }
+
+    /// p50 and p99 of `latency` in seconds, with the number of samples.
+    pub fn summary(&mut self, name: &str, help: &str, latency: &Latency) -> &mut Self {
+        self.header(name, help, "summary");
+        for (label, quantile) in [("0.5", 0.5), ("0.99", 0.99)] {
+            if let Some(value) = latency.percentile(quantile) {
+                let _ = writeln!(
+                    self.text,
+                    "{name}{{quantile=\"{label}\"}} {}",
+                    value.as_secs_f64()
+                );
+            }
+        }
+        let _ = writeln!(self.text, "{name}_count {}", latency.len());
+        self
+    }
+
+    fn header(&mut self, name: &str, help: &str, kind: &str) {
+        let _ = writeln!(self.text, "# HELP {name} {help}");
+        let _ = writeln!(self.text, "# TYPE {name} {kind}");
+    }
+
+    pub fn finish(&mut self) -> String {
+        std::mem::take(&mut self.text)
+    }
+}
+
+/// Serves `render()` on `http://address/metrics` until the task is dropped.
+pub async fn serve<F>(address: SocketAddr, render: F) -> Result<()>
+where
+    F: Fn() -> String + Send + Sync + 'static,
+{
+    let listener = TcpListener::bind(address).await?;
+    info!("📈 Metrics on http://{}{PATH}", listener.local_addr()?);
+
+    let render = std::sync::Arc::new(render);
+    loop {
+        let (stream, peer) = listener.accept().await?;
+        let render = render.clone();
+        tokio::spawn(async move {
+            if let Err(e) = respond(stream, || render()).await {
+                debug!("Metrics request from {peer} failed: {e}");
+            }
+        });
+    }
+}
+
+async fn respond(mut stream: TcpStream, render: impl Fn() -> String) -> Result<()> {
+    let mut request = Vec::new();
+    let mut buf = [0u8; 512];
+    while !request.windows(4).any(|end| end == b"\r\n\r\n") && request.len() < MAX_REQUEST_LEN {
+        match stream.read(&mut buf).await? {
+            0 => break,
+            n => request.extend_from_slice(&buf[..n]),
+        }
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut line = request.lines().next().unwrap_or_default().split(' ');
+    let response = match (line.next(), line.next()) {
+        (Some("GET"), Some(PATH)) => {
+            let body = render();
+            format!(
+                "HTTP/1.1 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                body.len()
+            )
+        }
+        _ => "HTTP/1.1 404 Not Found\r\nContent-Length: 0\r\nConnection: close\r\n\r\n".to_string(),
+    };
+
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_exposition() -> Result<()> {
+        let latency = Latency::default();
+        latency.record(Duration::from_millis(2));
+
+        let text = Exposition::default()
+            .counter("air_commands_total", "Commands applied.", 3)
+            .summary("air_latency_seconds", "Latency.", &latency)
+            .finish();
+
+        assert_eq!(
+            text,
+            "# HELP air_commands_total Commands applied.\n\
+             # TYPE air_commands_total counter\n\
+             air_commands_total 3\n\
+             # HELP air_latency_seconds Latency.\n\
+             # TYPE air_latency_seconds summary\n\
+             air_latency_seconds{quantile=\"0.5\"} 0.002\n\
+             air_latency_seconds{quantile=\"0.99\"} 0.002\n\
+             air_latency_seconds_count 1\n"
+        );
+
+        Ok(())
+    }
+
+    #[tokio::test]
+    async fn test_serve() -> Result<()> {
+        let listener = TcpListener::bind("127.0.0.1:0").await?;
+        let address = listener.local_addr()?;
+        let server = tokio::spawn(async move {
+            for _ in 0..2 {
+                let (stream, _) = listener.accept().await?;
+                respond(stream, || "metric 1\n".to_string()).await?;
+            }
+            crate::Result::Ok(())
+        });
+
+        let get = |path: &'static str| async move {
+            let mut stream = TcpStream::connect(address).await?;
+            stream
+                .write_all(format!("GET {path} HTTP/1.1\r\nHost: test\r\n\r\n").as_bytes())
+                .await?;
+            let mut response = String::new();
+            stream.read_to_string(&mut response).await?;
+            std::io::Result::Ok(response)
+        };
+
+        let response = get(PATH).await?;
+        assert!(response.starts_with("HTTP/1.1 200 OK\r\n"));
+        assert!(response.ends_with("\r\n\r\nmetric 1\n"));
+
+        let response = get("/").await?;
+        assert!(response.starts_with("HTTP/1.1 404"));
+
+        server.await??;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,91 @@
+use std::{
+    sync::{
+        atomic::{AtomicU64, Ordering},
+        Mutex,
+    },
+    time::{Duration, Instant},
+};
+
+/// Shortest period a rate is measured over.
+const MIN_PERIOD: Duration = Duration::from_secs(1);
+
+/// Counts events and measures how many come per second.
+#[derive(Debug)]
+pub struct Rate {
+    total: AtomicU64,
+    /// Start, total and rate of the last period.
+    period: Mutex<(Instant, u64, f64)>,
+}
+
+impl Default for Rate {
+    fn default() -> Self {
+        Self {
+            total: AtomicU64::new(0),
+            period: Mutex::new((Instant::now(), 0, 0.0)),
+        }
+    }
+}
+
+impl Rate {
+    pub fn add(&self, count: u64) {
+        self.total.fetch_add(count, Ordering::Relaxed);
+    }
+
+    pub fn total(&self) -> u64 {
+        self.total.load(Ordering::Relaxed)
+    }
+
+    /// Events per second since the previous period, which ends once at least a
+    /// second has passed.
+    pub fn per_second(&self) -> f64 {
+        self.per_second_at(Instant::now())
+    }
+
+    fn per_second_at(&self, now: Instant) -> f64 {
+        let mut period = self.period.lock().unwrap_or_else(|e| e.into_inner());
+        let (start, start_total, rate) = *period;
+
+        let elapsed = now.saturating_duration_since(start);
+        if elapsed < MIN_PERIOD {
+            return rate;
+        }
+
+        let total = self.total();
+        let rate = (total - start_total) as f64 / elapsed.as_secs_f64();
+        *period = (now, total, rate);
+
+        rate
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_per_second() -> Result<()> {
+        let rate = Rate::default();
+        let start = rate.period.lock().map_err(|e| e.to_string())?.0;
+
+        rate.add(500);
+        assert_eq!(rate.per_second_at(start + Duration::from_millis(10)), 0.0);
+        assert_eq!(rate.per_second_at(start + Duration::from_secs(2)), 250.0);
+
+        // Kept until the next period ends.
+        rate.add(10);
+        assert_eq!(
+            rate.per_second_at(start + Duration::from_millis(2500)),
+            250.0
+        );
+        assert_eq!(rate.per_second_at(start + Duration::from_secs(4)), 5.0);
+        assert_eq!(rate.total(), 510);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
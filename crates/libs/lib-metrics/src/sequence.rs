@@ -0,0 +1,79 @@
+/// Follows the numbers of received events to count the missing and late ones.
+///
+/// A number skipped is counted lost until it shows up, then it counts as
+/// reordered instead.
+#[derive(Debug, Default)]
+pub struct SequenceTracker {
+    highest: u64,
+    received: u64,
+}
+
+/// What one received number changed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Observed {
+    /// Numbers skipped before this one.
+    pub skipped: u64,
+    /// The number was skipped before and arrived late.
+    pub late: bool,
+}
+
+impl SequenceTracker {
+    pub fn observe(&mut self, number: u64) -> Observed {
+        self.received += 1;
+        if number > self.highest {
+            let skipped = number - self.highest - 1;
+            self.highest = number;
+            return Observed {
+                skipped,
+                late: false,
+            };
+        }
+
+        Observed {
+            skipped: 0,
+            late: true,
+        }
+    }
+
+    /// Numbers below the highest one that never arrived.
+    pub fn lost(&self) -> u64 {
+        self.highest.saturating_sub(self.received)
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_observe() -> Result<()> {
+        let mut tracker = SequenceTracker::default();
+
+        assert_eq!(tracker.observe(1), Observed::default());
+        assert_eq!(
+            tracker.observe(4),
+            Observed {
+                skipped: 2,
+                late: false
+            }
+        );
+        assert_eq!(tracker.lost(), 2);
+
+        assert_eq!(
+            tracker.observe(3),
+            Observed {
+                skipped: 0,
+                late: true
+            }
+        );
+        assert_eq!(tracker.lost(), 1);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
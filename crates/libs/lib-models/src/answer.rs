@@ -1,6 +1,60 @@
 use bincode::{Decode, Encode};
 
+use crate::{clipboard::ClipboardChunk, CommandKind, Key, MouseButton};
+
 #[derive(Debug, Clone, Encode, Decode)]
 pub enum Answer {
-    ClipboardContents(String),
+    /// Part of the server clipboard content.
+    ClipboardChunk(ClipboardChunk),
+    /// MIME types the server clipboard accepts.
+    ClipboardFormats(Vec<String>),
+    /// The server couldn't apply a command.
+    CommandFailed {
+        command: CommandKind,
+        error: InputErrorKind,
+    },
+    /// Answer to [`Command::Ping`](crate::Command::Ping).
+    Pong {
+        id: u64,
+        /// Client clock the ping was sent at.
+        sent_at: u64,
+        /// Server clock in microseconds since the Unix epoch when the ping
+        /// arrived.
+        received_at: u64,
+    },
+    /// The policy of the client doesn't let the server apply a command.
+    CommandDenied {
+        command: CommandKind,
+        reason: DenyReason,
+    },
+}
+
+/// Why the server refused a command.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub enum DenyReason {
+    /// The client may not send commands of this kind.
+    NotAllowed,
+    /// The command would hold a blocked key combination.
+    BlockedKeys,
+    /// The client sent more commands than it may.
+    RateLimited,
+}
+
+/// Why the server couldn't inject a command.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum InputErrorKind {
+    /// The key has no equivalent in the server backend.
+    UnsupportedKey(Key),
+    /// The button has no equivalent in the server backend.
+    UnsupportedButton(MouseButton),
+    /// The character can't be typed by the server backend.
+    UnsupportedChar(char),
+    /// The server doesn't handle this command.
+    UnsupportedCommand,
+    /// The server has no display with this index.
+    UnknownDisplay(u32),
+    /// The payload is bigger than the server accepts.
+    TooLarge { size: usize, max: usize },
+    /// The backend failed to inject the event.
+    Backend(String),
 }
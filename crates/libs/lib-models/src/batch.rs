@@ -0,0 +1,300 @@
+use crate::{Command, MouseScroll, ScrollDelta};
+
+/// Collects the commands of one input frame or tick.
+///
+/// Consecutive motion is coalesced: absolute positions keep the latest one,
+/// relative deltas and scroll of the same source are summed. Scroll after a
+/// stop starts a new motion and isn't merged into it.
+#[derive(Debug, Default)]
+pub struct CommandBatch {
+    commands: Vec<Command>,
+}
+
+impl CommandBatch {
+    pub fn push(&mut self, command: Command) {
+        if let Command::Batch(commands) = command {
+            commands.into_iter().for_each(|command| self.push(command));
+            return;
+        }
+
+        match (self.commands.last_mut(), command) {
+            (
+                Some(Command::SetMouse { display, x, y }),
+                Command::SetMouse {
+                    display: next_display,
+                    x: next_x,
+                    y: next_y,
+                },
+            ) if *display == next_display => {
+                *x = next_x;
+                *y = next_y;
+            }
+            (Some(Command::MoveMouse { x, y }), Command::MoveMouse { x: dx, y: dy }) => {
+                *x = x.saturating_add(dx);
+                *y = y.saturating_add(dy);
+            }
+            (Some(Command::MouseScroll(scroll)), Command::MouseScroll(next))
+                if scroll.source == next.source
+                    && !scroll.vertical.stop
+                    && !scroll.horizontal.stop =>
+            {
+                merge_scroll(scroll, &next);
+            }
+            (_, command) => self.commands.push(command),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.commands.len()
+    }
+
+    /// Empties the batch, a single command is returned as is.
+    pub fn take(&mut self) -> Option<Command> {
+        match self.commands.len() {
+            0 => None,
+            1 => self.commands.pop(),
+            _ => Some(Command::Batch(std::mem::take(&mut self.commands))),
+        }
+    }
+
+    pub fn into_commands(self) -> Vec<Command> {
+        self.commands
+    }
+}
+
+fn merge_scroll(scroll: &mut MouseScroll, next: &MouseScroll) {
+    let merge = |delta: &mut ScrollDelta, next: &ScrollDelta| {
+        delta.value120 = delta.value120.saturating_add(next.value120);
+        delta.pixels += next.pixels;
+        delta.stop |= next.stop;
+    };
+
+    merge(&mut scroll.vertical, &next.vertical);
+    merge(&mut scroll.horizontal, &next.horizontal);
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use crate::{CommandKind, Delivery, Key, MouseButton, ScrollSource};
+
+    fn set_mouse(x: u32, y: u32) -> Command {
+        Command::SetMouse { display: 0, x, y }
+    }
+
+    fn kinds(batch: CommandBatch) -> Vec<CommandKind> {
+        batch.into_commands().iter().map(Command::kind).collect()
+    }
+
+    #[test]
+    fn test_coalesce_motion() -> Result<()> {
+        let mut batch = CommandBatch::default();
+        batch.push(set_mouse(1, 1));
+        batch.push(set_mouse(2, 3));
+        batch.push(Command::MouseButtonPressed(MouseButton::Left));
+        batch.push(set_mouse(4, 5));
+        batch.push(Command::SetMouse {
+            display: 1,
+            x: 6,
+            y: 7,
+        });
+
+        let commands = batch.into_commands();
+        assert_eq!(commands.len(), 4);
+        assert!(matches!(
+            commands[0],
+            Command::SetMouse {
+                display: 0,
+                x: 2,
+                y: 3
+            }
+        ));
+        assert!(matches!(commands[2], Command::SetMouse { x: 4, y: 5, .. }));
+
+        let mut batch = CommandBatch::default();
+        batch.push(Command::MoveMouse { x: 3, y: -1 });
+        batch.push(Command::MoveMouse { x: 2, y: -4 });
+        assert!(matches!(
+            batch.take(),
+            Some(Command::MoveMouse { x: 5, y: -5 })
+        ));
+        assert!(batch.take().is_none());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_coalesce_scroll() -> Result<()> {
+        let scroll = |value120, source| {
+            Command::MouseScroll(MouseScroll {
+                source,
+                vertical: ScrollDelta {
+                    value120,
+                    pixels: 15.0,
+                    stop: false,
+                },
+                ..Default::default()
+            })
+        };
+
+        let mut batch = CommandBatch::default();
+        batch.push(scroll(120, ScrollSource::Wheel));
+        batch.push(scroll(-60, ScrollSource::Wheel));
+        batch.push(scroll(30, ScrollSource::Finger));
+
+        let commands = batch.into_commands();
+        assert_eq!(commands.len(), 2);
+        let Command::MouseScroll(merged) = &commands[0] else {
+            return Err("not a scroll".into());
+        };
+        assert_eq!(merged.vertical.value120, 60);
+        assert_eq!(merged.vertical.pixels, 30.0);
+
+        // A stop ends the motion, the scroll after it isn't merged before it.
+        let stop = Command::MouseScroll(MouseScroll {
+            source: ScrollSource::Finger,
+            vertical: ScrollDelta {
+                stop: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        });
+        let mut batch = CommandBatch::default();
+        batch.push(scroll(30, ScrollSource::Finger));
+        batch.push(stop);
+        batch.push(scroll(60, ScrollSource::Finger));
+
+        let commands = batch.into_commands();
+        assert_eq!(commands.len(), 2);
+        let (Command::MouseScroll(first), Command::MouseScroll(second)) =
+            (&commands[0], &commands[1])
+        else {
+            return Err("not a scroll".into());
+        };
+        assert!(first.vertical.stop);
+        assert_eq!(first.vertical.value120, 30);
+        assert!(!second.vertical.stop);
+        assert_eq!(second.vertical.value120, 60);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_take() -> Result<()> {
+        let mut batch = CommandBatch::default();
+        batch.push(set_mouse(1, 1));
+        batch.push(Command::MouseButtonPressed(MouseButton::Left));
+
+        let command = batch.take().ok_or("empty batch")?;
+        assert!(batch.is_empty());
+        // The press makes the whole batch reliable.
+        assert_eq!(command.delivery(), Delivery::Reliable);
+
+        // Batches pushed in a batch are flattened.
+        batch.push(command);
+        batch.push(Command::MouseButtonReleased(MouseButton::Left));
+        assert_eq!(
+            kinds(batch),
+            [
+                CommandKind::SetMouse,
+                CommandKind::MouseButtonPressed,
+                CommandKind::MouseButtonReleased
+            ]
+        );
+
+        let batch = Command::Batch(vec![set_mouse(1, 1), Command::MoveMouse { x: 1, y: 1 }]);
+        assert_eq!(batch.delivery(), Delivery::Datagram);
+
+        // Keys and clipboard go on their own.
+        assert!(!Command::KeyPressed(Key::A).is_batchable());
+        assert!(!Command::ClipboardFormats(Vec::new()).is_batchable());
+        assert!(!batch.is_batchable());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_batch_speed_compare() -> Result<()> {
+        use crate::SequencedCommand;
+        use lib_codec::frame::{self, Flags};
+        use std::time::Instant;
+
+        // One second of a 1000 Hz mouse: relative motion, a wheel notch every
+        // 10 ms and a click every 100 ms.
+        let input: Vec<Command> = (0..1000i32)
+            .map(|i| match i % 100 {
+                0 => Command::MouseButtonPressed(MouseButton::Left),
+                50 => Command::MouseButtonReleased(MouseButton::Left),
+                _ if i % 10 == 5 => Command::MouseScroll(MouseScroll {
+                    source: ScrollSource::Wheel,
+                    vertical: ScrollDelta {
+                        value120: 120,
+                        pixels: 15.0,
+                        stop: false,
+                    },
+                    ..Default::default()
+                }),
+                _ => Command::MoveMouse {
+                    x: i % 7 - 3,
+                    y: i % 5 - 2,
+                },
+            })
+            .collect();
+
+        // Every command in its own datagram.
+        let single_start = Instant::now();
+        let mut single_bytes = 0;
+        for (seq, command) in input.iter().enumerate() {
+            let sequenced = SequencedCommand::new(seq as u64, command.clone());
+            single_bytes += frame::encode(&sequenced, Flags::CHECKSUM)?.len();
+        }
+        let single_duration = single_start.elapsed();
+
+        // The commands of each 8 ms tick in one batch.
+        let batch_start = Instant::now();
+        let mut batch_bytes = 0;
+        let mut batches = 0;
+        let mut batch = CommandBatch::default();
+        for (seq, tick) in input.chunks(8).enumerate() {
+            for command in tick {
+                batch.push(command.clone());
+            }
+            let command = batch.take().ok_or("empty tick")?;
+            let sequenced = SequencedCommand::new(seq as u64, command);
+            batch_bytes += frame::encode(&sequenced, Flags::CHECKSUM)?.len();
+            batches += 1;
+        }
+        let batch_duration = batch_start.elapsed();
+
+        println!("\n========== BATCH ({} commands) ==========", input.len());
+        println!(
+            "single:                 {:?}, {} messages, {} bytes",
+            single_duration,
+            input.len(),
+            single_bytes
+        );
+        println!(
+            "batched:                {:?}, {} messages, {} bytes",
+            batch_duration, batches, batch_bytes
+        );
+        println!(
+            "Bytes saved:            {:.2}x",
+            single_bytes as f64 / batch_bytes as f64
+        );
+
+        assert_eq!(batches, 125);
+        assert!(batch_bytes < single_bytes);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,161 @@
+//! Splits encoded payloads in chunks, so a large image doesn't hold the
+//! stream it shares with other commands for the whole transfer.
+
+use super::{ClipboardPayload, Error, Result};
+use bincode::{Decode, Encode};
+
+/// Bytes of encoded payload per chunk.
+pub const CHUNK_SIZE: usize = 64 * 1024;
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ClipboardChunk {
+    /// Identifies the payload, chunks of a new transfer drop the previous one.
+    pub transfer: u32,
+    pub index: u32,
+    pub total: u32,
+    pub data: Vec<u8>,
+}
+
+impl ClipboardPayload {
+    pub fn to_chunks(&self, transfer: u32, chunk_size: usize) -> Result<Vec<ClipboardChunk>> {
+        let encoded = lib_codec::encode(self).map_err(|_| Error::Encode)?;
+        let total = encoded.len().div_ceil(chunk_size.max(1)) as u32;
+
+        let chunks = encoded
+            .chunks(chunk_size.max(1))
+            .enumerate()
+            .map(|(index, data)| ClipboardChunk {
+                transfer,
+                index: index as u32,
+                total,
+                data: data.to_vec(),
+            })
+            .collect();
+
+        Ok(chunks)
+    }
+}
+
+/// Rebuilds payloads from chunks received in order.
+#[derive(Debug)]
+pub struct ChunkAssembler {
+    transfer: Option<u32>,
+    buffer: Vec<u8>,
+    next: u32,
+    max_size: usize,
+}
+
+impl ChunkAssembler {
+    pub fn new(max_size: usize) -> Self {
+        Self {
+            transfer: None,
+            buffer: Vec::new(),
+            next: 0,
+            max_size,
+        }
+    }
+
+    /// Returns the payload once its last chunk arrived.
+    pub fn push(&mut self, chunk: ClipboardChunk) -> Result<Option<ClipboardPayload>> {
+        if self.transfer != Some(chunk.transfer) {
+            self.reset(Some(chunk.transfer));
+        }
+
+        if chunk.index != self.next {
+            let expected = self.next;
+            self.reset(None);
+            return Err(Error::ChunkOutOfOrder {
+                expected,
+                received: chunk.index,
+            });
+        }
+
+        let size = self.buffer.len() + chunk.data.len();
+        if size > self.max_size {
+            self.reset(None);
+            return Err(Error::TooLarge {
+                size,
+                max: self.max_size,
+            });
+        }
+
+        self.buffer.extend_from_slice(&chunk.data);
+        self.next += 1;
+
+        if self.next < chunk.total {
+            return Ok(None);
+        }
+
+        let payload = lib_codec::decode(&self.buffer).map_err(|_| Error::Decode);
+        self.reset(None);
+
+        payload.map(Some)
+    }
+
+    fn reset(&mut self, transfer: Option<u32>) {
+        self.transfer = transfer;
+        self.buffer.clear();
+        self.next = 0;
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use crate::clipboard::IMAGE_PNG;
+
+    #[test]
+    fn test_roundtrip() -> Result<()> {
+        let payload = ClipboardPayload::text("hello").with(IMAGE_PNG, vec![7u8; 1000]);
+        let chunks = payload.to_chunks(1, 100)?;
+        assert!(chunks.len() > 1);
+
+        let mut assembler = ChunkAssembler::new(usize::MAX);
+        let mut rebuilt = None;
+        for chunk in chunks {
+            rebuilt = assembler.push(chunk)?;
+        }
+
+        assert_eq!(rebuilt, Some(payload));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_new_transfer_drops_previous() -> Result<()> {
+        let old = ClipboardPayload::text("old".repeat(100)).to_chunks(1, 50)?;
+        let new = ClipboardPayload::text("new");
+
+        let mut assembler = ChunkAssembler::new(usize::MAX);
+        assert_eq!(assembler.push(old[0].clone())?, None);
+
+        let mut rebuilt = None;
+        for chunk in new.to_chunks(2, 50)? {
+            rebuilt = assembler.push(chunk)?;
+        }
+
+        assert_eq!(rebuilt, Some(new));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_size() -> Result<()> {
+        let chunks = ClipboardPayload::text("x".repeat(300)).to_chunks(1, 100)?;
+
+        let mut assembler = ChunkAssembler::new(150);
+        assert_eq!(assembler.push(chunks[0].clone())?, None);
+        assert!(matches!(
+            assembler.push(chunks[1].clone()),
+            Err(Error::TooLarge { .. })
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
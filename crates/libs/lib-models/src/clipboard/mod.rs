@@ -0,0 +1,346 @@
+//! Clipboard access and the state needed to keep two clipboards in sync.
+
+mod chunk;
+mod payload;
+mod system;
+
+pub use chunk::{ChunkAssembler, ClipboardChunk, CHUNK_SIZE};
+pub use payload::{
+    ClipboardEntry, ClipboardPayload, ALL_FORMATS, IMAGE_PNG, TEXT_HTML, TEXT_PLAIN, URI_LIST,
+};
+pub use system::SystemClipboard;
+
+use derive_more::From;
+use std::sync::{Arc, Mutex};
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// Largest content synchronised when nothing else is configured.
+pub const DEFAULT_MAX_SIZE: usize = 1024 * 1024;
+
+pub trait Clipboard: Send + Sync {
+    /// MIME types this clipboard can hold.
+    fn formats(&self) -> Vec<String>;
+    fn read(&mut self) -> Result<ClipboardPayload>;
+    /// Writes the entries of `payload` in the supported formats.
+    fn write(&mut self, payload: &ClipboardPayload) -> Result<()>;
+}
+
+/// In-memory clipboard accepting every known format, clones share the same
+/// content.
+#[derive(Debug, Clone, Default)]
+pub struct MemoryClipboard(Arc<Mutex<ClipboardPayload>>);
+
+impl MemoryClipboard {
+    pub fn contents(&self) -> ClipboardPayload {
+        self.0.lock().unwrap().clone()
+    }
+}
+
+/// Clones are the same clipboard.
+impl PartialEq for MemoryClipboard {
+    fn eq(&self, other: &Self) -> bool {
+        Arc::ptr_eq(&self.0, &other.0)
+    }
+}
+
+impl Clipboard for MemoryClipboard {
+    fn formats(&self) -> Vec<String> {
+        ALL_FORMATS
+            .iter()
+            .map(|format| format.to_string())
+            .collect()
+    }
+
+    fn read(&mut self) -> Result<ClipboardPayload> {
+        Ok(self.contents())
+    }
+
+    fn write(&mut self, payload: &ClipboardPayload) -> Result<()> {
+        *self.0.lock().unwrap() = payload.clone().negotiate(&self.formats());
+        Ok(())
+    }
+}
+
+/// Which clipboard a peer synchronises.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub enum ClipboardBackend {
+    Disabled,
+    #[default]
+    System,
+    Memory(MemoryClipboard),
+}
+
+impl ClipboardBackend {
+    /// `None` when disabled.
+    pub fn open(&self) -> Result<Option<Box<dyn Clipboard>>> {
+        Ok(match self {
+            Self::Disabled => None,
+            Self::System => Some(Box::new(SystemClipboard::open()?)),
+            Self::Memory(clipboard) => Some(Box::new(clipboard.clone())),
+        })
+    }
+}
+
+impl core::str::FromStr for ClipboardBackend {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s.trim() {
+            "off" | "disabled" => Ok(Self::Disabled),
+            "system" => Ok(Self::System),
+            "memory" => Ok(Self::Memory(MemoryClipboard::default())),
+            _ => Err(Error::UnknownBackend(s.to_string())),
+        }
+    }
+}
+
+/// Tracks the last content exchanged with the peer, so a change applied from
+/// the peer isn't sent back to it.
+pub struct ClipboardSync {
+    clipboard: Box<dyn Clipboard>,
+    last: Option<ClipboardPayload>,
+    max_size: usize,
+    /// Formats announced by the peer, everything is sent until then.
+    peer_formats: Option<Vec<String>>,
+    next_transfer: u32,
+    assembler: ChunkAssembler,
+}
+
+impl ClipboardSync {
+    pub fn new(clipboard: Box<dyn Clipboard>, max_size: usize) -> Self {
+        Self {
+            clipboard,
+            last: None,
+            max_size,
+            peer_formats: None,
+            next_transfer: 0,
+            assembler: ChunkAssembler::new(max_size.saturating_mul(2)),
+        }
+    }
+
+    /// MIME types to announce to the peer.
+    pub fn formats(&self) -> Vec<String> {
+        self.clipboard.formats()
+    }
+
+    pub fn set_peer_formats(&mut self, formats: Vec<String>) {
+        self.peer_formats = Some(formats);
+    }
+
+    /// Takes the current content as already synchronised, it won't be sent
+    /// until it changes.
+    pub fn mark_seen(&mut self) -> Result<()> {
+        self.last = Some(self.clipboard.read()?);
+        Ok(())
+    }
+
+    /// Returns the local content in the peer formats when it changed since the
+    /// last exchange.
+    pub fn poll(&mut self) -> Result<Option<ClipboardPayload>> {
+        let content = self.clipboard.read()?;
+        if self.last.as_ref() == Some(&content) {
+            return Ok(None);
+        }
+
+        self.last = Some(content.clone());
+
+        let content = match &self.peer_formats {
+            Some(formats) => content.negotiate(formats),
+            None => content,
+        };
+        if content.is_empty() {
+            return Ok(None);
+        }
+        self.check_size(content.size())?;
+
+        Ok(Some(content))
+    }
+
+    /// Same as [`ClipboardSync::poll`], split in chunks ready to be sent.
+    pub fn poll_chunks(&mut self) -> Result<Vec<ClipboardChunk>> {
+        let Some(content) = self.poll()? else {
+            return Ok(Vec::new());
+        };
+
+        let transfer = self.next_transfer;
+        self.next_transfer = self.next_transfer.wrapping_add(1);
+
+        content.to_chunks(transfer, CHUNK_SIZE)
+    }
+
+    /// Writes content received from the peer, returns `false` when the local
+    /// clipboard already holds it.
+    pub fn apply(&mut self, content: ClipboardPayload) -> Result<bool> {
+        self.check_size(content.size())?;
+        let content = content.negotiate(&self.clipboard.formats());
+        if content.is_empty() || self.last.as_ref() == Some(&content) {
+            return Ok(false);
+        }
+
+        self.clipboard.write(&content)?;
+        // What the clipboard holds now, so the next poll doesn't echo it.
+        self.last = Some(self.clipboard.read()?);
+
+        Ok(true)
+    }
+
+    /// Collects a chunk from the peer and applies the payload once complete.
+    pub fn receive(&mut self, chunk: ClipboardChunk) -> Result<bool> {
+        match self.assembler.push(chunk)? {
+            Some(content) => self.apply(content),
+            None => Ok(false),
+        }
+    }
+
+    fn check_size(&self, size: usize) -> Result<()> {
+        if size > self.max_size {
+            return Err(Error::TooLarge {
+                size,
+                max: self.max_size,
+            });
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, From)]
+pub enum Error {
+    Init,
+    Get,
+    Set,
+    UnknownBackend(String),
+    TooLarge { size: usize, max: usize },
+    ChunkOutOfOrder { expected: u32, received: u32 },
+    Encode,
+    Decode,
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    fn sync_with(clipboard: &MemoryClipboard, max_size: usize) -> ClipboardSync {
+        ClipboardSync::new(Box::new(clipboard.clone()), max_size)
+    }
+
+    #[test]
+    fn test_poll_reports_changes_once() -> Result<()> {
+        let mut local = MemoryClipboard::default();
+        let mut sync = sync_with(&local, DEFAULT_MAX_SIZE);
+
+        local.write(&ClipboardPayload::text("first"))?;
+        assert_eq!(sync.poll()?, Some(ClipboardPayload::text("first")));
+        assert_eq!(sync.poll()?, None);
+
+        local.write(&ClipboardPayload::text("second"))?;
+        assert_eq!(sync.poll()?, Some(ClipboardPayload::text("second")));
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_applied_content_is_not_echoed() -> Result<()> {
+        let local = MemoryClipboard::default();
+        let mut sync = sync_with(&local, DEFAULT_MAX_SIZE);
+        let remote = ClipboardPayload::text("remote").with(TEXT_HTML, "<b>remote</b>");
+
+        assert!(sync.apply(remote.clone())?);
+        assert_eq!(local.contents(), remote);
+        assert_eq!(sync.poll()?, None);
+        assert!(!sync.apply(remote)?);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_mark_seen_skips_current() -> Result<()> {
+        let mut local = MemoryClipboard::default();
+        local.write(&ClipboardPayload::text("already there"))?;
+        let mut sync = sync_with(&local, DEFAULT_MAX_SIZE);
+
+        sync.mark_seen()?;
+        assert_eq!(sync.poll()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_peer_formats() -> Result<()> {
+        let mut local = MemoryClipboard::default();
+        let mut sync = sync_with(&local, DEFAULT_MAX_SIZE);
+        sync.set_peer_formats(vec![TEXT_PLAIN.to_string()]);
+
+        local.write(&ClipboardPayload::text("caption").with(IMAGE_PNG, vec![1, 2, 3]))?;
+        assert_eq!(sync.poll()?, Some(ClipboardPayload::text("caption")));
+
+        // Nothing the peer can hold.
+        local.write(&ClipboardPayload::default().with(IMAGE_PNG, vec![4, 5]))?;
+        assert_eq!(sync.poll()?, None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chunks_between_peers() -> Result<()> {
+        let mut local = MemoryClipboard::default();
+        let remote = MemoryClipboard::default();
+        let mut local_sync = sync_with(&local, DEFAULT_MAX_SIZE);
+        let mut remote_sync = sync_with(&remote, DEFAULT_MAX_SIZE);
+
+        let image = ClipboardPayload::default().with(IMAGE_PNG, vec![9u8; CHUNK_SIZE * 3]);
+        local.write(&image)?;
+
+        let chunks = local_sync.poll_chunks()?;
+        assert!(chunks.len() > 3);
+
+        let mut applied = false;
+        for chunk in chunks {
+            applied = remote_sync.receive(chunk)?;
+        }
+
+        assert!(applied);
+        assert_eq!(remote.contents(), image);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_max_size() -> Result<()> {
+        let mut local = MemoryClipboard::default();
+        let mut sync = sync_with(&local, 4);
+
+        local.write(&ClipboardPayload::text("too long"))?;
+        assert!(matches!(
+            sync.poll(),
+            Err(Error::TooLarge { size: 8, max: 4 })
+        ));
+        // Reported once, not on every poll.
+        assert_eq!(sync.poll()?, None);
+
+        assert!(matches!(
+            sync.apply(ClipboardPayload::text("also too long")),
+            Err(Error::TooLarge { .. })
+        ));
+        assert_eq!(local.contents(), ClipboardPayload::text("too long"));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
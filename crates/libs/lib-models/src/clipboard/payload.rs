@@ -0,0 +1,75 @@
+use bincode::{Decode, Encode};
+
+pub const TEXT_PLAIN: &str = "text/plain";
+pub const TEXT_HTML: &str = "text/html";
+pub const IMAGE_PNG: &str = "image/png";
+pub const URI_LIST: &str = "text/uri-list";
+
+/// Formats known to the clipboard sync, in order of preference.
+pub const ALL_FORMATS: [&str; 4] = [IMAGE_PNG, TEXT_HTML, URI_LIST, TEXT_PLAIN];
+
+/// The clipboard content in one format.
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub struct ClipboardEntry {
+    pub mime: String,
+    pub data: Vec<u8>,
+}
+
+/// Everything a clipboard holds, one entry per MIME type.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Encode, Decode)]
+pub struct ClipboardPayload {
+    entries: Vec<ClipboardEntry>,
+}
+
+impl ClipboardPayload {
+    pub fn text(text: impl Into<String>) -> Self {
+        Self::default().with(TEXT_PLAIN, text.into().into_bytes())
+    }
+
+    /// Adds or replaces the entry for `mime`.
+    pub fn with(mut self, mime: impl Into<String>, data: impl Into<Vec<u8>>) -> Self {
+        let mime = mime.into();
+        self.entries.retain(|entry| entry.mime != mime);
+        self.entries.push(ClipboardEntry {
+            mime,
+            data: data.into(),
+        });
+        self
+    }
+
+    pub fn get(&self, mime: &str) -> Option<&[u8]> {
+        self.entries
+            .iter()
+            .find(|entry| entry.mime == mime)
+            .map(|entry| entry.data.as_slice())
+    }
+
+    pub fn as_text(&self) -> Option<&str> {
+        self.get(TEXT_PLAIN)
+            .and_then(|data| core::str::from_utf8(data).ok())
+    }
+
+    pub fn entries(&self) -> &[ClipboardEntry] {
+        &self.entries
+    }
+
+    pub fn mimes(&self) -> impl Iterator<Item = &str> {
+        self.entries.iter().map(|entry| entry.mime.as_str())
+    }
+
+    /// Sum of the entry sizes.
+    pub fn size(&self) -> usize {
+        self.entries.iter().map(|entry| entry.data.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+
+    /// Keeps only the entries whose MIME type is in `formats`.
+    pub fn negotiate<S: AsRef<str>>(mut self, formats: &[S]) -> Self {
+        self.entries
+            .retain(|entry| formats.iter().any(|format| format.as_ref() == entry.mime));
+        self
+    }
+}
@@ -0,0 +1,248 @@
+use super::{
+    Clipboard, ClipboardPayload, Error, Result, IMAGE_PNG, TEXT_HTML, TEXT_PLAIN, URI_LIST,
+};
+use arboard::ImageData;
+use std::{
+    borrow::Cow,
+    io::Cursor,
+    path::PathBuf,
+    sync::{Arc, Mutex},
+};
+
+/// Clipboard of the current desktop session. It holds one kind of content at
+/// a time: HTML along with its text, an image, a list of files or text.
+#[derive(Clone)]
+pub struct SystemClipboard(Arc<Mutex<Inner>>);
+
+struct Inner {
+    clipboard: arboard::Clipboard,
+    /// Last image read and its PNG, so an unchanged image isn't encoded on
+    /// every poll.
+    image: Option<(Vec<u8>, Vec<u8>)>,
+}
+
+impl SystemClipboard {
+    /// Kept open while in use, on X11 the content goes away with the last
+    /// open clipboard.
+    pub fn open() -> Result<Self> {
+        let clipboard = arboard::Clipboard::new().map_err(|_| Error::Init)?;
+        Ok(Self(Arc::new(Mutex::new(Inner {
+            clipboard,
+            image: None,
+        }))))
+    }
+}
+
+impl core::fmt::Debug for SystemClipboard {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::fmt::Result {
+        fmt.write_str("SystemClipboard")
+    }
+}
+
+impl Clipboard for SystemClipboard {
+    fn formats(&self) -> Vec<String> {
+        [IMAGE_PNG, TEXT_HTML, URI_LIST, TEXT_PLAIN]
+            .iter()
+            .map(|format| format.to_string())
+            .collect()
+    }
+
+    fn read(&mut self) -> Result<ClipboardPayload> {
+        let mut inner = self.0.lock().unwrap();
+        let Inner { clipboard, image } = &mut *inner;
+        let mut payload = ClipboardPayload::default();
+
+        if let Some(text) = content(clipboard.get_text())? {
+            payload = payload.with(TEXT_PLAIN, text);
+        }
+        if let Some(html) = content(clipboard.get().html())? {
+            payload = payload.with(TEXT_HTML, html);
+        }
+        if let Some(files) = content(clipboard.get().file_list())? {
+            payload = payload.with(URI_LIST, to_uri_list(&files));
+        }
+        match content(clipboard.get_image())? {
+            Some(read) => {
+                let png = match image.take() {
+                    Some((pixels, png)) if *pixels == *read.bytes => png,
+                    _ => encode_png(&read)?,
+                };
+                payload = payload.with(IMAGE_PNG, png.clone());
+                *image = Some((read.bytes.into_owned(), png));
+            }
+            None => *image = None,
+        }
+
+        Ok(payload)
+    }
+
+    fn write(&mut self, payload: &ClipboardPayload) -> Result<()> {
+        let clipboard = &mut self.0.lock().unwrap().clipboard;
+        let text = payload.as_text();
+
+        let result = if let Some(html) = payload.get(TEXT_HTML) {
+            let html = core::str::from_utf8(html).map_err(|_| Error::Decode)?;
+            clipboard.set_html(html, text)
+        } else if let Some(png) = payload.get(IMAGE_PNG) {
+            clipboard.set_image(decode_png(png)?)
+        } else if let Some(uris) = payload.get(URI_LIST) {
+            clipboard.set().file_list(&from_uri_list(uris))
+        } else if let Some(text) = text {
+            clipboard.set_text(text)
+        } else {
+            return Ok(());
+        };
+
+        result.map_err(|_| Error::Set)
+    }
+}
+
+/// `None` when the clipboard holds nothing in the asked format.
+fn content<T>(result: core::result::Result<T, arboard::Error>) -> Result<Option<T>> {
+    match result {
+        Ok(value) => Ok(Some(value)),
+        Err(arboard::Error::ContentNotAvailable | arboard::Error::ConversionFailure) => Ok(None),
+        Err(arboard::Error::ClipboardNotSupported) => Ok(None),
+        Err(_) => Err(Error::Get),
+    }
+}
+
+fn encode_png(image: &ImageData) -> Result<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, image.width as u32, image.height as u32);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    encoder
+        .write_header()
+        .and_then(|mut writer| writer.write_image_data(&image.bytes))
+        .map_err(|_| Error::Encode)?;
+
+    Ok(png)
+}
+
+/// RGBA pixels of a PNG, whatever its color type.
+fn decode_png(png: &[u8]) -> Result<ImageData<'static>> {
+    let mut decoder = png::Decoder::new(Cursor::new(png));
+    decoder.set_transformations(png::Transformations::normalize_to_color8());
+    let mut reader = decoder.read_info().map_err(|_| Error::Decode)?;
+    let mut pixels = vec![0; reader.output_buffer_size().ok_or(Error::Decode)?];
+    let frame = reader.next_frame(&mut pixels).map_err(|_| Error::Decode)?;
+    pixels.truncate(frame.buffer_size());
+
+    let rgba = match frame.color_type {
+        png::ColorType::Rgba => pixels,
+        png::ColorType::Rgb => pixels
+            .chunks(3)
+            .flat_map(|rgb| [rgb[0], rgb[1], rgb[2], u8::MAX])
+            .collect(),
+        png::ColorType::GrayscaleAlpha => pixels
+            .chunks(2)
+            .flat_map(|ga| [ga[0], ga[0], ga[0], ga[1]])
+            .collect(),
+        png::ColorType::Grayscale => pixels.iter().flat_map(|g| [*g, *g, *g, u8::MAX]).collect(),
+        png::ColorType::Indexed => return Err(Error::Decode),
+    };
+
+    Ok(ImageData {
+        width: frame.width as usize,
+        height: frame.height as usize,
+        bytes: Cow::Owned(rgba),
+    })
+}
+
+/// `file://` URIs, one per line.
+fn to_uri_list(files: &[PathBuf]) -> String {
+    files
+        .iter()
+        .map(|file| format!("file://{}\r\n", encode_path(&file.to_string_lossy())))
+        .collect()
+}
+
+/// Local files of a URI list, comments and other schemes are left out.
+fn from_uri_list(uris: &[u8]) -> Vec<PathBuf> {
+    String::from_utf8_lossy(uris)
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("file://"))
+        .map(|path| PathBuf::from(decode_path(path)))
+        .collect()
+}
+
+fn encode_path(path: &str) -> String {
+    path.bytes()
+        .map(|byte| match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'/' | b'-' | b'_' | b'.' | b'~' => {
+                (byte as char).to_string()
+            }
+            byte => format!("%{byte:02X}"),
+        })
+        .collect()
+}
+
+fn decode_path(path: &str) -> String {
+    let mut bytes = Vec::with_capacity(path.len());
+    let mut rest = path.as_bytes();
+    while let Some((&byte, tail)) = rest.split_first() {
+        let escaped = tail
+            .get(..2)
+            .and_then(|hex| core::str::from_utf8(hex).ok())
+            .and_then(|hex| u8::from_str_radix(hex, 16).ok());
+        match (byte, escaped) {
+            (b'%', Some(escaped)) => {
+                bytes.push(escaped);
+                rest = &tail[2..];
+            }
+            (byte, _) => {
+                bytes.push(byte);
+                rest = tail;
+            }
+        }
+    }
+
+    String::from_utf8_lossy(&bytes).into_owned()
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_png_roundtrip() -> Result<()> {
+        let image = ImageData {
+            width: 2,
+            height: 1,
+            bytes: Cow::Owned(vec![255, 0, 0, 255, 0, 0, 255, 128]),
+        };
+
+        let decoded = decode_png(&encode_png(&image)?)?;
+        assert_eq!((decoded.width, decoded.height), (2, 1));
+        assert_eq!(decoded.bytes, image.bytes);
+        assert!(decode_png(b"not a png").is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_uri_list() {
+        let files = vec![
+            PathBuf::from("/home/me/notes.txt"),
+            PathBuf::from("/tmp/with space/100%.png"),
+        ];
+
+        let uris = to_uri_list(&files);
+        assert_eq!(
+            uris,
+            "file:///home/me/notes.txt\r\nfile:///tmp/with%20space/100%25.png\r\n"
+        );
+        assert_eq!(from_uri_list(uris.as_bytes()), files);
+        assert_eq!(
+            from_uri_list(b"# comment\r\nhttps://example.com\r\nfile:///a"),
+            [PathBuf::from("/a")]
+        );
+    }
+}
+
+// endregion: --- Tests
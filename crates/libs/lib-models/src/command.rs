@@ -1,16 +1,216 @@
 use bincode::{Decode, Encode};
 
-use crate::{MouseButton, MouseScroll};
+use crate::{clipboard::ClipboardChunk, Key, ModifierState, MouseButton, MouseScroll};
 
 #[derive(Debug, Clone, Encode, Decode)]
 pub enum Command {
-    SetMouse { x: i32, y: i32 },
-    MoveMouse { x: i32, y: i32 },
-    KeyPressed(u32),
-    KeyReleased(u32),
+    /// Absolute position on a server display, in 1/[`NORMALIZED_ONE`] of
+    /// its size.
+    ///
+    /// [`NORMALIZED_ONE`]: crate::NORMALIZED_ONE
+    SetMouse {
+        /// Index in the displays reported by the server.
+        display: u32,
+        x: u32,
+        y: u32,
+    },
+    MoveMouse {
+        x: i32,
+        y: i32,
+    },
+    KeyPressed(Key),
+    KeyReleased(Key),
     InputText(String),
     MouseButtonPressed(MouseButton),
     MouseButtonReleased(MouseButton),
     MouseScroll(MouseScroll),
-    SetClipboard(String),
+    /// Part of the client clipboard content.
+    ClipboardChunk(ClipboardChunk),
+    /// MIME types the client clipboard accepts.
+    ClipboardFormats(Vec<String>),
+    /// The client got the keyboard focus with these keys already held.
+    FocusEntered {
+        pressed: Vec<Key>,
+    },
+    /// The client lost the keyboard focus, everything held must be released.
+    FocusLost,
+    Modifiers(ModifierState),
+    /// XKB keymap of the client in text format, the key codes that follow are
+    /// translated through it.
+    Keymap(String),
+    /// Pointer commands of one input frame, applied back to back by the
+    /// server. Built with [`CommandBatch`](crate::CommandBatch).
+    Batch(Vec<Command>),
+    /// Answered right away with [`Answer::Pong`](crate::Answer::Pong).
+    Ping {
+        id: u64,
+        /// Client clock in microseconds since the Unix epoch.
+        sent_at: u64,
+    },
+}
+
+impl Command {
+    pub fn kind(&self) -> CommandKind {
+        match self {
+            Self::SetMouse { .. } => CommandKind::SetMouse,
+            Self::MoveMouse { .. } => CommandKind::MoveMouse,
+            Self::KeyPressed(_) => CommandKind::KeyPressed,
+            Self::KeyReleased(_) => CommandKind::KeyReleased,
+            Self::InputText(_) => CommandKind::InputText,
+            Self::MouseButtonPressed(_) => CommandKind::MouseButtonPressed,
+            Self::MouseButtonReleased(_) => CommandKind::MouseButtonReleased,
+            Self::MouseScroll(_) => CommandKind::MouseScroll,
+            Self::ClipboardChunk(_) => CommandKind::ClipboardChunk,
+            Self::ClipboardFormats(_) => CommandKind::ClipboardFormats,
+            Self::FocusEntered { .. } => CommandKind::FocusEntered,
+            Self::FocusLost => CommandKind::FocusLost,
+            Self::Modifiers(_) => CommandKind::Modifiers,
+            Self::Keymap(_) => CommandKind::Keymap,
+            Self::Batch(_) => CommandKind::Batch,
+            Self::Ping { .. } => CommandKind::Ping,
+        }
+    }
+
+    /// Pointer and scroll commands, the only ones a [`Command::Batch`] carries.
+    pub fn is_batchable(&self) -> bool {
+        matches!(
+            self,
+            Self::SetMouse { .. }
+                | Self::MoveMouse { .. }
+                | Self::MouseButtonPressed(_)
+                | Self::MouseButtonReleased(_)
+                | Self::MouseScroll(_)
+        )
+    }
+
+    /// How the client sends the command to the server.
+    pub fn delivery(&self) -> Delivery {
+        match self {
+            // Pings go the way of the motion they measure.
+            Self::SetMouse { .. }
+            | Self::MoveMouse { .. }
+            | Self::MouseScroll(_)
+            | Self::Ping { .. } => Delivery::Datagram,
+            Self::KeyPressed(_)
+            | Self::KeyReleased(_)
+            | Self::InputText(_)
+            | Self::MouseButtonPressed(_)
+            | Self::MouseButtonReleased(_)
+            | Self::ClipboardChunk(_)
+            | Self::ClipboardFormats(_)
+            | Self::FocusEntered { .. }
+            | Self::FocusLost
+            | Self::Modifiers(_)
+            | Self::Keymap(_) => Delivery::Reliable,
+            // One command that must not be lost makes the whole batch reliable.
+            Self::Batch(commands) => match commands
+                .iter()
+                .any(|command| command.delivery() == Delivery::Reliable)
+            {
+                true => Delivery::Reliable,
+                false => Delivery::Datagram,
+            },
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Delivery {
+    /// Unreliable QUIC datagram, a lost command is superseded by the next one.
+    Datagram,
+    /// Reliable ordered stream, a lost command would leave the server state
+    /// wrong (e.g. a key stuck pressed).
+    Reliable,
+}
+
+/// A command with its place in the reliable sequence.
+#[derive(Debug, Clone, Encode, Decode)]
+pub struct SequencedCommand {
+    /// Number of a reliable command, starting at 1. Datagrams carry the number
+    /// of the last reliable command sent before them, so the server doesn't
+    /// apply them ahead of it.
+    pub seq: u64,
+    /// Number of the command among all those sent on both channels, starting
+    /// at 1, for loss and reorder stats. 0 when not counted.
+    pub id: u64,
+    /// When the client sent the command, in microseconds of the server clock
+    /// as the client estimates it from pings. 0 before the first pong.
+    pub sent_at: u64,
+    pub command: Command,
+}
+
+impl SequencedCommand {
+    pub fn new(seq: u64, command: Command) -> Self {
+        Self {
+            seq,
+            id: 0,
+            sent_at: 0,
+            command,
+        }
+    }
+
+    pub fn with_stamp(mut self, id: u64, sent_at: u64) -> Self {
+        self.id = id;
+        self.sent_at = sent_at;
+        self
+    }
+}
+
+/// [`Command`] variant without its payload.
+///
+/// The discriminant is the number of the kind in the hello capabilities, a
+/// new kind takes the next one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
+pub enum CommandKind {
+    SetMouse = 0,
+    MoveMouse = 1,
+    KeyPressed = 2,
+    KeyReleased = 3,
+    InputText = 4,
+    MouseButtonPressed = 5,
+    MouseButtonReleased = 6,
+    MouseScroll = 7,
+    ClipboardChunk = 8,
+    ClipboardFormats = 9,
+    FocusEntered = 10,
+    FocusLost = 11,
+    Modifiers = 12,
+    Keymap = 13,
+    Batch = 14,
+    Ping = 15,
+}
+
+impl CommandKind {
+    pub const ALL: [Self; 16] = [
+        Self::SetMouse,
+        Self::MoveMouse,
+        Self::KeyPressed,
+        Self::KeyReleased,
+        Self::InputText,
+        Self::MouseButtonPressed,
+        Self::MouseButtonReleased,
+        Self::MouseScroll,
+        Self::ClipboardChunk,
+        Self::ClipboardFormats,
+        Self::FocusEntered,
+        Self::FocusLost,
+        Self::Modifiers,
+        Self::Keymap,
+        Self::Batch,
+        Self::Ping,
+    ];
+
+    /// Commands only sent when both peers have a clipboard.
+    pub fn is_clipboard(self) -> bool {
+        matches!(self, Self::ClipboardChunk | Self::ClipboardFormats)
+    }
+
+    pub const fn id(self) -> u16 {
+        self as u16
+    }
+
+    /// `None` for a kind of a newer protocol.
+    pub fn from_id(id: u16) -> Option<Self> {
+        Self::ALL.into_iter().find(|kind| kind.id() == id)
+    }
 }
@@ -1,12 +1,47 @@
-#[derive(Debug, Clone)]
+use bincode::{Decode, Encode};
+
+/// A screen of the server desktop.
+///
+/// The position is in the desktop coordinates the input backends use, the
+/// size in physical pixels. The logical size is the physical size divided by
+/// the scale factor.
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
 pub struct DisplayParams {
+    x: i32,
+    y: i32,
     width: u32,
     height: u32,
+    scale: f64,
 }
 
 impl DisplayParams {
     pub fn new(width: u32, height: u32) -> Self {
-        Self { width, height }
+        Self {
+            x: 0,
+            y: 0,
+            width,
+            height,
+            scale: 1.0,
+        }
+    }
+
+    pub fn with_position(mut self, x: i32, y: i32) -> Self {
+        self.x = x;
+        self.y = y;
+        self
+    }
+
+    pub fn with_scale(mut self, scale: f64) -> Self {
+        self.scale = scale;
+        self
+    }
+
+    pub fn x(&self) -> i32 {
+        self.x
+    }
+
+    pub fn y(&self) -> i32 {
+        self.y
     }
 
     pub fn width(&self) -> u32 {
@@ -16,4 +51,168 @@ impl DisplayParams {
     pub fn height(&self) -> u32 {
         self.height
     }
+
+    pub fn scale(&self) -> f64 {
+        self.scale
+    }
+
+    pub fn logical_width(&self) -> f64 {
+        self.width as f64 / self.scale
+    }
+
+    pub fn logical_height(&self) -> f64 {
+        self.height as f64 / self.scale
+    }
+
+    /// Desktop coordinates of a position normalised to the display. They
+    /// keep the fractions of a logical pixel, a physical pixel of a HiDPI
+    /// display is one.
+    pub fn to_desktop(&self, x: u32, y: u32) -> (f64, f64) {
+        let axis = |start: i32, size: u32, value: u32| {
+            let offset = value.min(NORMALIZED_ONE) as f64 * size as f64 / NORMALIZED_ONE as f64;
+            // The last pixel is the end of the display, not the one after it.
+            start as f64 + offset.min(size.saturating_sub(1) as f64) / self.scale
+        };
+
+        (axis(self.x, self.width, x), axis(self.y, self.height, y))
+    }
+}
+
+/// Fixed-point value of the far edge of a display in normalised coordinates.
+pub const NORMALIZED_ONE: u32 = 1 << 16;
+
+/// Normalises a position in `0..size` to `0..=NORMALIZED_ONE`.
+pub fn normalize(value: f64, size: f64) -> u32 {
+    if size <= 0.0 {
+        return 0;
+    }
+
+    (value / size * NORMALIZED_ONE as f64)
+        .round()
+        .clamp(0.0, NORMALIZED_ONE as f64) as u32
+}
+
+/// Reads `WIDTHxHEIGHT[@SCALE][+X+Y]`, e.g. `2560x1440@1.5+1920+0`.
+impl core::str::FromStr for DisplayParams {
+    type Err = DisplayFormatError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let wrong = || DisplayFormatError(s.to_string());
+        let s = s.trim();
+
+        let (size, position) = match s.split_once('+') {
+            Some((size, position)) => (size, Some(position)),
+            None => (s, None),
+        };
+        let (size, scale): (_, f64) = match size.split_once('@') {
+            Some((size, scale)) => (size, scale.parse().map_err(|_| wrong())?),
+            None => (size, 1.0),
+        };
+        let (width, height) = size.split_once('x').ok_or_else(wrong)?;
+        let (width, height) = (
+            width.parse().map_err(|_| wrong())?,
+            height.parse().map_err(|_| wrong())?,
+        );
+        if width == 0 || height == 0 || !scale.is_finite() || scale <= 0.0 {
+            return Err(wrong());
+        }
+
+        let (x, y) = match position {
+            Some(position) => {
+                let (x, y) = position.split_once('+').ok_or_else(wrong)?;
+                (
+                    x.parse().map_err(|_| wrong())?,
+                    y.parse().map_err(|_| wrong())?,
+                )
+            }
+            None => (0, 0),
+        };
+
+        Ok(Self::new(width, height)
+            .with_position(x, y)
+            .with_scale(scale))
+    }
+}
+
+/// The text is not `WIDTHxHEIGHT[@SCALE][+X+Y]`.
+#[derive(Debug)]
+pub struct DisplayFormatError(pub String);
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for DisplayFormatError {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for DisplayFormatError {}
+// endregion: --- Error Boilerplate
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let display: DisplayParams = "2560x1440@1.5+1920+0".parse()?;
+        assert_eq!(
+            display,
+            DisplayParams::new(2560, 1440)
+                .with_position(1920, 0)
+                .with_scale(1.5)
+        );
+        assert_eq!(
+            "1920x1080".parse::<DisplayParams>()?,
+            DisplayParams::new(1920, 1080)
+        );
+
+        assert!("1920".parse::<DisplayParams>().is_err());
+        assert!("1920x1080@0".parse::<DisplayParams>().is_err());
+        assert!("1920x1080+10".parse::<DisplayParams>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_to_desktop() -> Result<()> {
+        // 1920x1080 logical pixels right of the first screen.
+        let display = DisplayParams::new(3840, 2160)
+            .with_position(1920, 0)
+            .with_scale(2.0);
+
+        assert_eq!(display.to_desktop(0, 0), (1920.0, 0.0));
+        assert_eq!(
+            display.to_desktop(NORMALIZED_ONE / 2, NORMALIZED_ONE / 4),
+            (2880.0, 270.0)
+        );
+        assert_eq!(
+            display.to_desktop(NORMALIZED_ONE, u32::MAX),
+            (3839.5, 1079.5)
+        );
+
+        // A physical pixel is half a logical one.
+        let pixel = normalize(1.0, 3840.0);
+        let (x, _) = display.to_desktop(pixel, 0);
+        assert!((x - 1920.5).abs() < 0.01, "{x}");
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_normalize() -> Result<()> {
+        // Sub-pixel positions survive.
+        assert_eq!(normalize(0.5, 1.0), NORMALIZED_ONE / 2);
+        assert_eq!(normalize(960.25, 1920.0), 32777);
+        assert_eq!(normalize(-3.0, 1920.0), 0);
+        assert_eq!(normalize(2000.0, 1920.0), NORMALIZED_ONE);
+        assert_eq!(normalize(10.0, 0.0), 0);
+
+        Ok(())
+    }
 }
+
+// endregion: --- Tests
@@ -0,0 +1,225 @@
+//! Hello messages exchanged on the control stream before any command.
+//!
+//! The client opens a bidirectional stream and writes its [`ProtocolVersion`]
+//! then a [`ClientHello`]. The server writes its own version then a
+//! [`HandshakeReply`]. The version goes in a frame of its own so a peer can
+//! read it even when it can't decode the hello of a newer protocol.
+//!
+//! Each side sends the newest version it speaks, the server answers with the
+//! newest one both speak. Capabilities go as [`CommandKind`] numbers, the
+//! ones a peer doesn't know are skipped.
+
+use crate::{CommandKind, DisplayParams};
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
+use core::ops::RangeInclusive;
+
+/// Newest version of the messages on the wire, bumped on any incompatible
+/// change.
+///
+/// 2: sequenced commands carry an id and a send time, pings, batches, keys
+/// as 32 bit extended usages and framed datagrams.
+pub const PROTOCOL_VERSION: u32 = 2;
+
+/// Oldest version still spoken, version 1 is wire incompatible.
+pub const MIN_PROTOCOL_VERSION: u32 = 2;
+
+pub const SUPPORTED_VERSIONS: RangeInclusive<u32> = MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION;
+
+/// Application close code of a connection from a device the server doesn't
+/// trust, sent before anything is read from it.
+pub const UNTRUSTED_DEVICE: u32 = 2;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct ProtocolVersion(pub u32);
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ClientHello {
+    /// Name and version of the client application.
+    pub name: String,
+    /// Commands the client may send.
+    pub commands: Vec<CommandKind>,
+    /// MIME types the client clipboard accepts, empty without clipboard.
+    pub clipboard_formats: Vec<String>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ServerHello {
+    /// Name and version of the server application.
+    pub name: String,
+    /// Commands the server applies, others are answered with
+    /// [`UnsupportedCommand`](crate::InputErrorKind::UnsupportedCommand).
+    pub commands: Vec<CommandKind>,
+    pub displays: Vec<DisplayParams>,
+    /// MIME types the server clipboard accepts, empty without clipboard.
+    pub clipboard_formats: Vec<String>,
+}
+
+impl ServerHello {
+    pub fn supports(&self, command: CommandKind) -> bool {
+        self.commands.contains(&command)
+    }
+}
+
+impl Encode for ClientHello {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.name.encode(encoder)?;
+        encode_commands(&self.commands, encoder)?;
+        self.clipboard_formats.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for ClientHello {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            name: String::decode(decoder)?,
+            commands: decode_commands(decoder)?,
+            clipboard_formats: Vec::decode(decoder)?,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(ClientHello);
+
+impl Encode for ServerHello {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.name.encode(encoder)?;
+        encode_commands(&self.commands, encoder)?;
+        self.displays.encode(encoder)?;
+        self.clipboard_formats.encode(encoder)
+    }
+}
+
+impl<Context> Decode<Context> for ServerHello {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self {
+            name: String::decode(decoder)?,
+            commands: decode_commands(decoder)?,
+            displays: Vec::decode(decoder)?,
+            clipboard_formats: Vec::decode(decoder)?,
+        })
+    }
+}
+
+bincode::impl_borrow_decode!(ServerHello);
+
+fn encode_commands<E: Encoder>(
+    commands: &[CommandKind],
+    encoder: &mut E,
+) -> Result<(), EncodeError> {
+    let ids: Vec<u16> = commands.iter().map(|command| command.id()).collect();
+    ids.encode(encoder)
+}
+
+/// Known commands of a capability list.
+fn decode_commands<Context, D: Decoder<Context = Context>>(
+    decoder: &mut D,
+) -> Result<Vec<CommandKind>, DecodeError> {
+    let ids: Vec<u16> = Vec::decode(decoder)?;
+    Ok(ids.into_iter().filter_map(CommandKind::from_id).collect())
+}
+
+#[derive(Debug, Clone, PartialEq, Encode, Decode)]
+pub enum HandshakeReply {
+    Accepted(ServerHello),
+    Rejected(HandshakeError),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Encode, Decode)]
+pub enum HandshakeError {
+    VersionMismatch {
+        client: u32,
+        server: u32,
+    },
+    /// The hello couldn't be decoded.
+    Malformed,
+}
+
+/// Newest version spoken by a client sending `client` and by us, when there
+/// is one.
+pub fn negotiate_version(client: u32) -> Result<u32, HandshakeError> {
+    let version = client.min(PROTOCOL_VERSION);
+    match SUPPORTED_VERSIONS.contains(&version) {
+        true => Ok(version),
+        false => Err(HandshakeError::VersionMismatch {
+            client,
+            server: PROTOCOL_VERSION,
+        }),
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_negotiate_version() -> Result<()> {
+        assert_eq!(negotiate_version(PROTOCOL_VERSION), Ok(PROTOCOL_VERSION));
+        // A newer client speaks ours.
+        assert_eq!(
+            negotiate_version(PROTOCOL_VERSION + 3),
+            Ok(PROTOCOL_VERSION)
+        );
+        assert_eq!(
+            negotiate_version(MIN_PROTOCOL_VERSION - 1),
+            Err(HandshakeError::VersionMismatch {
+                client: MIN_PROTOCOL_VERSION - 1,
+                server: PROTOCOL_VERSION
+            })
+        );
+        // Version 1 frames don't decode as ours.
+        assert!(negotiate_version(1).is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_commands_skipped() -> Result<()> {
+        // Hello of a newer client, with a command kind we don't know.
+        let mut encoded = lib_codec::encode(&"air_client 9.0.0".to_string())?;
+        encoded.extend(lib_codec::encode(&vec![
+            CommandKind::SetMouse.id(),
+            999u16,
+            CommandKind::Ping.id(),
+        ])?);
+        encoded.extend(lib_codec::encode(&vec!["text/plain".to_string()])?);
+
+        let hello: ClientHello = lib_codec::decode(&encoded)?;
+        assert_eq!(hello.commands, [CommandKind::SetMouse, CommandKind::Ping]);
+        assert_eq!(hello.clipboard_formats, ["text/plain"]);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_reply_roundtrip() -> Result<()> {
+        let fx_reply = HandshakeReply::Accepted(ServerHello {
+            name: "air_server 0.1.0".to_string(),
+            commands: vec![CommandKind::SetMouse, CommandKind::KeyPressed],
+            displays: vec![DisplayParams::new(1920, 1080)],
+            clipboard_formats: Vec::new(),
+        });
+
+        let encoded = lib_codec::encode(&fx_reply)?;
+        let reply: HandshakeReply = lib_codec::decode(&encoded)?;
+        assert_eq!(reply, fx_reply);
+
+        let HandshakeReply::Accepted(hello) = reply else {
+            return Err("not accepted".into());
+        };
+        assert!(hello.supports(CommandKind::SetMouse));
+        assert!(!hello.supports(CommandKind::ClipboardChunk));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
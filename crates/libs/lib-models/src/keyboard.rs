@@ -1,391 +1,495 @@
-#[derive(Debug)]
-#[repr(u8)]
-#[allow(non_camel_case_types)]
-pub enum Letter {
-    ///	"A" key.
-    A = 0x41,
-    ///	"B" key.
-    B = 0x42,
-    ///	"C" key.
-    C = 0x43,
-    ///	"D" key.
-    D = 0x44,
-    ///	"E" key.
-    E = 0x45,
-    ///	"F" key.
-    F = 0x46,
-    ///	"G" key.
-    G = 0x47,
-    ///	"H" key.
-    H = 0x48,
-    ///	"I" key.
-    I = 0x49,
-    ///	"J" key.
-    J = 0x4A,
-    ///	"K" key.
-    K = 0x4B,
-    ///	"L" key.
-    L = 0x4C,
-    ///	"M" key.
-    M = 0x4D,
-    ///	"N" key.
-    N = 0x4E,
-    ///	"O" key.
-    O = 0x4F,
-    ///	"P" key.
-    P = 0x50,
-    ///	"Q" key.
-    Q = 0x51,
-    ///	"R" key.
-    R = 0x52,
-    ///	"S" key.
-    S = 0x53,
-    ///	"T" key.
-    T = 0x54,
-    ///	"U" key.
-    U = 0x55,
-    ///	"V" key.
-    V = 0x56,
-    ///	"W" key.
-    W = 0x57,
-    ///	"X" key.
-    X = 0x58,
-    ///	"Y" key.
-    Y = 0x59,
-    ///	"Z" key.
-    Z = 0x5A,
+//! Keyboard keys and their codes on each platform.
+//!
+//! A [`Key`] is the USB HID usage of a physical key, on the keyboard page
+//! (0x07) or the consumer page (0x0c) for media and application keys. The
+//! table below gives the code of the same key for Linux evdev, Windows virtual
+//! keys, macOS virtual keycodes and the X11 keysym it types on a US layout.
+//! `-` marks a key the platform has no code for, or shares with another key.
+
+use bincode::{
+    de::Decoder,
+    enc::Encoder,
+    error::{DecodeError, EncodeError},
+    Decode, Encode,
+};
+
+struct KeyCodes {
+    key: Key,
+    name: &'static str,
+    /// Page in the high 16 bits, usage id in the low ones.
+    usage: u32,
+    evdev: Option<u32>,
+    vk: Option<u16>,
+    mac: Option<u16>,
+    keysym: Option<u32>,
+}
+
+macro_rules! code {
+    (-) => {
+        None
+    };
+    ($code:literal) => {
+        Some($code)
+    };
 }
 
-impl Letter {
-    pub fn from_u8(value: u8) -> Option<Letter> {
-        match value {
-            0x41 => Some(Letter::A),
-            0x42 => Some(Letter::B),
-            0x43 => Some(Letter::C),
-            0x44 => Some(Letter::D),
-            0x45 => Some(Letter::E),
-            0x46 => Some(Letter::F),
-            0x47 => Some(Letter::G),
-            0x48 => Some(Letter::H),
-            0x49 => Some(Letter::I),
-            0x4A => Some(Letter::J),
-            0x4B => Some(Letter::K),
-            0x4C => Some(Letter::L),
-            0x4D => Some(Letter::M),
-            0x4E => Some(Letter::N),
-            0x4F => Some(Letter::O),
-            0x50 => Some(Letter::P),
-            0x51 => Some(Letter::Q),
-            0x52 => Some(Letter::R),
-            0x53 => Some(Letter::S),
-            0x54 => Some(Letter::T),
-            0x55 => Some(Letter::U),
-            0x56 => Some(Letter::V),
-            0x57 => Some(Letter::W),
-            0x58 => Some(Letter::X),
-            0x59 => Some(Letter::Y),
-            0x5A => Some(Letter::Z),
-            _ => None, // Возвращаем None, если значение не соответствует ни одному из вариантов
+macro_rules! keys {
+    ($($page:literal => {
+        $($(#[doc = $doc:literal])* $name:ident = $usage:literal, $evdev:tt, $vk:tt, $mac:tt, $keysym:tt;)*
+    })*) => {
+        #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+        pub enum Key {
+            $($($(#[doc = $doc])* $name,)*)*
+            /// Usage without a name here, page in the high 16 bits.
+            Unknown(u32),
         }
-    }
+
+        const KEYS: &[KeyCodes] = &[
+            $($(KeyCodes {
+                key: Key::$name,
+                name: stringify!($name),
+                usage: $page << 16 | $usage,
+                evdev: code!($evdev),
+                vk: code!($vk),
+                mac: code!($mac),
+                keysym: code!($keysym),
+            },)*)*
+        ];
+
+        impl Key {
+            /// HID usage of the key, page in the high 16 bits and usage id in
+            /// the low ones.
+            pub const fn usage(self) -> u32 {
+                match self {
+                    $($(Self::$name => $page << 16 | $usage,)*)*
+                    Self::Unknown(usage) => usage,
+                }
+            }
+        }
+    };
 }
 
-#[derive(Debug)]
-#[repr(u8)]
-#[allow(non_camel_case_types)]
-pub enum Number {
-    ///	"0" key in standard key location.
-    Zero = 0x30,
-    ///	"1" key in standard key location.
-    One = 0x31,
-    ///	"2" key in standard key location.
-    Two = 0x32,
-    ///	"3" key in standard key location.
-    Three = 0x33,
-    ///	"4" key in standard key location.
-    Four = 0x34,
-    ///	"5" key in standard key location.
-    Five = 0x35,
-    ///	"6" key in standard key location.
-    Six = 0x36,
-    ///	"7" key in standard key location.
-    Seven = 0x37,
-    ///	"8" key in standard key location.
-    Eight = 0x38,
-    ///	"9" key in standard key location.
-    Nine = 0x39,
+keys! {
+    // Keyboard page.
+    0x07 => {
+    //                     usage evdev VK    mac   keysym
+    A                    = 0x04, 30,   0x41, 0x00, 0x61;
+    B                    = 0x05, 48,   0x42, 0x0b, 0x62;
+    C                    = 0x06, 46,   0x43, 0x08, 0x63;
+    D                    = 0x07, 32,   0x44, 0x02, 0x64;
+    E                    = 0x08, 18,   0x45, 0x0e, 0x65;
+    F                    = 0x09, 33,   0x46, 0x03, 0x66;
+    G                    = 0x0a, 34,   0x47, 0x05, 0x67;
+    H                    = 0x0b, 35,   0x48, 0x04, 0x68;
+    I                    = 0x0c, 23,   0x49, 0x22, 0x69;
+    J                    = 0x0d, 36,   0x4a, 0x26, 0x6a;
+    K                    = 0x0e, 37,   0x4b, 0x28, 0x6b;
+    L                    = 0x0f, 38,   0x4c, 0x25, 0x6c;
+    M                    = 0x10, 50,   0x4d, 0x2e, 0x6d;
+    N                    = 0x11, 49,   0x4e, 0x2d, 0x6e;
+    O                    = 0x12, 24,   0x4f, 0x1f, 0x6f;
+    P                    = 0x13, 25,   0x50, 0x23, 0x70;
+    Q                    = 0x14, 16,   0x51, 0x0c, 0x71;
+    R                    = 0x15, 19,   0x52, 0x0f, 0x72;
+    S                    = 0x16, 31,   0x53, 0x01, 0x73;
+    T                    = 0x17, 20,   0x54, 0x11, 0x74;
+    U                    = 0x18, 22,   0x55, 0x20, 0x75;
+    V                    = 0x19, 47,   0x56, 0x09, 0x76;
+    W                    = 0x1a, 17,   0x57, 0x0d, 0x77;
+    X                    = 0x1b, 45,   0x58, 0x07, 0x78;
+    Y                    = 0x1c, 21,   0x59, 0x10, 0x79;
+    Z                    = 0x1d, 44,   0x5a, 0x06, 0x7a;
+    Digit1               = 0x1e, 2,    0x31, 0x12, 0x31;
+    Digit2               = 0x1f, 3,    0x32, 0x13, 0x32;
+    Digit3               = 0x20, 4,    0x33, 0x14, 0x33;
+    Digit4               = 0x21, 5,    0x34, 0x15, 0x34;
+    Digit5               = 0x22, 6,    0x35, 0x17, 0x35;
+    Digit6               = 0x23, 7,    0x36, 0x16, 0x36;
+    Digit7               = 0x24, 8,    0x37, 0x1a, 0x37;
+    Digit8               = 0x25, 9,    0x38, 0x1c, 0x38;
+    Digit9               = 0x26, 10,   0x39, 0x19, 0x39;
+    Digit0               = 0x27, 11,   0x30, 0x1d, 0x30;
+    /// Return key of the main block.
+    Enter                = 0x28, 28,   0x0d, 0x24, 0xff0d;
+    Escape               = 0x29, 1,    0x1b, 0x35, 0xff1b;
+    Backspace            = 0x2a, 14,   0x08, 0x33, 0xff08;
+    Tab                  = 0x2b, 15,   0x09, 0x30, 0xff09;
+    Space                = 0x2c, 57,   0x20, 0x31, 0x20;
+    Minus                = 0x2d, 12,   0xbd, 0x1b, 0x2d;
+    Equal                = 0x2e, 13,   0xbb, 0x18, 0x3d;
+    LeftBracket          = 0x2f, 26,   0xdb, 0x21, 0x5b;
+    RightBracket         = 0x30, 27,   0xdd, 0x1e, 0x5d;
+    Backslash            = 0x31, 43,   0xdc, 0x2a, 0x5c;
+    /// Key next to Enter on ISO keyboards, evdev reports it as [`Key::Backslash`].
+    NonUsHash            = 0x32, -,    -,    -,    -;
+    Semicolon            = 0x33, 39,   0xba, 0x29, 0x3b;
+    Quote                = 0x34, 40,   0xde, 0x27, 0x27;
+    Grave                = 0x35, 41,   0xc0, 0x32, 0x60;
+    Comma                = 0x36, 51,   0xbc, 0x2b, 0x2c;
+    Period               = 0x37, 52,   0xbe, 0x2f, 0x2e;
+    Slash                = 0x38, 53,   0xbf, 0x2c, 0x2f;
+    CapsLock             = 0x39, 58,   0x14, 0x39, 0xffe5;
+    F1                   = 0x3a, 59,   0x70, 0x7a, 0xffbe;
+    F2                   = 0x3b, 60,   0x71, 0x78, 0xffbf;
+    F3                   = 0x3c, 61,   0x72, 0x63, 0xffc0;
+    F4                   = 0x3d, 62,   0x73, 0x76, 0xffc1;
+    F5                   = 0x3e, 63,   0x74, 0x60, 0xffc2;
+    F6                   = 0x3f, 64,   0x75, 0x61, 0xffc3;
+    F7                   = 0x40, 65,   0x76, 0x62, 0xffc4;
+    F8                   = 0x41, 66,   0x77, 0x64, 0xffc5;
+    F9                   = 0x42, 67,   0x78, 0x65, 0xffc6;
+    F10                  = 0x43, 68,   0x79, 0x6d, 0xffc7;
+    F11                  = 0x44, 87,   0x7a, 0x67, 0xffc8;
+    F12                  = 0x45, 88,   0x7b, 0x6f, 0xffc9;
+    PrintScreen          = 0x46, 99,   0x2c, -,    0xff61;
+    ScrollLock           = 0x47, 70,   0x91, -,    0xff14;
+    Pause                = 0x48, 119,  0x13, -,    0xff13;
+    Insert               = 0x49, 110,  0x2d, -,    0xff63;
+    Home                 = 0x4a, 102,  0x24, 0x73, 0xff50;
+    PageUp               = 0x4b, 104,  0x21, 0x74, 0xff55;
+    /// Forward delete.
+    Delete               = 0x4c, 111,  0x2e, 0x75, 0xffff;
+    End                  = 0x4d, 107,  0x23, 0x77, 0xff57;
+    PageDown             = 0x4e, 109,  0x22, 0x79, 0xff56;
+    Right                = 0x4f, 106,  0x27, 0x7c, 0xff53;
+    Left                 = 0x50, 105,  0x25, 0x7b, 0xff51;
+    Down                 = 0x51, 108,  0x28, 0x7d, 0xff54;
+    Up                   = 0x52, 103,  0x26, 0x7e, 0xff52;
+    /// Num Lock, Clear on Mac keyboards.
+    NumLock              = 0x53, 69,   0x90, 0x47, 0xff7f;
+    KeypadDivide         = 0x54, 98,   0x6f, 0x4b, 0xffaf;
+    KeypadMultiply       = 0x55, 55,   0x6a, 0x43, 0xffaa;
+    KeypadSubtract       = 0x56, 74,   0x6d, 0x4e, 0xffad;
+    KeypadAdd            = 0x57, 78,   0x6b, 0x45, 0xffab;
+    /// Windows reports it as [`Key::Enter`].
+    KeypadEnter          = 0x58, 96,   -,    0x4c, 0xff8d;
+    Keypad1              = 0x59, 79,   0x61, 0x53, 0xffb1;
+    Keypad2              = 0x5a, 80,   0x62, 0x54, 0xffb2;
+    Keypad3              = 0x5b, 81,   0x63, 0x55, 0xffb3;
+    Keypad4              = 0x5c, 75,   0x64, 0x56, 0xffb4;
+    Keypad5              = 0x5d, 76,   0x65, 0x57, 0xffb5;
+    Keypad6              = 0x5e, 77,   0x66, 0x58, 0xffb6;
+    Keypad7              = 0x5f, 71,   0x67, 0x59, 0xffb7;
+    Keypad8              = 0x60, 72,   0x68, 0x5b, 0xffb8;
+    Keypad9              = 0x61, 73,   0x69, 0x5c, 0xffb9;
+    Keypad0              = 0x62, 82,   0x60, 0x52, 0xffb0;
+    KeypadDecimal        = 0x63, 83,   0x6e, 0x41, 0xffae;
+    /// Extra key next to left shift on ISO keyboards.
+    NonUsBackslash       = 0x64, 86,   0xe2, 0x0a, 0x3c;
+    /// Context menu key.
+    Application          = 0x65, 127,  0x5d, 0x6e, 0xff67;
+    Power                = 0x66, 116,  -,    -,    0x1008ff2a;
+    KeypadEqual          = 0x67, 117,  -,    0x51, 0xffbd;
+    F13                  = 0x68, 183,  0x7c, 0x69, 0xffca;
+    F14                  = 0x69, 184,  0x7d, 0x6b, 0xffcb;
+    F15                  = 0x6a, 185,  0x7e, 0x71, 0xffcc;
+    F16                  = 0x6b, 186,  0x7f, 0x6a, 0xffcd;
+    F17                  = 0x6c, 187,  0x80, 0x40, 0xffce;
+    F18                  = 0x6d, 188,  0x81, 0x4f, 0xffcf;
+    F19                  = 0x6e, 189,  0x82, 0x50, 0xffd0;
+    F20                  = 0x6f, 190,  0x83, 0x5a, 0xffd1;
+    F21                  = 0x70, 191,  0x84, -,    0xffd2;
+    F22                  = 0x71, 192,  0x85, -,    0xffd3;
+    F23                  = 0x72, 193,  0x86, -,    0xffd4;
+    F24                  = 0x73, 194,  0x87, -,    0xffd5;
+    Execute              = 0x74, 134,  0x2b, -,    0xff62;
+    /// Help, Insert on Mac keyboards.
+    Help                 = 0x75, 138,  0x2f, 0x72, 0xff6a;
+    Menu                 = 0x76, 130,  -,    -,    -;
+    Select               = 0x77, 132,  0x29, -,    0xff60;
+    Stop                 = 0x78, 128,  -,    -,    0x1008ff28;
+    Again                = 0x79, 129,  -,    -,    0xff66;
+    Undo                 = 0x7a, 131,  -,    -,    0xff65;
+    Cut                  = 0x7b, 137,  -,    -,    0x1008ff58;
+    Copy                 = 0x7c, 133,  -,    -,    0x1008ff57;
+    Paste                = 0x7d, 135,  -,    -,    0x1008ff6d;
+    Find                 = 0x7e, 136,  -,    -,    0xff68;
+    Mute                 = 0x7f, 113,  0xad, 0x4a, 0x1008ff12;
+    VolumeUp             = 0x80, 115,  0xaf, 0x48, 0x1008ff13;
+    VolumeDown           = 0x81, 114,  0xae, 0x49, 0x1008ff11;
+    LockingCapsLock      = 0x82, -,    -,    -,    -;
+    LockingNumLock       = 0x83, -,    -,    -,    -;
+    LockingScrollLock    = 0x84, -,    -,    -,    -;
+    KeypadComma          = 0x85, 121,  0x6c, 0x5f, 0xffac;
+    KeypadEqualAs400     = 0x86, -,    -,    -,    -;
+    /// Ro on Japanese keyboards.
+    International1       = 0x87, 89,   0xc1, 0x5e, -;
+    /// Katakana/Hiragana on Japanese keyboards.
+    International2       = 0x88, 93,   0x15, -,    0xff27;
+    /// Yen on Japanese keyboards.
+    International3       = 0x89, 124,  -,    0x5d, 0xa5;
+    /// Henkan on Japanese keyboards.
+    International4       = 0x8a, 92,   0x1c, -,    0xff23;
+    /// Muhenkan on Japanese keyboards.
+    International5       = 0x8b, 94,   0x1d, -,    0xff22;
+    International6       = 0x8c, 95,   -,    -,    -;
+    International7       = 0x8d, -,    -,    -,    -;
+    International8       = 0x8e, -,    -,    -,    -;
+    International9       = 0x8f, -,    -,    -,    -;
+    /// Hangul on Korean keyboards, Kana on Mac JIS keyboards.
+    Lang1                = 0x90, 122,  -,    0x68, 0xff31;
+    /// Hanja on Korean keyboards, Eisu on Mac JIS keyboards.
+    Lang2                = 0x91, 123,  0x19, 0x66, 0xff34;
+    Lang3                = 0x92, 90,   -,    -,    0xff26;
+    Lang4                = 0x93, 91,   -,    -,    0xff25;
+    Lang5                = 0x94, 85,   -,    -,    0xff2a;
+    Lang6                = 0x95, -,    -,    -,    -;
+    Lang7                = 0x96, -,    -,    -,    -;
+    Lang8                = 0x97, -,    -,    -,    -;
+    Lang9                = 0x98, -,    -,    -,    -;
+    AlternateErase       = 0x99, -,    -,    -,    -;
+    SysReq               = 0x9a, -,    0xf6, -,    0xff15;
+    Cancel               = 0x9b, -,    0x03, -,    0xff69;
+    Clear                = 0x9c, -,    0x0c, -,    0xff0b;
+    Prior                = 0x9d, -,    -,    -,    -;
+    Return               = 0x9e, -,    -,    -,    -;
+    Separator            = 0x9f, -,    -,    -,    -;
+    Out                  = 0xa0, -,    -,    -,    -;
+    Oper                 = 0xa1, -,    -,    -,    -;
+    ClearAgain           = 0xa2, -,    -,    -,    -;
+    CrSel                = 0xa3, -,    0xf7, -,    -;
+    ExSel                = 0xa4, -,    0xf8, -,    -;
+    Keypad00             = 0xb0, -,    -,    -,    -;
+    Keypad000            = 0xb1, -,    -,    -,    -;
+    ThousandsSeparator   = 0xb2, -,    -,    -,    -;
+    DecimalSeparator     = 0xb3, -,    -,    -,    -;
+    CurrencyUnit         = 0xb4, -,    -,    -,    -;
+    CurrencySubunit      = 0xb5, -,    -,    -,    -;
+    KeypadLeftParen      = 0xb6, 179,  -,    -,    -;
+    KeypadRightParen     = 0xb7, 180,  -,    -,    -;
+    KeypadLeftBrace      = 0xb8, -,    -,    -,    -;
+    KeypadRightBrace     = 0xb9, -,    -,    -,    -;
+    KeypadTab            = 0xba, -,    -,    -,    -;
+    KeypadBackspace      = 0xbb, -,    -,    -,    -;
+    KeypadA              = 0xbc, -,    -,    -,    -;
+    KeypadB              = 0xbd, -,    -,    -,    -;
+    KeypadC              = 0xbe, -,    -,    -,    -;
+    KeypadD              = 0xbf, -,    -,    -,    -;
+    KeypadE              = 0xc0, -,    -,    -,    -;
+    KeypadF              = 0xc1, -,    -,    -,    -;
+    KeypadXor            = 0xc2, -,    -,    -,    -;
+    KeypadCaret          = 0xc3, -,    -,    -,    -;
+    KeypadPercent        = 0xc4, -,    -,    -,    -;
+    KeypadLess           = 0xc5, -,    -,    -,    -;
+    KeypadGreater        = 0xc6, -,    -,    -,    -;
+    KeypadAmpersand      = 0xc7, -,    -,    -,    -;
+    KeypadDoubleAmpersand = 0xc8, -,   -,    -,    -;
+    KeypadPipe           = 0xc9, -,    -,    -,    -;
+    KeypadDoublePipe     = 0xca, -,    -,    -,    -;
+    KeypadColon          = 0xcb, -,    -,    -,    -;
+    KeypadHash           = 0xcc, -,    -,    -,    -;
+    KeypadSpace          = 0xcd, -,    -,    -,    -;
+    KeypadAt             = 0xce, -,    -,    -,    -;
+    KeypadExclamation    = 0xcf, -,    -,    -,    -;
+    KeypadMemoryStore    = 0xd0, -,    -,    -,    -;
+    KeypadMemoryRecall   = 0xd1, -,    -,    -,    -;
+    KeypadMemoryClear    = 0xd2, -,    -,    -,    -;
+    KeypadMemoryAdd      = 0xd3, -,    -,    -,    -;
+    KeypadMemorySubtract = 0xd4, -,    -,    -,    -;
+    KeypadMemoryMultiply = 0xd5, -,    -,    -,    -;
+    KeypadMemoryDivide   = 0xd6, -,    -,    -,    -;
+    KeypadPlusMinus      = 0xd7, -,    -,    -,    -;
+    KeypadClear          = 0xd8, -,    -,    -,    -;
+    KeypadClearEntry     = 0xd9, -,    -,    -,    -;
+    KeypadBinary         = 0xda, -,    -,    -,    -;
+    KeypadOctal          = 0xdb, -,    -,    -,    -;
+    KeypadDecimalBase    = 0xdc, -,    -,    -,    -;
+    KeypadHexadecimal    = 0xdd, -,    -,    -,    -;
+    LeftControl          = 0xe0, 29,   0xa2, 0x3b, 0xffe3;
+    LeftShift            = 0xe1, 42,   0xa0, 0x38, 0xffe1;
+    LeftAlt              = 0xe2, 56,   0xa4, 0x3a, 0xffe9;
+    /// Windows, Super or Command key.
+    LeftMeta             = 0xe3, 125,  0x5b, 0x37, 0xffeb;
+    RightControl         = 0xe4, 97,   0xa3, 0x3e, 0xffe4;
+    RightShift           = 0xe5, 54,   0xa1, 0x3c, 0xffe2;
+    RightAlt             = 0xe6, 100,  0xa5, 0x3d, 0xffea;
+    RightMeta            = 0xe7, 126,  0x5c, 0x36, 0xffec;
+    }
+    // Consumer page. Mute and volume are on the keyboard page, evdev has one
+    // code for both.
+    0x0c => {
+    //                     usage  evdev VK    mac   keysym
+    BrightnessUp         = 0x06f, 225,  -,    -,    0x1008ff02;
+    BrightnessDown       = 0x070, 224,  -,    -,    0x1008ff03;
+    NextTrack            = 0x0b5, 163,  0xb0, -,    0x1008ff17;
+    PreviousTrack        = 0x0b6, 165,  0xb1, -,    0x1008ff16;
+    MediaStop            = 0x0b7, 166,  0xb2, -,    0x1008ff15;
+    Eject                = 0x0b8, 161,  -,    -,    0x1008ff2c;
+    PlayPause            = 0x0cd, 164,  0xb3, -,    0x1008ff14;
+    Mail                 = 0x18a, 155,  0xb4, -,    0x1008ff19;
+    Calculator           = 0x192, 140,  0xb7, -,    0x1008ff1d;
+    BrowserSearch        = 0x221, 217,  0xaa, -,    0x1008ff1b;
+    BrowserHome          = 0x223, 172,  0xac, -,    0x1008ff18;
+    BrowserBack          = 0x224, 158,  0xa6, -,    0x1008ff26;
+    BrowserForward       = 0x225, 159,  0xa7, -,    0x1008ff27;
+    BrowserRefresh       = 0x227, 173,  0xa8, -,    0x1008ff73;
+    BrowserFavorites     = 0x22a, 156,  0xab, -,    0x1008ff30;
+    }
 }
 
-impl Number {
-    pub fn from_u8(value: u8) -> Option<Number> {
-        match value {
-            0x30 => Some(Number::Zero),
-            0x31 => Some(Number::One),
-            0x32 => Some(Number::Two),
-            0x33 => Some(Number::Three),
-            0x34 => Some(Number::Four),
-            0x35 => Some(Number::Five),
-            0x36 => Some(Number::Six),
-            0x37 => Some(Number::Seven),
-            0x38 => Some(Number::Eight),
-            0x39 => Some(Number::Nine),
-            _ => None, // Возвращаем None, если значение не соответствует ни одному из вариантов
-        }
+impl Key {
+    fn find(matches: impl Fn(&KeyCodes) -> bool) -> Option<&'static KeyCodes> {
+        KEYS.iter().find(|codes| matches(codes))
+    }
+
+    fn codes(self) -> Option<&'static KeyCodes> {
+        Self::find(|codes| codes.key == self)
+    }
+
+    pub fn from_usage(usage: u32) -> Self {
+        Self::find(|codes| codes.usage == usage)
+            .map(|codes| codes.key)
+            .unwrap_or(Self::Unknown(usage))
+    }
+
+    /// Key by its variant name, e.g. `LeftControl`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        Self::find(|codes| codes.name == name).map(|codes| codes.key)
+    }
+
+    pub fn from_evdev(code: u32) -> Option<Self> {
+        Self::find(|codes| codes.evdev == Some(code)).map(|codes| codes.key)
+    }
+
+    pub fn to_evdev(self) -> Option<u32> {
+        self.codes()?.evdev
+    }
+
+    /// Key of a Windows virtual key code.
+    pub fn from_vk(vk: u16) -> Option<Self> {
+        Self::find(|codes| codes.vk == Some(vk)).map(|codes| codes.key)
+    }
+
+    pub fn to_vk(self) -> Option<u16> {
+        self.codes()?.vk
+    }
+
+    /// Key of a macOS virtual keycode (`kVK_*`).
+    pub fn from_mac(code: u16) -> Option<Self> {
+        Self::find(|codes| codes.mac == Some(code)).map(|codes| codes.key)
+    }
+
+    pub fn to_mac(self) -> Option<u16> {
+        self.codes()?.mac
+    }
+
+    /// Key typing `keysym` on a US layout, upper case letters included.
+    pub fn from_keysym(keysym: u32) -> Option<Self> {
+        let keysym = match keysym {
+            0x41..=0x5a => keysym + 0x20,
+            keysym => keysym,
+        };
+
+        Self::find(|codes| codes.keysym == Some(keysym)).map(|codes| codes.key)
+    }
+
+    /// Keysym typed without modifiers on a US layout.
+    pub fn to_keysym(self) -> Option<u32> {
+        self.codes()?.keysym
     }
 }
 
-#[derive(Debug)]
-#[repr(u8)]
-#[allow(non_camel_case_types)]
-pub enum Numpad {
-    ///	"0" on the numeric keypad.
-    NUMPAD0 = 0x60,
-    ///	"1" on the numeric keypad.
-    NUMPAD1 = 0x61,
-    ///	"2" on the numeric keypad.
-    NUMPAD2 = 0x62,
-    ///	"3" on the numeric keypad.
-    NUMPAD3 = 0x63,
-    ///	"4" on the numeric keypad.
-    NUMPAD4 = 0x64,
-    ///	"5" on the numeric keypad.
-    NUMPAD5 = 0x65,
-    ///	"6" on the numeric keypad.
-    NUMPAD6 = 0x66,
-    ///	"7" on the numeric keypad.
-    NUMPAD7 = 0x67,
-    ///	"8" on the numeric keypad.
-    NUMPAD8 = 0x68,
-    ///	"9" on the numeric keypad.
-    NUMPAD9 = 0x69,
-    ///	"*" on the numeric keypad.
-    MULTIPLY = 0x6A,
-    ///	"+" on the numeric keypad.
-    ADD = 0x6B,
-    ///	"-" on the numeric keypad.
-    SUBTRACT = 0x6D,
-    ///	Decimal point on the numeric keypad.
-    DECIMAL = 0x6E,
-    ///	"/" on the numeric keypad.
-    DIVIDE = 0x6F,
-    ///	Num Lock key.
-    NUM_LOCK = 0x90,
+// Sent as the HID usage, so the encoding doesn't depend on the variant order.
+impl Encode for Key {
+    fn encode<E: Encoder>(&self, encoder: &mut E) -> Result<(), EncodeError> {
+        self.usage().encode(encoder)
+    }
 }
 
-#[derive(Debug)]
-#[repr(u8)]
-#[allow(non_camel_case_types)]
-pub enum F {
-    ///	F1 key.
-    F1 = 0x70,
-    ///	F2 key.
-    F2 = 0x71,
-    ///	F3 key.
-    F3 = 0x72,
-    ///	F4 key.
-    F4 = 0x73,
-    ///	F5 key.
-    F5 = 0x74,
-    ///	F6 key.
-    F6 = 0x75,
-    ///	F7 key.
-    F7 = 0x76,
-    ///	F8 key.
-    F8 = 0x77,
-    ///	F9 key.
-    F9 = 0x78,
-    ///	F10 key.
-    F10 = 0x79,
-    ///	F11 key.
-    F11 = 0x7A,
-    ///	F12 key.
-    F12 = 0x7B,
-    ///	F13 key.
-    F13 = 0x7C,
-    ///	F14 key.
-    F14 = 0x7D,
-    ///	F15 key.
-    F15 = 0x7E,
-    ///	F16 key.
-    F16 = 0x7F,
-    ///	F17 key.
-    F17 = 0x80,
-    ///	F18 key.
-    F18 = 0x81,
-    ///	F19 key.
-    F19 = 0x82,
-    ///	F20 key.
-    F20 = 0x83,
-    ///	F21 key.
-    F21 = 0x84,
-    ///	F22 key.
-    F22 = 0x85,
-    ///	F23 key.
-    F23 = 0x86,
-    ///	F24 key.
-    F24 = 0x87,
+impl<Context> Decode<Context> for Key {
+    fn decode<D: Decoder<Context = Context>>(decoder: &mut D) -> Result<Self, DecodeError> {
+        Ok(Self::from_usage(u32::decode(decoder)?))
+    }
 }
 
-#[derive(Debug)]
-#[repr(u8)]
-#[allow(non_camel_case_types)]
-pub enum KeyboardButton {
-    ///	Cancel key.
-    CANCEL = 0x03,
-    ///	Help key.
-    HELP = 0x06,
-    ///	Backspace key.
-    BACK_SPACE = 0x08,
-    ///	Tab key.
-    TAB = 0x09,
-    ///	"5" key on Numpad when NumLock is unlocked. Or on Mac, clear key which is positioned at NumLock key.
-    CLEAR = 0x0C,
-    ///	Return/enter key on the main keyboard.
-    RETURN = 0x0D,
-    ENTER = 0x0E,
-    ///	Shift key.
-    SHIFT = 0x10,
-    ///	Control key.
-    CONTROL = 0x11,
-    ///	Alt (Option on Mac) key.
-    ALT = 0x12,
-    ///	Pause key.
-    PAUSE = 0x13,
-    ///	Caps lock.
-    CAPS_LOCK = 0x14,
-    ///	Escape key.
-    ESCAPE = 0x1B,
-    CONVERT = 0x1C,
-    NONCONVERT = 0x1D,
-    ACCEPT = 0x1E,
-    MODECHANGE = 0x1F,
-    ///	Space bar.
-    SPACE = 0x20,
-    ///	Page Up key.
-    PAGE_UP = 0x21,
-    ///	Page Down key.
-    PAGE_DOWN = 0x22,
-    ///	End key.
-    END = 0x23,
-    ///	Home key.
-    HOME = 0x24,
-    ///	Left arrow.
-    LEFT = 0x25,
-    ///	Up arrow.
-    UP = 0x26,
-    ///	Right arrow.
-    RIGHT = 0x27,
-    ///	Down arrow.
-    DOWN = 0x28,
-    SELECT = 0x29,
-    PRINT = 0x2A,
-    EXECUTE = 0x2B,
-    ///	Print Screen key.
-    PRINTSCREEN = 0x2C,
-    ///	Ins(ert) key.
-    INSERT = 0x2D,
-    ///	Del(ete) key.
-    DELETE = 0x2E,
-    ///	Colon (":") key.
-    COLON = 0x3A,
-    ///	Semicolon (",") key.
-    SEMICOLON = 0x3B,
-    ///	Less-than ("<") key.
-    LESS_THAN = 0x3C,
-    ///	Equals ("=") key.
-    EQUALS = 0x3D,
-    ///	Greater-than (">") key.
-    GREATER_THAN = 0x3E,
-    ///	Question mark ("?") key.
-    QUESTION_MARK = 0x3F,
-    ///	Atmark ("@") key.
-    AT = 0x40,
-    ///	Windows logo key on Windows. Or Super or Hyper key on Linux.
-    WIN = 0x5B,
-    ///	Opening context menu key.
-    CONTEXT_MENU = 0x5D,
-    SLEEP = 0x5F,
-    SEPARATOR = 0x6C,
-
-    ///	Scroll Lock key.
-    SCROLL_LOCK = 0x91,
-    ///	Circumflex ("^") key.
-    CIRCUMFLEX = 0xA0,
-    ///	Exclamation ("!") key.
-    EXCLAMATION = 0xA1,
-    ///	Hash ("#") key.
-    HASH = 0xA3,
-    ///	Dollar sign ("$") key.
-    DOLLAR = 0xA4,
-    ///	Percent ("%") key.
-    PERCENT = 0xA5,
-    ///	Ampersand ("&") key.
-    AMPERSAND = 0xA6,
-    ///	Underscore ("_") key.
-    UNDERSCORE = 0xA7,
-    ///	Open parenthesis ("(") key.
-    OPEN_PAREN = 0xA8,
-    ///	Close parenthesis (")") key.
-    CLOSE_PAREN = 0xA9,
-    ///	Asterisk ("*") key.
-    ASTERISK = 0xAA,
-    ///	Plus ("+") key.
-    PLUS = 0xAB,
-    ///	Pipe ("|") key.
-    PIPE = 0xAC,
-    ///	Hyphen-US/docs/Minus ("-") key.
-    HYPHEN_MINUS = 0xAD,
-    ///	Open curly bracket ("{") key.
-    OPEN_CURLY_BRACKET = 0xAE,
-    ///	Close curly bracket ("}") key.
-    CLOSE_CURLY_BRACKET = 0xAF,
-    ///	Tilde ("~") key.
-    TILDE = 0xB0,
-    ///	Audio mute key.
-    VOLUME_MUTE = 0xB5,
-    ///	Audio volume down key
-    VOLUME_DOWN = 0xB6,
-    ///	Audio volume up key
-    VOLUME_UP = 0xB7,
-    ///	Comma (",") key.
-    COMMA = 0xBC,
-    ///	Period (".") key.
-    PERIOD = 0xBE,
-    ///	Slash ("/") key.
-    SLASH = 0xBF,
-    ///	Back tick ("`") key.
-    BACK_QUOTE = 0xC0,
-    ///	Open square bracket ("[") key.
-    OPEN_BRACKET = 0xDB,
-    ///	Back slash ("\") key.
-    BACK_SLASH = 0xDC,
-    ///	Close square bracket ("]") key.
-    CLOSE_BRACKET = 0xDD,
-    ///	Quote (''') key.
-    QUOTE = 0xDE,
-    ///	Meta key on Linux, Command key on Mac.
-    META = 0xE0,
-    ///	AltGr key (Level 3 Shift key or Level 5 Shift key) on Linux.
-    ALTGR = 0xE1,
-    ///	Attn (Attention) key of IBM midrange computers, e.g., AS/400.
-    ATTN = 0xF6,
-    ///	CrSel (Cursor Selection) key of IBM 3270 keyboard layout.
-    CRSEL = 0xF7,
-    ///	ExSel (Extend Selection) key of IBM 3270 keyboard layout.
-    EXSEL = 0xF8,
-    ///	Erase EOF key of IBM 3270 keyboard layout.
-    EREOF = 0xF9,
-    ///	Play key of IBM 3270 keyboard layout.
-    PLAY = 0xFA,
-    ///	Zoom key.
-    ZOOM = 0xFB,
-    ///	PA1 key of IBM 3270 keyboard layout.
-    PA1 = 0xFD,
-    /// Clear key, but we're not sure the meaning difference from CLEAR.
-    WIN_OEM_CLEAR = 0xFE,
-
-    Letter(Letter),
-    Number(Number) = 0,
-    Numpad(Numpad),
-    F(F),
+bincode::impl_borrow_decode!(Key);
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_table_roundtrip() -> Result<()> {
+        for codes in KEYS {
+            let key = codes.key;
+
+            assert_eq!(key.usage(), codes.usage, "{key:?}");
+            assert_eq!(Key::from_usage(codes.usage), key, "{key:?}");
+            assert_eq!(
+                Key::from_name(&format!("{key:?}")),
+                Some(key),
+                "{key:?} name"
+            );
+            if let Some(evdev) = codes.evdev {
+                assert_eq!(Key::from_evdev(evdev), Some(key), "{key:?} evdev");
+            }
+            if let Some(vk) = codes.vk {
+                assert_eq!(Key::from_vk(vk), Some(key), "{key:?} vk");
+            }
+            if let Some(mac) = codes.mac {
+                assert_eq!(Key::from_mac(mac), Some(key), "{key:?} mac");
+            }
+            if let Some(keysym) = codes.keysym {
+                assert_eq!(Key::from_keysym(keysym), Some(key), "{key:?} keysym");
+            }
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_conversions() -> Result<()> {
+        let fx_keys = [
+            (Key::A, 30, 0x41, 0x00, 0x61),
+            (Key::Digit1, 2, 0x31, 0x12, 0x31),
+            (Key::Enter, 28, 0x0d, 0x24, 0xff0d),
+            (Key::Left, 105, 0x25, 0x7b, 0xff51),
+            (Key::LeftShift, 42, 0xa0, 0x38, 0xffe1),
+        ];
+
+        for (key, evdev, vk, mac, keysym) in fx_keys {
+            assert_eq!(key.to_evdev(), Some(evdev), "{key:?}");
+            assert_eq!(key.to_vk(), Some(vk), "{key:?}");
+            assert_eq!(key.to_mac(), Some(mac), "{key:?}");
+            assert_eq!(key.to_keysym(), Some(keysym), "{key:?}");
+        }
+        assert_eq!(Key::from_keysym(0x41), Some(Key::A));
+        assert_eq!(Key::from_evdev(164), Some(Key::PlayPause)); // KEY_PLAYPAUSE
+        assert_eq!(Key::PlayPause.usage(), 0xc_00cd);
+        assert_eq!(Key::VolumeUp.usage(), 0x7_0080);
+        assert_eq!(Key::from_evdev(0x2ff), None);
+        assert_eq!(Key::from_name("leftshift"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_unknown_usage() -> Result<()> {
+        let key = Key::from_usage(0x7_00a5);
+
+        assert_eq!(key, Key::Unknown(0x7_00a5));
+        assert_eq!(key.usage(), 0x7_00a5);
+        assert_eq!(key.to_evdev(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_encode_as_usage() -> Result<()> {
+        let encoded = lib_codec::encode(&Key::Z)?;
+        assert_eq!(encoded, lib_codec::encode(&0x7_001du32)?);
+
+        for key in [Key::Z, Key::PlayPause, Key::Unknown(0x7_00a5)] {
+            let decoded: Key = lib_codec::decode(&lib_codec::encode(&key)?)?;
+            assert_eq!(decoded, key);
+        }
+
+        Ok(())
+    }
 }
+
+// endregion: --- Tests
@@ -0,0 +1,308 @@
+//! X11 keysym names and their conversion to characters.
+//!
+//! Covers the keysyms found in common Latin and Cyrillic keymaps. Other names
+//! can still be written as `U<hex>` or `0x<hex>` in a keymap.
+
+pub const NO_SYMBOL: u32 = 0;
+
+/// Meta keys, on no key of a US layout but injected like Super.
+pub const META_L: u32 = 0xffe7;
+pub const META_R: u32 = 0xffe8;
+
+/// Named keysyms without a character of their own.
+const NAMED: &[(&str, u32)] = &[
+    ("BackSpace", 0xff08),
+    ("Tab", 0xff09),
+    ("ISO_Left_Tab", 0xfe20),
+    ("Return", 0xff0d),
+    ("Pause", 0xff13),
+    ("Scroll_Lock", 0xff14),
+    ("Sys_Req", 0xff15),
+    ("Escape", 0xff1b),
+    ("Delete", 0xffff),
+    ("Home", 0xff50),
+    ("Left", 0xff51),
+    ("Up", 0xff52),
+    ("Right", 0xff53),
+    ("Down", 0xff54),
+    ("Prior", 0xff55),
+    ("Next", 0xff56),
+    ("End", 0xff57),
+    ("Print", 0xff61),
+    ("Insert", 0xff63),
+    ("Menu", 0xff67),
+    ("Num_Lock", 0xff7f),
+    ("KP_Enter", 0xff8d),
+    ("KP_Home", 0xff95),
+    ("KP_Left", 0xff96),
+    ("KP_Up", 0xff97),
+    ("KP_Right", 0xff98),
+    ("KP_Down", 0xff99),
+    ("KP_Prior", 0xff9a),
+    ("KP_Next", 0xff9b),
+    ("KP_End", 0xff9c),
+    ("KP_Begin", 0xff9d),
+    ("KP_Insert", 0xff9e),
+    ("KP_Delete", 0xff9f),
+    ("KP_Multiply", 0xffaa),
+    ("KP_Add", 0xffab),
+    ("KP_Separator", 0xffac),
+    ("KP_Subtract", 0xffad),
+    ("KP_Decimal", 0xffae),
+    ("KP_Divide", 0xffaf),
+    ("KP_0", 0xffb0),
+    ("KP_1", 0xffb1),
+    ("KP_2", 0xffb2),
+    ("KP_3", 0xffb3),
+    ("KP_4", 0xffb4),
+    ("KP_5", 0xffb5),
+    ("KP_6", 0xffb6),
+    ("KP_7", 0xffb7),
+    ("KP_8", 0xffb8),
+    ("KP_9", 0xffb9),
+    ("KP_Equal", 0xffbd),
+    ("Shift_L", 0xffe1),
+    ("Shift_R", 0xffe2),
+    ("Control_L", 0xffe3),
+    ("Control_R", 0xffe4),
+    ("Caps_Lock", 0xffe5),
+    ("Meta_L", 0xffe7),
+    ("Meta_R", 0xffe8),
+    ("Alt_L", 0xffe9),
+    ("Alt_R", 0xffea),
+    ("Super_L", 0xffeb),
+    ("Super_R", 0xffec),
+    ("ISO_Level3_Shift", 0xfe03),
+    ("Mode_switch", 0xff7e),
+    ("Multi_key", 0xff20),
+    ("VoidSymbol", 0xffffff),
+];
+
+/// Named keysyms of Latin-1 characters, their value is the code point.
+const LATIN1: &[(&str, u32)] = &[
+    ("space", 0x20),
+    ("exclam", 0x21),
+    ("quotedbl", 0x22),
+    ("numbersign", 0x23),
+    ("dollar", 0x24),
+    ("percent", 0x25),
+    ("ampersand", 0x26),
+    ("apostrophe", 0x27),
+    ("parenleft", 0x28),
+    ("parenright", 0x29),
+    ("asterisk", 0x2a),
+    ("plus", 0x2b),
+    ("comma", 0x2c),
+    ("minus", 0x2d),
+    ("period", 0x2e),
+    ("slash", 0x2f),
+    ("colon", 0x3a),
+    ("semicolon", 0x3b),
+    ("less", 0x3c),
+    ("equal", 0x3d),
+    ("greater", 0x3e),
+    ("question", 0x3f),
+    ("at", 0x40),
+    ("bracketleft", 0x5b),
+    ("backslash", 0x5c),
+    ("bracketright", 0x5d),
+    ("asciicircum", 0x5e),
+    ("underscore", 0x5f),
+    ("grave", 0x60),
+    ("braceleft", 0x7b),
+    ("bar", 0x7c),
+    ("braceright", 0x7d),
+    ("asciitilde", 0x7e),
+    ("nobreakspace", 0xa0),
+    ("section", 0xa7),
+    ("degree", 0xb0),
+    ("adiaeresis", 0xe4),
+    ("Adiaeresis", 0xc4),
+    ("odiaeresis", 0xf6),
+    ("Odiaeresis", 0xd6),
+    ("udiaeresis", 0xfc),
+    ("Udiaeresis", 0xdc),
+    ("ssharp", 0xdf),
+    ("eacute", 0xe9),
+    ("egrave", 0xe8),
+    ("agrave", 0xe0),
+    ("ccedilla", 0xe7),
+];
+
+/// Cyrillic keysym names with their keysym and code point.
+const CYRILLIC: &[(&str, u32, char)] = &[
+    ("Cyrillic_io", 0x6a3, 'ё'),
+    ("Cyrillic_IO", 0x6b3, 'Ё'),
+    ("Cyrillic_yu", 0x6c0, 'ю'),
+    ("Cyrillic_a", 0x6c1, 'а'),
+    ("Cyrillic_be", 0x6c2, 'б'),
+    ("Cyrillic_tse", 0x6c3, 'ц'),
+    ("Cyrillic_de", 0x6c4, 'д'),
+    ("Cyrillic_ie", 0x6c5, 'е'),
+    ("Cyrillic_ef", 0x6c6, 'ф'),
+    ("Cyrillic_ghe", 0x6c7, 'г'),
+    ("Cyrillic_ha", 0x6c8, 'х'),
+    ("Cyrillic_i", 0x6c9, 'и'),
+    ("Cyrillic_shorti", 0x6ca, 'й'),
+    ("Cyrillic_ka", 0x6cb, 'к'),
+    ("Cyrillic_el", 0x6cc, 'л'),
+    ("Cyrillic_em", 0x6cd, 'м'),
+    ("Cyrillic_en", 0x6ce, 'н'),
+    ("Cyrillic_o", 0x6cf, 'о'),
+    ("Cyrillic_pe", 0x6d0, 'п'),
+    ("Cyrillic_ya", 0x6d1, 'я'),
+    ("Cyrillic_er", 0x6d2, 'р'),
+    ("Cyrillic_es", 0x6d3, 'с'),
+    ("Cyrillic_te", 0x6d4, 'т'),
+    ("Cyrillic_u", 0x6d5, 'у'),
+    ("Cyrillic_zhe", 0x6d6, 'ж'),
+    ("Cyrillic_ve", 0x6d7, 'в'),
+    ("Cyrillic_softsign", 0x6d8, 'ь'),
+    ("Cyrillic_yeru", 0x6d9, 'ы'),
+    ("Cyrillic_ze", 0x6da, 'з'),
+    ("Cyrillic_sha", 0x6db, 'ш'),
+    ("Cyrillic_e", 0x6dc, 'э'),
+    ("Cyrillic_shcha", 0x6dd, 'щ'),
+    ("Cyrillic_che", 0x6de, 'ч'),
+    ("Cyrillic_hardsign", 0x6df, 'ъ'),
+    ("Cyrillic_YU", 0x6e0, 'Ю'),
+    ("Cyrillic_A", 0x6e1, 'А'),
+    ("Cyrillic_BE", 0x6e2, 'Б'),
+    ("Cyrillic_TSE", 0x6e3, 'Ц'),
+    ("Cyrillic_DE", 0x6e4, 'Д'),
+    ("Cyrillic_IE", 0x6e5, 'Е'),
+    ("Cyrillic_EF", 0x6e6, 'Ф'),
+    ("Cyrillic_GHE", 0x6e7, 'Г'),
+    ("Cyrillic_HA", 0x6e8, 'Х'),
+    ("Cyrillic_I", 0x6e9, 'И'),
+    ("Cyrillic_SHORTI", 0x6ea, 'Й'),
+    ("Cyrillic_KA", 0x6eb, 'К'),
+    ("Cyrillic_EL", 0x6ec, 'Л'),
+    ("Cyrillic_EM", 0x6ed, 'М'),
+    ("Cyrillic_EN", 0x6ee, 'Н'),
+    ("Cyrillic_O", 0x6ef, 'О'),
+    ("Cyrillic_PE", 0x6f0, 'П'),
+    ("Cyrillic_YA", 0x6f1, 'Я'),
+    ("Cyrillic_ER", 0x6f2, 'Р'),
+    ("Cyrillic_ES", 0x6f3, 'С'),
+    ("Cyrillic_TE", 0x6f4, 'Т'),
+    ("Cyrillic_U", 0x6f5, 'У'),
+    ("Cyrillic_ZHE", 0x6f6, 'Ж'),
+    ("Cyrillic_VE", 0x6f7, 'В'),
+    ("Cyrillic_SOFTSIGN", 0x6f8, 'Ь'),
+    ("Cyrillic_YERU", 0x6f9, 'Ы'),
+    ("Cyrillic_ZE", 0x6fa, 'З'),
+    ("Cyrillic_SHA", 0x6fb, 'Ш'),
+    ("Cyrillic_E", 0x6fc, 'Э'),
+    ("Cyrillic_SHCHA", 0x6fd, 'Щ'),
+    ("Cyrillic_CHE", 0x6fe, 'Ч'),
+    ("Cyrillic_HARDSIGN", 0x6ff, 'Ъ'),
+];
+
+/// Keysym of a name as written in a keymap.
+pub fn from_name(name: &str) -> Option<u32> {
+    let mut chars = name.chars();
+    if let (Some(ch), None) = (chars.next(), chars.next()) {
+        return Some(from_char(ch));
+    }
+
+    if name == "NoSymbol" {
+        return Some(NO_SYMBOL);
+    }
+    if let Some(hex) = name.strip_prefix("0x") {
+        return u32::from_str_radix(hex, 16).ok();
+    }
+    if let Some(hex) = name.strip_prefix('U') {
+        if let Some(ch) = u32::from_str_radix(hex, 16).ok().and_then(char::from_u32) {
+            return Some(from_char(ch));
+        }
+    }
+
+    NAMED
+        .iter()
+        .chain(LATIN1)
+        .find(|(known, _)| *known == name)
+        .map(|(_, keysym)| *keysym)
+        .or_else(|| {
+            CYRILLIC
+                .iter()
+                .find(|(known, ..)| *known == name)
+                .map(|(_, keysym, _)| *keysym)
+        })
+        .or_else(|| {
+            // Function keys, F1 is 0xffbe.
+            let number: u32 = name.strip_prefix('F')?.parse().ok()?;
+            (1..=35).contains(&number).then(|| 0xffbd + number)
+        })
+}
+
+/// Keysym typing `ch`.
+pub fn from_char(ch: char) -> u32 {
+    let code = ch as u32;
+    match code {
+        0x20..=0x7e | 0xa0..=0xff => code,
+        _ => CYRILLIC
+            .iter()
+            .find(|(.., known)| *known == ch)
+            .map(|(_, keysym, _)| *keysym)
+            .unwrap_or(0x0100_0000 | code),
+    }
+}
+
+/// Character typed by `keysym`, if any.
+pub fn to_char(keysym: u32) -> Option<char> {
+    match keysym {
+        0x20..=0x7e | 0xa0..=0xff => char::from_u32(keysym),
+        0x0100_0100..=0x0110_ffff => char::from_u32(keysym & 0x00ff_ffff),
+        0x6a3..=0x6ff => CYRILLIC
+            .iter()
+            .find(|(_, known, _)| *known == keysym)
+            .map(|(.., ch)| *ch),
+        _ => None,
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_names() -> Result<()> {
+        let fx_names = [
+            ("a", 0x61),
+            ("A", 0x41),
+            ("1", 0x31),
+            ("exclam", 0x21),
+            ("Return", 0xff0d),
+            ("F1", 0xffbe),
+            ("F12", 0xffc9),
+            ("Cyrillic_ef", 0x6c6),
+            ("U20AC", 0x0100_20ac),
+            ("0xff51", 0xff51),
+            ("NoSymbol", NO_SYMBOL),
+        ];
+
+        for (name, keysym) in fx_names {
+            assert_eq!(from_name(name), Some(keysym), "{name}");
+        }
+        assert_eq!(from_name("NotAKeysym"), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_chars_roundtrip() -> Result<()> {
+        for ch in ['a', 'Z', '~', 'ä', 'ф', 'Ё', '€'] {
+            assert_eq!(to_char(from_char(ch)), Some(ch), "{ch}");
+        }
+        assert_eq!(to_char(0xff0d), None);
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
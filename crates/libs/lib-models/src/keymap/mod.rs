@@ -0,0 +1,348 @@
+//! Translation of keys through the XKB keymap of the client.
+//!
+//! A Wayland client receives its keymap as XKB text. Only the parts needed to
+//! name a key are read: the keycodes and the keysyms of each group.
+
+pub mod keysym;
+
+use crate::Key;
+use bincode::{Decode, Encode};
+use derive_more::From;
+use std::collections::HashMap;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+/// XKB keycodes are evdev codes shifted by 8.
+const XKB_OFFSET: u32 = 8;
+
+/// Key identity that doesn't depend on the OS of the sender.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Encode, Decode)]
+pub struct PortableKey {
+    /// Physical key.
+    pub key: Key,
+    /// Keysym of the key without modifiers in the active group.
+    pub keysym: Option<u32>,
+}
+
+impl PortableKey {
+    /// Physical key only, for when no keymap is known.
+    pub fn new(key: Key) -> Self {
+        Self { key, keysym: None }
+    }
+
+    /// Character typed by the key, if any.
+    pub fn char(&self) -> Option<char> {
+        self.keysym.and_then(keysym::to_char)
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct Keymap {
+    /// Keysyms by evdev code, group then level.
+    keys: HashMap<u32, Vec<Vec<u32>>>,
+}
+
+impl Keymap {
+    /// Reads an `xkb_keymap` in the text format of `xkbcomp`.
+    pub fn parse(text: &str) -> Result<Self> {
+        let text = strip_comments(text);
+        let keycodes = parse_keycodes(section(&text, "xkb_keycodes")?);
+        let symbols = section(&text, "xkb_symbols")?;
+
+        let mut keys = HashMap::new();
+        for statement in split_top_level(symbols, ';') {
+            let Some((name, groups)) = parse_key(statement) else {
+                continue;
+            };
+            let Some(&code) = keycodes.get(name) else {
+                continue;
+            };
+
+            keys.insert(code, groups);
+        }
+
+        if keys.is_empty() {
+            return Err(Error::NoKeys);
+        }
+
+        Ok(Self { keys })
+    }
+
+    /// Keysyms of the key in `group` by level, the group wraps like in XKB.
+    pub fn keysyms(&self, key: Key, group: u32) -> &[u32] {
+        match key.to_evdev().and_then(|code| self.keys.get(&code)) {
+            Some(groups) if !groups.is_empty() => &groups[group as usize % groups.len()],
+            _ => &[],
+        }
+    }
+
+    pub fn translate(&self, key: Key, group: u32) -> PortableKey {
+        let keysym = self
+            .keysyms(key, group)
+            .first()
+            .copied()
+            .filter(|&keysym| keysym != keysym::NO_SYMBOL);
+
+        PortableKey { key, keysym }
+    }
+}
+
+fn strip_comments(text: &str) -> String {
+    text.lines()
+        .map(|line| match line.find("//") {
+            Some(start) => &line[..start],
+            None => line,
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// Body of the first `name { ... }` block.
+fn section<'a>(text: &'a str, name: &'static str) -> Result<&'a str> {
+    let start = text.find(name).ok_or(Error::SectionMissing(name))?;
+    let open = text[start..]
+        .find('{')
+        .map(|offset| start + offset + 1)
+        .ok_or(Error::SectionMissing(name))?;
+
+    let mut depth = 1;
+    for (offset, ch) in text[open..].char_indices() {
+        match ch {
+            '{' => depth += 1,
+            '}' => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return Ok(&text[open..open + offset]);
+        }
+    }
+
+    Err(Error::SectionMissing(name))
+}
+
+/// Splits on `separator` outside of braces, brackets and strings.
+fn split_top_level(text: &str, separator: char) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut quoted = false;
+    let mut start = 0;
+
+    for (offset, ch) in text.char_indices() {
+        match ch {
+            '"' => quoted = !quoted,
+            '{' | '[' | '(' if !quoted => depth += 1,
+            '}' | ']' | ')' if !quoted => depth -= 1,
+            ch if ch == separator && depth == 0 && !quoted => {
+                parts.push(text[start..offset].trim());
+                start = offset + ch.len_utf8();
+            }
+            _ => {}
+        }
+    }
+    parts.push(text[start..].trim());
+
+    parts.into_iter().filter(|part| !part.is_empty()).collect()
+}
+
+/// Evdev codes by key name, aliases included.
+fn parse_keycodes(body: &str) -> HashMap<&str, u32> {
+    let mut codes = HashMap::new();
+    let mut aliases = Vec::new();
+
+    for statement in split_top_level(body, ';') {
+        let Some((left, right)) = statement.split_once('=') else {
+            continue;
+        };
+        let (left, right) = (left.trim(), right.trim());
+
+        match left.strip_prefix("alias") {
+            Some(alias) => aliases.push((key_name(alias.trim()), key_name(right))),
+            None => {
+                let (Some(name), Ok(code)) = (key_name(left), right.parse::<u32>()) else {
+                    continue;
+                };
+                if let Some(code) = code.checked_sub(XKB_OFFSET) {
+                    codes.insert(name, code);
+                }
+            }
+        }
+    }
+
+    for (alias, target) in aliases {
+        if let (Some(alias), Some(&code)) = (alias, target.and_then(|name| codes.get(name))) {
+            codes.insert(alias, code);
+        }
+    }
+
+    codes
+}
+
+/// `ESC` for `<ESC>`.
+fn key_name(text: &str) -> Option<&str> {
+    text.strip_prefix('<')?.strip_suffix('>')
+}
+
+/// Name and keysym groups of a `key <NAME> { ... }` statement.
+fn parse_key(statement: &str) -> Option<(&str, Vec<Vec<u32>>)> {
+    let statement = ["replace", "override", "augment"]
+        .iter()
+        .fold(statement, |statement, prefix| {
+            statement.strip_prefix(prefix).unwrap_or(statement).trim()
+        });
+    let rest = statement.strip_prefix("key")?.trim();
+    let (name, block) = rest.split_once('{')?;
+    let name = key_name(name.trim())?;
+    let block = block.trim().strip_suffix('}')?;
+
+    let mut groups: Vec<Vec<u32>> = Vec::new();
+    for entry in split_top_level(block, ',') {
+        // Either an implicit `[ ... ]` for the next group or `symbols[GroupN] = [ ... ]`.
+        let (index, list) = match entry.split_once('=') {
+            Some((field, list)) => {
+                let Some(index) = field.trim().strip_prefix("symbols") else {
+                    continue;
+                };
+                let number: usize = index
+                    .trim()
+                    .trim_start_matches('[')
+                    .trim_end_matches(']')
+                    .trim()
+                    .trim_start_matches(|ch: char| ch.is_ascii_alphabetic())
+                    .parse()
+                    .ok()?;
+                (number.checked_sub(1)?, list.trim())
+            }
+            None if entry.starts_with('[') => (groups.len(), entry),
+            None => continue,
+        };
+
+        let levels = list
+            .strip_prefix('[')?
+            .strip_suffix(']')?
+            .split(',')
+            .map(|name| keysym::from_name(name.trim()).unwrap_or(keysym::NO_SYMBOL))
+            .collect();
+
+        if groups.len() <= index {
+            groups.resize(index + 1, Vec::new());
+        }
+        groups[index] = levels;
+    }
+
+    Some((name, groups))
+}
+
+#[derive(Debug, From)]
+pub enum Error {
+    SectionMissing(&'static str),
+    NoKeys,
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    const FX_KEYMAP: &str = r#"xkb_keymap {
+xkb_keycodes "evdev+aliases(qwerty)" {
+    minimum = 8;
+    maximum = 255;
+    <ESC>                = 9;
+    <AE01>               = 10;
+    <AD01>               = 24;
+    <AC01>               = 38;
+    <AC12>               = 51;
+    <LFSH>               = 50;
+    indicator 1 = "Caps Lock";
+    alias <BKSL> = <AC12>;
+};
+xkb_types "complete" {
+    virtual_modifiers NumLock;
+    type "ONE_LEVEL" {
+        modifiers= none;
+        level_name[Level1]= "Any";
+    };
+};
+xkb_symbols "pc+us+ru:2" {
+    name[group1]="English (US)";
+    name[group2]="Russian";
+
+    key <ESC>                {	[          Escape ] };
+    key <AE01>               {	[               1,          exclam ],
+                                [               1,          exclam ] };
+    key <AD01>               {
+        type= "ALPHABETIC",
+        symbols[Group1]= [               q,               Q ],
+        symbols[Group2]= [     Cyrillic_shorti,     Cyrillic_SHORTI ]
+    };
+    key <AC01>               {	[               a,               A ], [ Cyrillic_ef, Cyrillic_EF ] };
+    key <BKSL>               {	[       backslash,             bar ] };
+    key <LFSH>               {	[         Shift_L ] };
+    modifier_map Shift { <LFSH> };
+};
+};
+"#;
+
+    #[test]
+    fn test_parse() -> Result<()> {
+        let keymap = Keymap::parse(FX_KEYMAP)?;
+
+        assert_eq!(keymap.keysyms(Key::Escape, 0), [0xff1b]);
+        assert_eq!(keymap.keysyms(Key::Digit1, 1), [0x31, 0x21]);
+        assert_eq!(keymap.keysyms(Key::Q, 1), [0x6ca, 0x6ea]);
+        // The alias names the same key.
+        assert_eq!(keymap.keysyms(Key::Backslash, 0), [0x5c, 0x7c]);
+        assert!(keymap.keysyms(Key::PrintScreen, 0).is_empty());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_translate() -> Result<()> {
+        let keymap = Keymap::parse(FX_KEYMAP)?;
+
+        let key = keymap.translate(Key::A, 0);
+        assert_eq!(key.key, Key::A);
+        assert_eq!(key.char(), Some('a'));
+        assert_eq!(keymap.translate(Key::A, 1).char(), Some('ф'));
+        // Groups wrap around.
+        assert_eq!(keymap.translate(Key::A, 2).char(), Some('a'));
+        // Single group keys keep their symbol in every group.
+        assert_eq!(keymap.translate(Key::LeftShift, 1).keysym, Some(0xffe1));
+
+        let unknown = keymap.translate(Key::PrintScreen, 0);
+        assert_eq!(unknown, PortableKey::new(Key::PrintScreen));
+        assert_eq!(unknown.char(), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() -> Result<()> {
+        assert!(matches!(
+            Keymap::parse("xkb_keymap { };"),
+            Err(Error::SectionMissing("xkb_keycodes"))
+        ));
+        assert!(matches!(
+            Keymap::parse("xkb_keycodes { <ESC> = 9; }; xkb_symbols { };"),
+            Err(Error::NoKeys)
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -1,13 +1,20 @@
 pub mod clipboard;
+pub mod handshake;
+pub mod keymap;
 
 mod answer;
+mod batch;
 mod command;
 mod display;
 mod keyboard;
+mod message;
+mod modifiers;
 mod mouse;
 
-pub use answer::Answer;
-pub use command::Command;
-pub use display::DisplayParams;
-pub use keyboard::KeyboardButton;
-pub use mouse::{MouseButton, MouseScroll};
+pub use answer::{Answer, DenyReason, InputErrorKind};
+pub use batch::CommandBatch;
+pub use command::{Command, CommandKind, Delivery, SequencedCommand};
+pub use display::{normalize, DisplayFormatError, DisplayParams, NORMALIZED_ONE};
+pub use keyboard::Key;
+pub use modifiers::ModifierState;
+pub use mouse::{MouseButton, MouseScroll, ScrollDelta, ScrollSource};
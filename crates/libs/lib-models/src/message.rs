@@ -0,0 +1,88 @@
+//! Frame types of the messages sent on streams, see [`lib_codec::frame`].
+//!
+//! Kept in one place so no two messages share a type.
+
+use crate::{
+    handshake::{ClientHello, HandshakeReply, ProtocolVersion},
+    Answer, SequencedCommand,
+};
+use lib_codec::Message;
+
+// -- Handshake
+impl Message for ProtocolVersion {
+    const TYPE: u8 = 0x01;
+}
+
+impl Message for ClientHello {
+    const TYPE: u8 = 0x02;
+}
+
+impl Message for HandshakeReply {
+    const TYPE: u8 = 0x03;
+}
+
+// -- Session
+impl Message for SequencedCommand {
+    const TYPE: u8 = 0x10;
+}
+
+impl Message for Answer {
+    const TYPE: u8 = 0x11;
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use crate::Command;
+    use lib_codec::frame::{self, Flags};
+
+    /// Command 7, sent after reliable command 3 at 1000 µs, moving by (5, -2).
+    const FX_COMMAND_FRAME: [u8; 17] = [65, 76, 1, 16, 0, 8, 0, 0, 0, 3, 7, 251, 232, 3, 1, 10, 3];
+
+    #[test]
+    fn test_types_unique() -> Result<()> {
+        let mut types = vec![
+            ProtocolVersion::TYPE,
+            ClientHello::TYPE,
+            HandshakeReply::TYPE,
+            SequencedCommand::TYPE,
+            Answer::TYPE,
+        ];
+        let count = types.len();
+        types.sort();
+        types.dedup();
+
+        assert_eq!(types.len(), count);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_command_frame() -> Result<()> {
+        let fx_command =
+            SequencedCommand::new(3, Command::MoveMouse { x: 5, y: -2 }).with_stamp(7, 1000);
+
+        let encoded = frame::encode(&fx_command, Flags::default())?;
+        assert_eq!(encoded, FX_COMMAND_FRAME);
+
+        let (frame, _) = frame::decode(&FX_COMMAND_FRAME)?;
+        let command: SequencedCommand = frame.decode()?;
+        assert!(matches!(
+            command,
+            SequencedCommand {
+                seq: 3,
+                id: 7,
+                sent_at: 1000,
+                command: Command::MoveMouse { x: 5, y: -2 }
+            }
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
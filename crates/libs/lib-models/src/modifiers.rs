@@ -0,0 +1,26 @@
+use bincode::{Decode, Encode};
+
+/// XKB modifier masks of the client keyboard, as in `wl_keyboard.modifiers`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub struct ModifierState {
+    pub depressed: u32,
+    pub latched: u32,
+    pub locked: u32,
+    /// Active layout index.
+    pub group: u32,
+}
+
+impl ModifierState {
+    // Real modifiers in the order of the standard XKB keymaps.
+    pub const SHIFT: u32 = 1 << 0;
+    pub const CAPS_LOCK: u32 = 1 << 1;
+    pub const CTRL: u32 = 1 << 2;
+    pub const ALT: u32 = 1 << 3;
+    pub const NUM_LOCK: u32 = 1 << 4;
+    pub const SUPER: u32 = 1 << 6;
+
+    /// Modifiers in effect, held or latched.
+    pub fn active(&self) -> u32 {
+        self.depressed | self.latched
+    }
+}
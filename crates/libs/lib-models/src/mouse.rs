@@ -1,31 +1,225 @@
 use bincode::{Decode, Encode};
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy, Encode, Decode)]
+/// Evdev code of the first mouse button (`BTN_LEFT`), macOS numbers the
+/// buttons in the same order from 0.
+const BTN_LEFT: u32 = 0x110;
+
+/// Evdev buttons accepted as [`MouseButton::Other`], `BTN_0` to the end of
+/// the mouse range.
+const BUTTON_CODES: core::ops::RangeInclusive<u32> = 0x100..=0x11f;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Encode, Decode)]
 pub enum MouseButton {
-    LEFT = 272,
-    RIGHT = 273,
-    MIDDLE = 274,
-    MOUSE4 = 275,
-    MOUSE5 = 276,
+    Left,
+    Right,
+    Middle,
+    /// First side button.
+    Back,
+    /// Second side button.
+    Forward,
+    /// Any other button by its evdev code.
+    Other(u32),
 }
 
 impl MouseButton {
-    pub fn from_linux_code(value: u32) -> Self {
-        match value {
-            272 => Self::LEFT,   // BTN_LEFT
-            273 => Self::RIGHT,  // BTN_RIGHT
-            274 => Self::MIDDLE, // BTN_MIDDLE
-            275 => Self::MOUSE4, // BTN_BACK
-            276 => Self::MOUSE5, // BTN_FORWARD
-            _ => panic!("Unsupported mouse button code: {}", value),
+    const NAMED: [Self; 5] = [
+        Self::Left,
+        Self::Right,
+        Self::Middle,
+        Self::Back,
+        Self::Forward,
+    ];
+
+    pub fn from_evdev(code: u32) -> Option<Self> {
+        Self::NAMED
+            .into_iter()
+            .find(|button| button.to_evdev() == code)
+            .or_else(|| BUTTON_CODES.contains(&code).then_some(Self::Other(code)))
+    }
+
+    pub const fn to_evdev(self) -> u32 {
+        match self {
+            Self::Left => BTN_LEFT,
+            Self::Right => 0x111,   // BTN_RIGHT
+            Self::Middle => 0x112,  // BTN_MIDDLE
+            Self::Back => 0x113,    // BTN_SIDE
+            Self::Forward => 0x114, // BTN_EXTRA
+            Self::Other(code) => code,
         }
     }
+
+    /// Button of a Windows virtual key (`VK_LBUTTON`, `VK_XBUTTON1`, ...).
+    pub fn from_vk(vk: u16) -> Option<Self> {
+        Self::NAMED
+            .into_iter()
+            .find(|button| button.to_vk() == Some(vk))
+    }
+
+    /// Windows only names the five standard buttons.
+    pub const fn to_vk(self) -> Option<u16> {
+        match self {
+            Self::Left => Some(0x01),    // VK_LBUTTON
+            Self::Right => Some(0x02),   // VK_RBUTTON
+            Self::Middle => Some(0x04),  // VK_MBUTTON
+            Self::Back => Some(0x05),    // VK_XBUTTON1
+            Self::Forward => Some(0x06), // VK_XBUTTON2
+            Self::Other(_) => None,
+        }
+    }
+
+    /// Button of a macOS button number, 0 being the left button.
+    pub fn from_mac(number: u32) -> Option<Self> {
+        Self::from_evdev(BTN_LEFT.checked_add(number)?)
+    }
+
+    pub fn to_mac(self) -> Option<u32> {
+        self.to_evdev().checked_sub(BTN_LEFT)
+    }
+}
+
+/// Scroll motion of one pointer frame, positive values go down and right.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Encode, Decode)]
+pub struct MouseScroll {
+    pub source: ScrollSource,
+    pub vertical: ScrollDelta,
+    pub horizontal: ScrollDelta,
 }
 
-#[allow(non_camel_case_types)]
-#[derive(Debug, Clone, Copy, Encode, Decode)]
-pub enum MouseScroll {
-    Vertical(i32),
-    Horizontal(i32),
+impl MouseScroll {
+    pub fn is_empty(&self) -> bool {
+        self.vertical.is_empty() && self.horizontal.is_empty()
+    }
 }
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Encode, Decode)]
+pub struct ScrollDelta {
+    /// Wheel motion in 1/120 of a notch, 0 when the source has no notches.
+    pub value120: i32,
+    /// Continuous motion in surface pixels.
+    pub pixels: f64,
+    /// The motion on this axis stopped, e.g. the fingers left the touchpad.
+    pub stop: bool,
+}
+
+impl ScrollDelta {
+    /// Pixels a wheel notch scrolls, as reported by the Wayland compositors.
+    pub const PIXELS_PER_NOTCH: f64 = 15.0;
+
+    pub fn is_empty(&self) -> bool {
+        self.value120 == 0 && self.pixels == 0.0 && !self.stop
+    }
+
+    /// Motion in 1/120 of a notch, derived from the pixels when the source
+    /// has no notches.
+    pub fn to_value120(&self) -> f64 {
+        match self.value120 {
+            0 => self.pixels * 120.0 / Self::PIXELS_PER_NOTCH,
+            value120 => value120 as f64,
+        }
+    }
+
+    /// Motion in pixels, derived from the notches when the source has no
+    /// pixels.
+    pub fn to_pixels(&self) -> f64 {
+        if self.pixels == 0.0 {
+            return self.value120 as f64 * Self::PIXELS_PER_NOTCH / 120.0;
+        }
+        self.pixels
+    }
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Encode, Decode)]
+pub enum ScrollSource {
+    #[default]
+    Wheel,
+    /// Fingers on a touchpad, ends with a stop.
+    Finger,
+    /// Continuous motion without a physical end, e.g. a trackball.
+    Continuous,
+    /// Side tilt of the wheel.
+    WheelTilt,
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_named_roundtrip() -> Result<()> {
+        let fx_buttons = [
+            (MouseButton::Left, 272, 0x01, 0),
+            (MouseButton::Right, 273, 0x02, 1),
+            (MouseButton::Middle, 274, 0x04, 2),
+            (MouseButton::Back, 275, 0x05, 3),
+            (MouseButton::Forward, 276, 0x06, 4),
+        ];
+
+        for (button, evdev, vk, mac) in fx_buttons {
+            assert_eq!(button.to_evdev(), evdev, "{button:?}");
+            assert_eq!(MouseButton::from_evdev(evdev), Some(button), "{button:?}");
+            assert_eq!(button.to_vk(), Some(vk), "{button:?}");
+            assert_eq!(MouseButton::from_vk(vk), Some(button), "{button:?}");
+            assert_eq!(button.to_mac(), Some(mac), "{button:?}");
+            assert_eq!(MouseButton::from_mac(mac), Some(button), "{button:?}");
+        }
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_other_buttons() -> Result<()> {
+        // BTN_FORWARD, sixth button on macOS.
+        let button = MouseButton::from_evdev(0x115).ok_or("not a button")?;
+        assert_eq!(button, MouseButton::Other(0x115));
+        assert_eq!(button.to_vk(), None);
+        assert_eq!(button.to_mac(), Some(5));
+        assert_eq!(MouseButton::from_mac(5), Some(button));
+
+        // BTN_0 has no macOS number.
+        assert_eq!(
+            MouseButton::from_evdev(0x100).and_then(MouseButton::to_mac),
+            None
+        );
+
+        assert_eq!(MouseButton::from_evdev(30), None); // KEY_A
+        assert_eq!(MouseButton::from_vk(0x41), None);
+        assert_eq!(MouseButton::from_mac(64), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_scroll_value120() -> Result<()> {
+        let wheel = ScrollDelta {
+            value120: -60,
+            pixels: -7.5,
+            stop: false,
+        };
+        assert_eq!(wheel.to_value120(), -60.0);
+
+        let finger = ScrollDelta {
+            pixels: 3.75,
+            ..Default::default()
+        };
+        assert_eq!(finger.to_value120(), 30.0);
+
+        let scroll = MouseScroll {
+            source: ScrollSource::Finger,
+            vertical: ScrollDelta {
+                stop: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert!(!scroll.is_empty());
+        assert!(MouseScroll::default().is_empty());
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
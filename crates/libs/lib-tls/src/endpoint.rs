@@ -0,0 +1,189 @@
+//! QUIC endpoints where both peers present a certificate.
+//!
+//! The client pins the server certificates, the server takes any client
+//! certificate the client proves to own and leaves the decision to its trust
+//! store, see [`peer_fingerprint`].
+
+use crate::{Fingerprint, Identity, Result};
+use lib_quic::quinn::{
+    self,
+    crypto::rustls::{QuicClientConfig, QuicServerConfig},
+    rustls::{
+        self,
+        client::danger::HandshakeSignatureValid,
+        crypto::CryptoProvider,
+        pki_types::{CertificateDer, UnixTime},
+        server::danger::{ClientCertVerified, ClientCertVerifier},
+        DigitallySignedStruct, DistinguishedName, SignatureScheme,
+    },
+};
+use std::{net::SocketAddr, sync::Arc};
+
+/// Server endpoint on `address` that requires a client certificate.
+pub fn server(address: SocketAddr, identity: &Identity) -> Result<quinn::Endpoint> {
+    let provider = provider();
+    let tls = rustls::ServerConfig::builder_with_provider(provider.clone())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_client_cert_verifier(Arc::new(AnyClientCert(provider)))
+        .with_single_cert(identity.certs.clone(), identity.clone_key())?;
+
+    let config = quinn::ServerConfig::with_crypto(Arc::new(QuicServerConfig::try_from(tls)?));
+
+    Ok(quinn::Endpoint::server(config, address)?)
+}
+
+/// Client endpoint trusting only `server_certs`, authenticated with
+/// `identity`.
+pub fn client(
+    server_certs: Vec<CertificateDer<'static>>,
+    identity: &Identity,
+) -> Result<quinn::Endpoint> {
+    let mut roots = rustls::RootCertStore::empty();
+    for cert in server_certs {
+        roots.add(cert)?;
+    }
+
+    let tls = rustls::ClientConfig::builder_with_provider(provider())
+        .with_protocol_versions(&[&rustls::version::TLS13])?
+        .with_root_certificates(roots)
+        .with_client_auth_cert(identity.certs.clone(), identity.clone_key())?;
+
+    let mut endpoint = quinn::Endpoint::client(SocketAddr::from(([0, 0, 0, 0], 0)))?;
+    endpoint.set_default_client_config(quinn::ClientConfig::new(Arc::new(
+        QuicClientConfig::try_from(tls)?,
+    )));
+
+    Ok(endpoint)
+}
+
+/// Fingerprint of the certificate the peer authenticated with.
+pub fn peer_fingerprint(connection: &quinn::Connection) -> Option<Fingerprint> {
+    let certs = connection
+        .peer_identity()?
+        .downcast::<Vec<CertificateDer<'static>>>()
+        .ok()?;
+
+    certs.first().map(|cert| Fingerprint::of(cert))
+}
+
+/// The default provider when one is installed, ring otherwise.
+fn provider() -> Arc<CryptoProvider> {
+    CryptoProvider::get_default()
+        .cloned()
+        .unwrap_or_else(|| Arc::new(rustls::crypto::ring::default_provider()))
+}
+
+/// Accepts any client certificate whose key signs the handshake, the device
+/// is authorized by its fingerprint afterwards.
+#[derive(Debug)]
+struct AnyClientCert(Arc<CryptoProvider>);
+
+impl ClientCertVerifier for AnyClientCert {
+    fn root_hint_subjects(&self) -> &[DistinguishedName] {
+        &[]
+    }
+
+    fn verify_client_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _now: UnixTime,
+    ) -> core::result::Result<ClientCertVerified, rustls::Error> {
+        Ok(ClientCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> core::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls12_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> core::result::Result<HandshakeSignatureValid, rustls::Error> {
+        rustls::crypto::verify_tls13_signature(
+            message,
+            cert,
+            dss,
+            &self.0.signature_verification_algorithms,
+        )
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use std::path::PathBuf;
+
+    fn fx_identity(name: &str) -> Result<(Identity, PathBuf)> {
+        let dir = std::env::temp_dir().join(format!("lib-tls-{name}-{}", std::process::id()));
+        let identity = Identity::create(
+            &dir.join("cert.pem"),
+            &dir.join("key.pem"),
+            vec!["localhost".to_string()],
+        )?;
+        Ok((identity, dir))
+    }
+
+    #[tokio::test]
+    async fn test_mutual_tls() -> Result<()> {
+        let (server_identity, server_dir) = fx_identity("server")?;
+        let (client_identity, client_dir) = fx_identity("client")?;
+
+        let server = server(SocketAddr::from(([127, 0, 0, 1], 0)), &server_identity)?;
+        let address = server.local_addr()?;
+        let client = client(server_identity.certs.clone(), &client_identity)?;
+
+        let (accepted, connection) = tokio::join!(
+            async { server.accept().await?.await.ok() },
+            client.connect(address, "localhost")?
+        );
+        let connection = connection?;
+
+        assert_eq!(
+            accepted.as_ref().and_then(peer_fingerprint),
+            Some(client_identity.fingerprint())
+        );
+        assert_eq!(
+            peer_fingerprint(&connection),
+            Some(server_identity.fingerprint())
+        );
+
+        // Another server certificate isn't trusted.
+        let (other_identity, other_dir) = fx_identity("other")?;
+        let client = super::client(other_identity.certs.clone(), &client_identity)?;
+        let (accepted, connection) = tokio::join!(
+            async { server.accept().await?.await.ok() },
+            client.connect(address, "localhost")?
+        );
+        assert!(accepted.is_none());
+        assert!(connection.is_err());
+
+        for dir in [server_dir, client_dir, other_dir] {
+            std::fs::remove_dir_all(dir)?;
+        }
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,37 @@
+use derive_more::derive::From;
+
+pub type Result<T> = core::result::Result<T, Error>;
+
+#[derive(Debug, From)]
+pub enum Error {
+    /// The PEM file holds no certificate.
+    NoCertificate(std::path::PathBuf),
+    /// The PEM file holds no private key.
+    NoPrivateKey(std::path::PathBuf),
+    CertificateUnreadable(String),
+    FingerprintWrongFormat(String),
+    TrustStoreWrongFormat {
+        line: usize,
+        content: String,
+    },
+
+    // -- Externals
+    #[from]
+    Rustls(lib_quic::quinn::rustls::Error),
+    #[from]
+    Rcgen(rcgen::Error),
+    #[from]
+    NoInitialCipherSuite(lib_quic::quinn::crypto::rustls::NoInitialCipherSuite),
+    #[from]
+    Io(std::io::Error),
+}
+
+// region:    --- Error Boilerplate
+impl core::fmt::Display for Error {
+    fn fmt(&self, fmt: &mut core::fmt::Formatter) -> core::result::Result<(), core::fmt::Error> {
+        write!(fmt, "{self:?}")
+    }
+}
+
+impl std::error::Error for Error {}
+// endregion: --- Error Boilerplate
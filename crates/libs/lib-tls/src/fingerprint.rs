@@ -0,0 +1,106 @@
+use crate::{Error, Result};
+use sha2::{Digest, Sha256};
+use std::{fmt, str::FromStr};
+
+/// SHA-256 of a DER certificate, the identity of a device.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Fingerprint([u8; 32]);
+
+impl Fingerprint {
+    pub fn of(cert_der: &[u8]) -> Self {
+        Self(Sha256::digest(cert_der).into())
+    }
+
+    pub fn as_bytes(&self) -> &[u8; 32] {
+        &self.0
+    }
+}
+
+/// Colon separated uppercase hex, as printed by `openssl x509 -fingerprint`.
+impl fmt::Display for Fingerprint {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, byte) in self.0.iter().enumerate() {
+            if i > 0 {
+                f.write_str(":")?;
+            }
+            write!(f, "{byte:02X}")?;
+        }
+        Ok(())
+    }
+}
+
+/// Hex with or without colons, any case.
+impl FromStr for Fingerprint {
+    type Err = Error;
+
+    fn from_str(text: &str) -> Result<Self> {
+        let wrong_format = || Error::FingerprintWrongFormat(text.to_string());
+        let hex: Vec<u8> = text.bytes().filter(|&b| b != b':').collect();
+        if hex.len() != 64 {
+            return Err(wrong_format());
+        }
+
+        let mut bytes = [0u8; 32];
+        for (byte, pair) in bytes.iter_mut().zip(hex.chunks_exact(2)) {
+            let pair = std::str::from_utf8(pair).map_err(|_| wrong_format())?;
+            *byte = u8::from_str_radix(pair, 16).map_err(|_| wrong_format())?;
+        }
+
+        Ok(Self(bytes))
+    }
+}
+
+/// Six digits both peers show on pairing, the same on both sides only when
+/// nobody sits between them.
+pub fn pairing_code(server: &Fingerprint, client: &Fingerprint) -> String {
+    let digest = Sha256::new()
+        .chain_update(server.as_bytes())
+        .chain_update(client.as_bytes())
+        .finalize();
+    let code = u32::from_be_bytes([digest[0], digest[1], digest[2], digest[3]]) % 1_000_000;
+
+    format!("{:03} {:03}", code / 1000, code % 1000)
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_fingerprint_text() -> Result<()> {
+        let fingerprint = Fingerprint::of(b"certificate");
+        let text = fingerprint.to_string();
+
+        assert_eq!(text.len(), 32 * 3 - 1);
+        assert_eq!(text.parse::<Fingerprint>()?, fingerprint);
+        assert_eq!(
+            text.replace(':', "")
+                .to_lowercase()
+                .parse::<Fingerprint>()?,
+            fingerprint
+        );
+        assert!("AB:CD".parse::<Fingerprint>().is_err());
+        assert!("ZZ".repeat(32).parse::<Fingerprint>().is_err());
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_pairing_code() -> Result<()> {
+        let server = Fingerprint::of(b"server");
+        let client = Fingerprint::of(b"client");
+
+        let code = pairing_code(&server, &client);
+        assert_eq!(code.len(), 7);
+        assert_eq!(code, pairing_code(&server, &client));
+        assert_ne!(code, pairing_code(&server, &Fingerprint::of(b"other")));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
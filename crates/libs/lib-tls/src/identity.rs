@@ -0,0 +1,115 @@
+use crate::{Error, Fingerprint, Result};
+use lib_quic::quinn::rustls::pki_types::{CertificateDer, PrivateKeyDer};
+use std::{fs, io::BufReader, path::Path};
+use tracing::info;
+
+/// Certificate chain and private key a peer authenticates with.
+#[derive(Debug)]
+pub struct Identity {
+    pub certs: Vec<CertificateDer<'static>>,
+    pub key: PrivateKeyDer<'static>,
+}
+
+impl Identity {
+    pub fn load(cert: &Path, key: &Path) -> Result<Self> {
+        let certs = load_certs(cert)?;
+        let mut reader = BufReader::new(fs::File::open(key)?);
+        let key = rustls_pemfile::private_key(&mut reader)?
+            .ok_or_else(|| Error::NoPrivateKey(key.to_path_buf()))?;
+
+        Ok(Self { certs, key })
+    }
+
+    /// Writes a new self-signed identity for `names` and loads it.
+    pub fn create(cert: &Path, key: &Path, names: Vec<String>) -> Result<Self> {
+        let certified = rcgen::generate_simple_self_signed(names)?;
+
+        for path in [cert, key] {
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir)?;
+            }
+        }
+        fs::write(cert, certified.cert.pem())?;
+        write_private(key, certified.signing_key.serialize_pem().as_bytes())?;
+
+        Self::load(cert, key)
+    }
+
+    /// Loads the identity, or creates it on the first run.
+    pub fn load_or_create(cert: &Path, key: &Path, names: Vec<String>) -> Result<Self> {
+        if cert.exists() && key.exists() {
+            return Self::load(cert, key);
+        }
+
+        let identity = Self::create(cert, key, names)?;
+        info!(
+            "New device identity {} in {}",
+            identity.fingerprint(),
+            cert.display()
+        );
+
+        Ok(identity)
+    }
+
+    /// Fingerprint of the leaf certificate.
+    pub fn fingerprint(&self) -> Fingerprint {
+        Fingerprint::of(&self.certs[0])
+    }
+
+    pub fn clone_key(&self) -> PrivateKeyDer<'static> {
+        self.key.clone_key()
+    }
+}
+
+/// Certificates of a PEM file, at least one.
+pub fn load_certs(path: &Path) -> Result<Vec<CertificateDer<'static>>> {
+    let mut reader = BufReader::new(fs::File::open(path)?);
+    let certs = rustls_pemfile::certs(&mut reader).collect::<std::io::Result<Vec<_>>>()?;
+    if certs.is_empty() {
+        return Err(Error::NoCertificate(path.to_path_buf()));
+    }
+
+    Ok(certs)
+}
+
+/// Writes a file only the owner can read.
+pub(crate) fn write_private(path: &Path, content: &[u8]) -> std::io::Result<()> {
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    std::os::unix::fs::OpenOptionsExt::mode(&mut options, 0o600);
+
+    std::io::Write::write_all(&mut options.open(path)?, content)
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_load_or_create() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("lib-tls-identity-{}", std::process::id()));
+        let (cert, key) = (dir.join("cert.pem"), dir.join("key.pem"));
+
+        let created = Identity::load_or_create(&cert, &key, vec!["device".to_string()])?;
+        let loaded = Identity::load_or_create(&cert, &key, vec!["other".to_string()])?;
+        assert_eq!(created.fingerprint(), loaded.fingerprint());
+        assert_eq!(created.certs.len(), 1);
+
+        fs::write(&cert, "")?;
+        assert!(matches!(
+            Identity::load(&cert, &key),
+            Err(Error::NoCertificate(_))
+        ));
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,18 @@
+//! Device identities and mutual TLS for the QUIC endpoints: certificates,
+//! fingerprints, pairing codes and the store of trusted devices.
+
+// region:    --- Modules
+
+mod error;
+mod fingerprint;
+mod identity;
+
+pub mod endpoint;
+pub mod pki;
+pub mod trust;
+
+pub use error::{Error, Result};
+pub use fingerprint::{pairing_code, Fingerprint};
+pub use identity::{load_certs, Identity};
+
+// endregion: --- Modules
@@ -0,0 +1,201 @@
+//! A private certificate authority issuing the server and client
+//! certificates, so no certificate has to be made by hand.
+
+use crate::{identity::write_private, Error, Identity, Result};
+use lib_quic::quinn::rustls::pki_types::CertificateDer;
+use rcgen::{
+    BasicConstraints, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa, Issuer, KeyPair,
+    KeyUsagePurpose,
+};
+use std::{fs, net::IpAddr, path::Path};
+
+/// What an issued certificate is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Usage {
+    Server,
+    Client,
+}
+
+pub struct CertificateAuthority {
+    issuer: Issuer<'static, KeyPair>,
+    cert: CertificateDer<'static>,
+    pem: String,
+}
+
+impl CertificateAuthority {
+    /// Writes a new authority named `name`.
+    pub fn create(name: &str, cert: &Path, key: &Path) -> Result<Self> {
+        let mut params = CertificateParams::new(Vec::new())?;
+        params.distinguished_name.push(DnType::CommonName, name);
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params.key_usages = vec![
+            KeyUsagePurpose::KeyCertSign,
+            KeyUsagePurpose::CrlSign,
+            KeyUsagePurpose::DigitalSignature,
+        ];
+
+        let signing_key = KeyPair::generate()?;
+        let certificate = params.self_signed(&signing_key)?;
+        create_parent(cert)?;
+        create_parent(key)?;
+        fs::write(cert, certificate.pem())?;
+        write_private(key, signing_key.serialize_pem().as_bytes())?;
+
+        Ok(Self {
+            cert: certificate.der().clone(),
+            pem: certificate.pem(),
+            issuer: Issuer::new(params, signing_key),
+        })
+    }
+
+    pub fn load(cert: &Path, key: &Path) -> Result<Self> {
+        let pem = fs::read_to_string(cert)?;
+        let signing_key = KeyPair::from_pem(&fs::read_to_string(key)?)?;
+        let issuer = Issuer::from_ca_cert_pem(&pem, signing_key)?;
+        let cert = crate::load_certs(cert)?.remove(0);
+
+        Ok(Self { issuer, cert, pem })
+    }
+
+    pub fn cert(&self) -> &CertificateDer<'static> {
+        &self.cert
+    }
+
+    /// Writes a certificate for `names`, followed by the authority one, and
+    /// its new key. `names` are the DNS names and IP addresses of a server,
+    /// the first one is also the common name.
+    pub fn issue(
+        &self,
+        usage: Usage,
+        names: &[String],
+        cert: &Path,
+        key: &Path,
+    ) -> Result<Identity> {
+        let subject_alt_names = match usage {
+            Usage::Server => names.to_vec(),
+            Usage::Client => Vec::new(),
+        };
+        let mut params = CertificateParams::new(subject_alt_names)?;
+        if let Some(name) = names.first() {
+            params
+                .distinguished_name
+                .push(DnType::CommonName, name.as_str());
+        }
+        params.use_authority_key_identifier_extension = true;
+        params.key_usages = vec![KeyUsagePurpose::DigitalSignature];
+        params.extended_key_usages = vec![match usage {
+            Usage::Server => ExtendedKeyUsagePurpose::ServerAuth,
+            Usage::Client => ExtendedKeyUsagePurpose::ClientAuth,
+        }];
+
+        let signing_key = KeyPair::generate()?;
+        let certificate = params.signed_by(&signing_key, &self.issuer)?;
+        create_parent(cert)?;
+        create_parent(key)?;
+        fs::write(cert, certificate.pem() + &self.pem)?;
+        write_private(key, signing_key.serialize_pem().as_bytes())?;
+
+        Identity::load(cert, key)
+    }
+}
+
+/// Common name and subject alternative names of a certificate.
+pub fn names(cert: &CertificateDer<'_>) -> Result<Vec<String>> {
+    let (_, cert) = x509_parser::parse_x509_certificate(cert)
+        .map_err(|e| Error::CertificateUnreadable(e.to_string()))?;
+
+    let mut names: Vec<String> = cert
+        .subject()
+        .iter_common_name()
+        .filter_map(|name| name.as_str().ok())
+        .map(str::to_string)
+        .collect();
+
+    let alternative = cert
+        .subject_alternative_name()
+        .map_err(|e| Error::CertificateUnreadable(e.to_string()))?;
+    for name in alternative.iter().flat_map(|san| &san.value.general_names) {
+        let name = match name {
+            x509_parser::extensions::GeneralName::DNSName(name) => name.to_string(),
+            x509_parser::extensions::GeneralName::IPAddress(&[a, b, c, d]) => {
+                IpAddr::from([a, b, c, d]).to_string()
+            }
+            x509_parser::extensions::GeneralName::IPAddress(bytes) => {
+                match <[u8; 16]>::try_from(*bytes) {
+                    Ok(bytes) => IpAddr::from(bytes).to_string(),
+                    Err(_) => continue,
+                }
+            }
+            _ => continue,
+        };
+        if !names.contains(&name) {
+            names.push(name);
+        }
+    }
+
+    Ok(names)
+}
+
+fn create_parent(path: &Path) -> std::io::Result<()> {
+    match path.parent() {
+        Some(dir) => fs::create_dir_all(dir),
+        None => Ok(()),
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+    use crate::endpoint;
+    use std::net::SocketAddr;
+
+    #[tokio::test]
+    async fn test_issue() -> Result<()> {
+        let dir = std::env::temp_dir().join(format!("lib-tls-pki-{}", std::process::id()));
+        let (ca_cert, ca_key) = (dir.join("ca.pem"), dir.join("ca-key.pem"));
+
+        CertificateAuthority::create("air-link test", &ca_cert, &ca_key)?;
+        // Issues the same once loaded back.
+        let ca = CertificateAuthority::load(&ca_cert, &ca_key)?;
+        let server_names = ["desktop.lan".to_string(), "127.0.0.1".to_string()];
+        let server = ca.issue(
+            Usage::Server,
+            &server_names,
+            &dir.join("cert.pem"),
+            &dir.join("key.pem"),
+        )?;
+        let client = ca.issue(
+            Usage::Client,
+            &["laptop".to_string()],
+            &dir.join("client-cert.pem"),
+            &dir.join("client-key.pem"),
+        )?;
+
+        assert_eq!(server.certs.len(), 2);
+        assert_eq!(names(&server.certs[0])?, server_names);
+        assert_eq!(names(&client.certs[0])?, ["laptop"]);
+        assert_eq!(names(ca.cert())?, ["air-link test"]);
+
+        // The client trusts the authority and checks the server name.
+        let server = endpoint::server(SocketAddr::from(([127, 0, 0, 1], 0)), &server)?;
+        let address = server.local_addr()?;
+        let client = endpoint::client(vec![ca.cert().clone()], &client)?;
+        for (name, trusted) in [("desktop.lan", true), ("other.lan", false)] {
+            let (_, connection) = tokio::join!(
+                async { server.accept().await?.await.ok() },
+                client.connect(address, name)?
+            );
+            assert_eq!(connection.is_ok(), trusted, "{name}");
+        }
+
+        fs::remove_dir_all(dir)?;
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests
@@ -0,0 +1,222 @@
+//! Devices paired with the server, one per line:
+//!
+//! ```text
+//! # status  fingerprint                          name
+//! trusted   3A:F1:...:07                          laptop
+//! revoked   C4:09:...:E2                          old-desktop
+//! ```
+
+use crate::{Error, Fingerprint, Result};
+use std::{fmt::Write, fs, path::Path};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeviceStatus {
+    Trusted,
+    /// Paired once, rejected from now on.
+    Revoked,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Device {
+    pub fingerprint: Fingerprint,
+    pub status: DeviceStatus,
+    pub name: String,
+}
+
+#[derive(Debug, Default)]
+pub struct TrustStore {
+    devices: Vec<Device>,
+}
+
+impl TrustStore {
+    /// A missing file is an empty store.
+    pub fn load(path: &Path) -> Result<Self> {
+        match fs::read_to_string(path) {
+            Ok(text) => Self::parse(&text),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e.into()),
+        }
+    }
+
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut devices = Vec::new();
+        for (index, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let wrong_format = || Error::TrustStoreWrongFormat {
+                line: index + 1,
+                content: line.to_string(),
+            };
+            let mut fields = line.split_whitespace();
+            let status = match fields.next() {
+                Some("trusted") => DeviceStatus::Trusted,
+                Some("revoked") => DeviceStatus::Revoked,
+                _ => return Err(wrong_format()),
+            };
+            let fingerprint = fields
+                .next()
+                .ok_or_else(wrong_format)?
+                .parse()
+                .map_err(|_| wrong_format())?;
+            // The rest of the line, names may have spaces.
+            let name = fields.collect::<Vec<_>>().join(" ");
+
+            devices.push(Device {
+                fingerprint,
+                status,
+                name,
+            });
+        }
+
+        Ok(Self { devices })
+    }
+
+    /// Writes the store through a temporary file, so a crash leaves the
+    /// previous one.
+    pub fn save(&self, path: &Path) -> Result<()> {
+        if let Some(dir) = path.parent() {
+            fs::create_dir_all(dir)?;
+        }
+        let temporary = path.with_extension("tmp");
+        fs::write(&temporary, self.to_text())?;
+        fs::rename(temporary, path)?;
+
+        Ok(())
+    }
+
+    pub fn to_text(&self) -> String {
+        let mut text = String::from("# status  fingerprint  name\n");
+        for device in &self.devices {
+            let status = match device.status {
+                DeviceStatus::Trusted => "trusted",
+                DeviceStatus::Revoked => "revoked",
+            };
+            let _ = writeln!(text, "{status} {} {}", device.fingerprint, device.name);
+        }
+        text
+    }
+
+    pub fn status(&self, fingerprint: &Fingerprint) -> Option<DeviceStatus> {
+        self.get(fingerprint).map(|device| device.status)
+    }
+
+    pub fn get(&self, fingerprint: &Fingerprint) -> Option<&Device> {
+        self.devices
+            .iter()
+            .find(|device| device.fingerprint == *fingerprint)
+    }
+
+    pub fn devices(&self) -> &[Device] {
+        &self.devices
+    }
+
+    /// Trusts the device, again if it was revoked.
+    pub fn trust(&mut self, fingerprint: Fingerprint, name: &str) {
+        self.devices
+            .retain(|device| device.fingerprint != fingerprint);
+        self.devices.push(Device {
+            fingerprint,
+            status: DeviceStatus::Trusted,
+            name: name.to_string(),
+        });
+    }
+
+    /// Returns false for an unknown device.
+    pub fn revoke(&mut self, fingerprint: &Fingerprint) -> bool {
+        match self
+            .devices
+            .iter_mut()
+            .find(|device| device.fingerprint == *fingerprint)
+        {
+            Some(device) => {
+                device.status = DeviceStatus::Revoked;
+                true
+            }
+            None => false,
+        }
+    }
+}
+
+// region:    --- Tests
+
+#[cfg(test)]
+mod tests {
+    type Result<T> = core::result::Result<T, Box<dyn std::error::Error>>; // For tests.
+
+    use super::*;
+
+    #[test]
+    fn test_trust_and_revoke() -> Result<()> {
+        let laptop = Fingerprint::of(b"laptop");
+        let desktop = Fingerprint::of(b"desktop");
+
+        let mut store = TrustStore::default();
+        store.trust(laptop, "my laptop");
+        store.trust(desktop, "desktop");
+        assert!(store.revoke(&desktop));
+        assert!(!store.revoke(&Fingerprint::of(b"unknown")));
+
+        let store = TrustStore::parse(&store.to_text())?;
+        assert_eq!(store.status(&laptop), Some(DeviceStatus::Trusted));
+        assert_eq!(store.status(&desktop), Some(DeviceStatus::Revoked));
+        assert_eq!(
+            store.get(&laptop).map(|d| d.name.as_str()),
+            Some("my laptop")
+        );
+        assert_eq!(store.status(&Fingerprint::of(b"unknown")), None);
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_aligned() -> Result<()> {
+        let laptop = Fingerprint::of(b"laptop");
+        let desktop = Fingerprint::of(b"old-desktop");
+
+        // Laid out as in the module doc.
+        let text = format!(
+            "# status  fingerprint                          name\n\
+             trusted   {laptop}   my laptop\n\
+             revoked\t{desktop}\t\told-desktop  \n"
+        );
+        let store = TrustStore::parse(&text)?;
+
+        assert_eq!(
+            store.devices(),
+            [
+                Device {
+                    fingerprint: laptop,
+                    status: DeviceStatus::Trusted,
+                    name: "my laptop".to_string(),
+                },
+                Device {
+                    fingerprint: desktop,
+                    status: DeviceStatus::Revoked,
+                    name: "old-desktop".to_string(),
+                },
+            ]
+        );
+
+        Ok(())
+    }
+
+    #[test]
+    fn test_parse_errors() -> Result<()> {
+        assert!(TrustStore::parse("# empty\n\n")?.devices().is_empty());
+        assert!(matches!(
+            TrustStore::parse("\nallowed AB laptop"),
+            Err(Error::TrustStoreWrongFormat { line: 2, .. })
+        ));
+        assert!(matches!(
+            TrustStore::parse("trusted AB laptop"),
+            Err(Error::TrustStoreWrongFormat { line: 1, .. })
+        ));
+
+        Ok(())
+    }
+}
+
+// endregion: --- Tests